@@ -1,115 +1,357 @@
 // Additional advanced modules for Redox OS Console Dashboard
-use crate::system::SystemState;
+use crate::packages::{PackageGraph, PackageManager};
+use crate::system::Package;
+use crate::ui::{format_bytes, format_size_delta, App, DependencyTree, Prompt, TreeChildren};
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
     widgets::{
-        Block, Borders, List, ListItem, Paragraph, Table, Row, Cell, Gauge, Wrap,
+        Block, Borders, Gauge, List, ListItem, Paragraph, Table, TableState, Row, Cell, Wrap,
     },
     Frame,
 };
 use rand::Rng;
-
-pub fn draw_package_manager<B: Backend>(f: &mut Frame<B>, _system: &SystemState, area: Rect) {
+use std::time::{Duration, Instant};
+
+pub fn draw_package_manager<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let (system, manager) = (&app.system, &app.packages);
+    let packages = app.visible_packages();
+    let prompt = match &app.prompt {
+        Some(Prompt::PackageSearch(text)) => Some((text, "Search name and description [Enter=Done Esc=Clear]")),
+        Some(Prompt::PackageInstall(text)) => Some((text, "Install packages, separated by spaces [Enter=Queue Esc=Cancel]")),
+        _ => None,
+    };
+    let constraints = if prompt.is_some() {
+        vec![Constraint::Min(0), Constraint::Length(3), Constraint::Length(14)]
+    } else {
+        vec![Constraint::Min(0), Constraint::Length(14)]
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(12), Constraint::Min(0)].as_ref())
+        .constraints(constraints)
         .split(area);
 
-    // Package Status Table
-    let header_cells = ["Package", "Version", "Status", "Size", "Dependencies", "Update Available"]
+    // Installed packages
+    let header_cells = ["Op", "Package", "Version", "Size", "Update", "Dependencies"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let packages_data = vec![
-        ("redox-kernel", "0.8.5", "INSTALLED", "12.3MB", "3", "0.8.6"),
-        ("ion-shell", "1.0.5", "INSTALLED", "2.1MB", "5", "-"),
-        ("netstack", "0.3.2", "INSTALLED", "8.7MB", "12", "0.3.3"),
-        ("orbital", "0.5.1", "INSTALLED", "15.2MB", "8", "-"),
-        ("pkg-manager", "0.4.8", "INSTALLED", "1.8MB", "2", "0.4.9"),
-        ("rust-std", "1.75.0", "INSTALLED", "45.1MB", "0", "1.76.0"),
-    ];
-
-    let rows = packages_data.iter().map(|(name, version, status, size, deps, update)| {
-        let status_color = if *status == "INSTALLED" { Color::Green } else { Color::Red };
-        let update_color = if *update == "-" { Color::Green } else { Color::Red };
-        
+    let rows = packages.iter().map(|package| {
+        let (op, op_color) = match manager.queued(&package.name) {
+            Some("upgrade") => ("↑", Color::Yellow),
+            Some("remove") => ("-", Color::Red),
+            Some(_) => ("+", Color::Green),
+            None => (" ", Color::Green),
+        };
         let cells = vec![
-            Cell::from(*name).style(Style::default().fg(Color::Green)),
-            Cell::from(*version).style(Style::default().fg(Color::Green)),
-            Cell::from(*status).style(Style::default().fg(status_color)),
-            Cell::from(*size).style(Style::default().fg(Color::Green)),
-            Cell::from(*deps).style(Style::default().fg(Color::Green)),
-            Cell::from(*update).style(Style::default().fg(update_color)),
+            Cell::from(op).style(Style::default().fg(op_color).add_modifier(Modifier::BOLD)),
+            Cell::from(package.name.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(package.version.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(package.size_bytes.map(format_bytes).unwrap_or_else(|| "-".to_string()))
+                .style(Style::default().fg(Color::Green)),
+            match &package.update {
+                Some(version) => Cell::from(version.clone()).style(Style::default().fg(Color::Yellow)),
+                None => Cell::from("-").style(Style::default().fg(Color::Green)),
+            },
+            Cell::from(format!("({}) {}", package.depends.len(), package.depends.join(", ")))
+                .style(Style::default().fg(Color::Green)),
         ];
         Row::new(cells).height(1)
     });
 
+    let title = if app.package_search.is_empty() {
+        format!(
+            "Package Manager ({}{}, {} installed) [↑/↓/PgUp/PgDn=Select Enter=Dependencies /=Search]",
+            manager.backend_name(),
+            if manager.is_loading() { ", loading" } else { "" },
+            system.packages.len()
+        )
+    } else {
+        format!(
+            "Package Manager ({}, {} of {} matching \"{}\") [Enter=Dependencies /=Search l=List all]",
+            manager.backend_name(),
+            packages.len(),
+            system.packages.len(),
+            app.package_search
+        )
+    };
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Package Manager (pkg)").style(Style::default().fg(Color::Green)))
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
         .widths(&[
-            Constraint::Length(15),
-            Constraint::Length(8),
+            Constraint::Length(2),
+            Constraint::Length(28),
+            Constraint::Length(24),
             Constraint::Length(10),
-            Constraint::Length(8),
-            Constraint::Length(12),
-            Constraint::Length(15),
+            Constraint::Length(24),
+            Constraint::Length(70),
         ]);
 
-    f.render_widget(table, chunks[0]);
-
-    // Package Actions and Repository Status
+    let mut state = TableState::default();
+    state.select(Some(app.selected_package.min(packages.len().saturating_sub(1))));
+    f.render_stateful_widget(table, chunks[0], &mut state);
+
+    if let Some((text, title)) = prompt {
+        let input = Paragraph::new(format!("{}_", text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(input, chunks[1]);
+    }
+
+    // Operations queue with its dry-run plan, command output and repository status
     let package_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(chunks[1]);
-
-    let package_actions = vec![
-        "[i] Install Package",
-        "[u] Update Package",
-        "[r] Remove Package", 
-        "[s] Search Packages",
-        "[l] List All Packages",
-        "[c] Clean Cache",
-        "[d] Show Dependencies",
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(40), Constraint::Percentage(25)].as_ref())
+        .split(chunks[chunks.len() - 1]);
+    draw_package_operations(f, manager, package_chunks[0]);
+    draw_package_output(f, manager, package_chunks[1]);
+
+    let updates = system.packages.iter().filter(|package| package.update.is_some()).count();
+    let installed_size: u64 = system.packages.iter().filter_map(|package| package.size_bytes).sum();
+    let loaded = match manager.loaded_at {
+        Some(at) => format!("{}s ago", at.elapsed().as_secs()),
+        None if manager.is_loading() => "loading".to_string(),
+        None => "never".to_string(),
+    };
+    let mut lines = vec![
+        Spans::from(format!("Backend: {}", manager.backend_name())),
+        Spans::from(format!("Installed: {} ({})", system.packages.len(), format_bytes(installed_size))),
+        Spans::from(format!("Index: {} packages, {} updates", manager.index.len(), updates)),
+        Spans::from(format!("Last Read: {}", loaded)),
+        Spans::from(""),
     ];
+    let repos = &app.repos;
+    if repos.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "No repositories configured",
+            Style::default().fg(Color::Yellow),
+        )));
+    } else if repos.health.is_empty() {
+        lines.push(Spans::from("Checking repositories..."));
+    }
+    for repo in &repos.health {
+        let color = match repo.status.as_str() {
+            "ONLINE" => Color::Green,
+            "STALE" | "NO SIGNATURE" => Color::Yellow,
+            _ => Color::Red,
+        };
+        lines.push(Spans::from(vec![
+            Span::styled(format!("{} ", repo.name), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(repo.status.clone(), Style::default().fg(color)),
+        ]));
+        let age = match repo.age_hours {
+            Some(age) if age >= 48.0 => format!("{:.0}d", age / 24.0),
+            Some(age) => format!("{:.0}h", age),
+            None => "?".to_string(),
+        };
+        let signature = match repo.signature_present {
+            Some(true) => "signature present",
+            Some(false) => "no signature",
+            None => "signature ?",
+        };
+        let cache = repo.cache_bytes.map_or("no cache".to_string(), |bytes| format!("cache {}", format_bytes(bytes)));
+        let detail = match &repo.error {
+            Some(err) if repo.status == "UNREACHABLE" || repo.status == "NO INDEX" => err.clone(),
+            _ => format!("index {} old, {}, {}", age, signature, cache),
+        };
+        lines.push(Spans::from(Span::styled(format!("  {}", detail), Style::default().fg(Color::Gray))));
+    }
+    if let Some(at) = repos.checked_at {
+        let state = if repos.is_checking() { "checking" } else { "auto 5m" };
+        lines.push(Spans::from(format!("Checked {}s ago ({})", at.elapsed().as_secs(), state)));
+    }
+
+    let repo_para = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Repository Status").style(Style::default().fg(Color::Green)))
+        .style(Style::default().fg(Color::Green))
+        .wrap(Wrap { trim: false });
 
-    let action_items: Vec<ListItem> = package_actions
-        .iter()
-        .map(|action| {
-            ListItem::new(vec![Spans::from(Span::styled(
-                *action,
-                Style::default().fg(Color::Red),
-            ))])
-        })
-        .collect();
+    f.render_widget(repo_para, package_chunks[2]);
+}
 
-    let actions_list = List::new(action_items)
-        .block(Block::default().borders(Borders::ALL).title("Package Actions").style(Style::default().fg(Color::Green)));
+fn draw_package_operations<B: Backend>(f: &mut Frame<B>, manager: &PackageManager, area: Rect) {
+    let plan = &manager.plan;
+    let names = |packages: &[Package]| packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ");
+    let line = |label: &str, text: String, color: Color| {
+        Spans::from(vec![
+            Span::styled(format!("{:<9}", label), Style::default().fg(Color::Yellow)),
+            Span::styled(text, Style::default().fg(color)),
+        ])
+    };
+
+    let mut lines = Vec::new();
+    if manager.queue.is_empty() {
+        lines.push(Spans::from(Span::styled("Queue is empty", Style::default().fg(Color::Green))));
+    } else {
+        let queued: Vec<String> = manager
+            .queue
+            .iter()
+            .map(|op| match op.action {
+                "install" => format!("+{}", op.package),
+                "upgrade" => format!("↑{}", op.package),
+                "remove" => format!("-{}", op.package),
+                _ => "clean cache".to_string(),
+            })
+            .collect();
+        lines.push(line("Queue", queued.join(" "), Color::Green));
+        lines.push(Spans::from(""));
+        if !plan.install.is_empty() {
+            lines.push(line("Install", names(&plan.install), Color::Green));
+        }
+        if !plan.upgrade.is_empty() {
+            let upgrades: Vec<String> =
+                plan.upgrade.iter().map(|u| format!("{} {} → {}", u.name, u.from, u.to)).collect();
+            lines.push(line("Upgrade", upgrades.join(", "), Color::Yellow));
+        }
+        if !plan.remove.is_empty() {
+            lines.push(line("Remove", names(&plan.remove), Color::Red));
+        }
+        if !plan.missing.is_empty() {
+            lines.push(line("Missing", plan.missing.join(", "), Color::Red));
+        }
+        if !plan.conflicts.is_empty() {
+            lines.push(line("Conflict", format!("{} removed but needed", plan.conflicts.join(", ")), Color::Red));
+        }
+        lines.push(line("Size", format_size_delta(plan.size_delta), Color::Green));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        format!("[i]nstall [u]pgrade [r]emove [c]lean [a]pply ({}) [x] Clear", manager.mode().name()),
+        Style::default().fg(Color::Red),
+    )));
+
+    let para = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Operations (dry-run plan)").style(Style::default().fg(Color::Green)))
+        .wrap(Wrap { trim: true });
+    f.render_widget(para, area);
+}
 
-    f.render_widget(actions_list, package_chunks[0]);
+fn draw_package_output<B: Backend>(f: &mut Frame<B>, manager: &PackageManager, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Output").style(Style::default().fg(Color::Green));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let (progress, output) = match &manager.run {
+        Some(_) => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                .split(inner);
+            (Some(split[0]), split[1])
+        }
+        None => (None, inner),
+    };
+    if let (Some(run), Some(progress)) = (&manager.run, progress) {
+        let ratio = if run.steps == 0 { 0.0 } else { run.step.saturating_sub(1) as f64 / run.steps as f64 };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Yellow))
+            .ratio(ratio)
+            .label(format!("Step {}/{}: {}", run.step, run.steps, run.command));
+        f.render_widget(gauge, progress);
+    }
+
+    let visible = output.height as usize;
+    let lines: Vec<Spans> = manager.output[manager.output.len().saturating_sub(visible)..]
+        .iter()
+        .map(|line| Spans::from(Span::styled(line.clone(), Style::default().fg(Color::Green))))
+        .collect();
+    f.render_widget(Paragraph::new(lines), output);
+}
 
-    let mut rng = rand::thread_rng();
-    let repo_text = format!(
-        "Repository Status:\n\n• Official Repo: ONLINE\n• Community Repo: ONLINE\n• Local Cache: VALID\n\nTotal Packages: {}\nInstalled: {}\nUpdates Available: 3\nCache Size: {:.1} MB\n\nLast Update: 2025-08-21 10:30\nNext Check: Auto (6h)",
-        rng.gen_range(850..1200),
-        packages_data.len(),
-        rng.gen_range(45.0..85.0)
-    );
+pub fn draw_package_detail<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let packages = app.visible_packages();
+    let package = match packages.get(app.selected_package.min(packages.len().saturating_sub(1))) {
+        Some(package) => *package,
+        None => return,
+    };
+    let graph = PackageGraph::new(&app.system.packages);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .split(area);
 
-    let repo_para = Paragraph::new(repo_text)
-        .block(Block::default().borders(Borders::ALL).title("Repository Status").style(Style::default().fg(Color::Green)))
-        .style(Style::default().fg(Color::Green))
+    // Summary with the installed dependency closure and any cycles through it
+    let closure = graph.closure(&package.name);
+    let closure_size: u64 = closure.iter().filter_map(|package| package.size_bytes).sum();
+    let cycles = graph.cycles(&package.name);
+    let cycle_text = if cycles.is_empty() {
+        "none".to_string()
+    } else {
+        cycles
+            .iter()
+            .map(|cycle| format!("[{}]", cycle.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    let summary = vec![
+        Spans::from(vec![
+            Span::styled(format!("{} {}", package.name, package.version), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                package.update.as_ref().map(|version| format!("  (update: {})", version)).unwrap_or_default(),
+                Style::default().fg(Color::Yellow),
+            ),
+        ]),
+        Spans::from(Span::styled(package.description.clone(), Style::default().fg(Color::Green))),
+        Spans::from(Span::styled(
+            format!(
+                "Depends on {} directly; closure of {} installed packages totalling {}",
+                package.depends.len(),
+                closure.len().saturating_sub(1),
+                format_bytes(closure_size)
+            ),
+            Style::default().fg(Color::Green),
+        )),
+        Spans::from(Span::styled(
+            format!("Required directly by {} installed packages", graph.dependents(&package.name).len()),
+            Style::default().fg(Color::Green),
+        )),
+        Spans::from(vec![
+            Span::styled("Cycles: ", Style::default().fg(Color::Green)),
+            Span::styled(cycle_text, Style::default().fg(if cycles.is_empty() { Color::Green } else { Color::Red })),
+        ]),
+    ];
+    let summary = Paragraph::new(summary)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Package {} [↑/↓=Previous/Next Enter/Esc=Back]", package.name))
+                .style(Style::default().fg(Color::Green)),
+        )
         .wrap(Wrap { trim: true });
+    f.render_widget(summary, chunks[0]);
 
-    f.render_widget(repo_para, package_chunks[1]);
+    let tree_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[1]);
+    let label = |name: &str| match graph.get(name) {
+        Some(package) => match &package.update {
+            Some(update) => (format!("{} → {}", package.version, update), Color::Yellow),
+            None => (package.version.clone(), Color::Green),
+        },
+        None => ("not installed".to_string(), Color::DarkGray),
+    };
+    let dependencies = |name: &str| graph.get(name).map(|package| package.depends.clone()).unwrap_or_default();
+    let dependents = |name: &str| graph.dependents(name).iter().map(|name| name.to_string()).collect();
+    let trees: [(TreeChildren, &str); 2] = [(&dependencies, "Dependencies"), (&dependents, "Reverse Dependencies")];
+    for ((children, title), chunk) in trees.into_iter().zip(tree_chunks) {
+        let lines = DependencyTree { children, label: &label }.lines(&package.name);
+        let tree = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)));
+        f.render_widget(tree, chunk);
+    }
 }
 
-pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState, area: Rect) {
+pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(8), Constraint::Length(8), Constraint::Min(0)].as_ref())
@@ -121,7 +363,7 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
         .split(chunks[0]);
 
-    let dev_tools = vec![
+    let dev_tools = [
         "[g] Attach GDB Debugger",
         "[r] Run Unit Tests",
         "[b] Build Project",
@@ -146,7 +388,7 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
     f.render_widget(tools_list, dev_chunks[0]);
 
     let mut rng = rand::thread_rng();
-    let debug_sessions = vec![
+    let debug_sessions = [
         format!("GDB Session #1 - PID {} (ion)", rng.gen_range(100..999)),
         format!("LLDB Session #2 - PID {} (editor)", rng.gen_range(100..999)),
         "Valgrind - Memory analysis running".to_string(),
@@ -174,7 +416,7 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
         .split(chunks[1]);
 
-    let test_results = vec![
+    let test_results = [
         "✓ kernel/scheduler: 24/24 passed",
         "✓ fs/redoxfs: 18/18 passed",
         "✗ network/tcp: 12/15 passed (3 failed)",
@@ -185,8 +427,7 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
     let test_items: Vec<ListItem> = test_results
         .iter()
         .map(|result| {
-            let color = if result.contains("✓") { Color::Green } 
-                       else if result.contains("✗") { Color::Red }
+            let color = if result.contains("✓") { Color::Green }
                        else { Color::Red };
             
             ListItem::new(vec![Spans::from(Span::styled(
@@ -201,12 +442,12 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
 
     f.render_widget(test_list, test_chunks[0]);
 
-    let build_info = vec![
-        format!("Build Status: SUCCESS"),
+    let build_info = [
+        "Build Status: SUCCESS".to_string(),
         format!("Build Time: {:.1}s", rng.gen_range(15.0..45.0)),
         format!("Warnings: {}", rng.gen_range(2..12)),
         format!("Binary Size: {:.1} MB", rng.gen_range(8.0..25.0)),
-        format!("Debug Symbols: ENABLED"),
+        "Debug Symbols: ENABLED".to_string(),
     ];
 
     let build_items: Vec<ListItem> = build_info
@@ -224,119 +465,213 @@ pub fn draw_developer_tools<B: Backend>(f: &mut Frame<B>, _system: &SystemState,
 
     f.render_widget(build_list, test_chunks[1]);
 
-    // Code Analysis
-    let analysis_text = format!(
-        "Code Analysis Dashboard:\n\n🔧 Static Analysis:\n• Clippy warnings: {}\n• Unsafe blocks: {}\n• TODO comments: {}\n• Code coverage: {:.1}%\n\n🚀 Performance:\n• Hot paths identified: {}\n• Memory leaks: 0\n• Deadlock potential: LOW\n\n📊 Metrics:\n• Lines of code: {}\n• Cyclomatic complexity: {:.1}\n• Technical debt: {:.1}h",
-        rng.gen_range(5..25),
-        rng.gen_range(2..8),
-        rng.gen_range(15..45),
-        rng.gen_range(75.0..95.0),
-        rng.gen_range(3..12),
-        rng.gen_range(25000..85000),
-        rng.gen_range(2.1..5.8),
-        rng.gen_range(8.0..24.0)
-    );
+    draw_collector_diagnostics(f, app, chunks[2]);
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn age(at: Option<Instant>) -> String {
+    at.map_or("-".to_string(), |at| format!("{:.1}s", at.elapsed().as_secs_f64()))
+}
+
+// Per-collector timing of the collection pipeline and the command probes
+fn draw_collector_diagnostics<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let header_cells = ["Collector", "Every", "Status", "Runs", "Last", "Avg", "Max", "Age", "Skipped", "Errors", "Last error"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let analysis_para = Paragraph::new(analysis_text)
-        .block(Block::default().borders(Borders::ALL).title("Code Analysis").style(Style::default().fg(Color::Green)))
+    let state_color = |state: &str| match state {
+        "OK" => Color::Green,
+        "PAUSED" | "WAITING" => Color::Yellow,
+        _ => Color::Red,
+    };
+    let collectors = app.pipeline.status.iter().map(|status| {
+        let state = status.state();
+        Row::new(vec![
+            Cell::from(status.collector.name()),
+            Cell::from(format!("{:?}", status.interval)),
+            Cell::from(state).style(Style::default().fg(state_color(state))),
+            Cell::from(status.runs.to_string()),
+            Cell::from(millis(status.last_duration)),
+            Cell::from(millis(status.average())),
+            Cell::from(millis(status.max_duration)),
+            Cell::from(age(status.last_sample)),
+            Cell::from(status.superseded.to_string()),
+            Cell::from(status.errors.to_string()),
+            Cell::from(status.last_error.clone().unwrap_or_default()).style(Style::default().fg(Color::Red)),
+        ])
         .style(Style::default().fg(Color::Green))
-        .wrap(Wrap { trim: true });
+    });
+    let probes = app.probes.status.iter().map(|probe| {
+        let state = match (&probe.last_error, probe.runs) {
+            (Some(_), _) => "FAILING",
+            (None, 0) => "WAITING",
+            (None, _) => "OK",
+        };
+        Row::new(vec![
+            Cell::from(format!("probe.{}", probe.name)),
+            Cell::from(format!("{:?}", probe.interval)),
+            Cell::from(state).style(Style::default().fg(state_color(state))),
+            Cell::from(probe.runs.to_string()),
+            Cell::from(millis(probe.last_duration)),
+            Cell::from("-"),
+            Cell::from("-"),
+            Cell::from(age(probe.last_run)),
+            Cell::from("-"),
+            Cell::from(probe.failures.to_string()),
+            Cell::from(probe.last_error.clone().unwrap_or_default()).style(Style::default().fg(Color::Red)),
+        ])
+        .style(Style::default().fg(Color::Green))
+    });
+
+    let title = format!("Collection Pipeline (UI thread applied last samples in {})", millis(app.pipeline.apply_time));
+    let table = Table::new(collectors.chain(probes).collect::<Vec<_>>())
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .widths(&[
+            Constraint::Length(20),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Min(10),
+        ]);
 
-    f.render_widget(analysis_para, chunks[2]);
+    f.render_widget(table, area);
 }
 
-pub fn draw_plugin_system<B: Backend>(f: &mut Frame<B>, _system: &SystemState, area: Rect) {
+pub fn draw_plugin_system<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let plugins = &app.plugins.plugins;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(10), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Min(0), Constraint::Length(14)].as_ref())
         .split(area);
 
     // Plugin Status Table
-    let header_cells = ["Plugin", "Version", "Status", "Type", "Memory", "Hooks"]
+    let header_cells = ["Plugin", "Version", "Type", "Status", "Tabs", "Rules", "Cmds", "Calls", "Last", "CPU", "Memory", "Errors"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let plugins_data = vec![
-        ("metrics-exporter", "1.2.0", "ACTIVE", "Native", "2.1MB", "4"),
-        ("wasm-runner", "0.8.5", "ACTIVE", "WASM", "1.8MB", "2"),
-        ("log-aggregator", "2.1.1", "ACTIVE", "Native", "3.2MB", "6"),
-        ("network-monitor", "1.0.3", "PAUSED", "WASM", "0.9MB", "3"),
-        ("custom-dashboard", "0.5.2", "ACTIVE", "JSON", "0.5MB", "1"),
-    ];
-
-    let rows = plugins_data.iter().map(|(name, version, status, ptype, memory, hooks)| {
-        let status_color = match *status {
-            "ACTIVE" => Color::Green,
-            "PAUSED" => Color::Red,
+    let rows = plugins.iter().map(|slot| {
+        let status_color = match slot.state.as_str() {
+            "ENABLED" => Color::Green,
+            "DISABLED" | "UNLOADED" => Color::Yellow,
             _ => Color::Red,
         };
-        
+        let stats = &slot.stats;
         let cells = vec![
-            Cell::from(*name).style(Style::default().fg(Color::Green)),
-            Cell::from(*version).style(Style::default().fg(Color::Green)),
-            Cell::from(*status).style(Style::default().fg(status_color)),
-            Cell::from(*ptype).style(Style::default().fg(Color::Green)),
-            Cell::from(*memory).style(Style::default().fg(Color::Green)),
-            Cell::from(*hooks).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.name.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.version.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.kind).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.state.clone()).style(Style::default().fg(status_color)),
+            Cell::from(slot.tabs().len().to_string()).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.rules().len().to_string()).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.commands().len().to_string()).style(Style::default().fg(Color::Green)),
+            Cell::from(stats.collect_calls.to_string()).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.2}ms", stats.last_collect.as_secs_f64() * 1000.0)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.2}%", stats.cpu_percent())).style(Style::default().fg(Color::Green)),
+            Cell::from(slot.memory_bytes().map(format_bytes).unwrap_or_else(|| "-".to_string()))
+                .style(Style::default().fg(Color::Green)),
+            Cell::from(stats.errors.to_string())
+                .style(Style::default().fg(if stats.errors > 0 { Color::Red } else { Color::Green })),
         ];
         Row::new(cells).height(1)
     });
 
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Plugin System Manager").style(Style::default().fg(Color::Green)))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Plugin System Manager [↑/↓=Select e=Enable/Disable l=Load u=Unload :=Command]")
+                .style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
         .widths(&[
             Constraint::Length(18),
             Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(5),
+            Constraint::Length(6),
+            Constraint::Length(5),
+            Constraint::Length(7),
             Constraint::Length(8),
-            Constraint::Length(8),
-            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Length(9),
             Constraint::Length(6),
         ]);
 
-    f.render_widget(table, chunks[0]);
+    let mut state = TableState::default();
+    state.select(Some(app.selected_plugin.min(plugins.len().saturating_sub(1))));
+    f.render_stateful_widget(table, chunks[0], &mut state);
 
-    // Plugin Management and Registry
+    // Selected plugin and registry totals
     let plugin_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
         .split(chunks[1]);
 
-    let plugin_actions = vec![
-        "[l] Load Plugin",
-        "[u] Unload Plugin",
-        "[r] Reload Plugin", 
-        "[c] Configure Plugin",
-        "[e] Enable/Disable Plugin",
-        "[d] Debug Plugin",
-        "[i] Install from Registry",
-    ];
-
-    let action_items: Vec<ListItem> = plugin_actions
-        .iter()
-        .map(|action| {
-            ListItem::new(vec![Spans::from(Span::styled(
-                *action,
-                Style::default().fg(Color::Red),
-            ))])
-        })
-        .collect();
-
-    let actions_list = List::new(action_items)
-        .block(Block::default().borders(Borders::ALL).title("Plugin Actions").style(Style::default().fg(Color::Green)));
-
-    f.render_widget(actions_list, plugin_chunks[0]);
+    let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().fg(Color::Yellow));
+    let list = |items: &[String]| if items.is_empty() { "-".to_string() } else { items.join(", ") };
+    let mut lines = Vec::new();
+    if let Some(slot) = plugins.get(app.selected_plugin) {
+        lines.push(Spans::from(Span::styled(slot.description.clone(), Style::default().fg(Color::Green))));
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(vec![label("Tabs"), Span::raw(list(&slot.tabs()))]));
+        lines.push(Spans::from(vec![label("Rules"), Span::raw(list(slot.rules()))]));
+        for command in slot.commands() {
+            lines.push(Spans::from(vec![
+                label("Command"),
+                Span::styled(command.usage, Style::default().fg(Color::Cyan)),
+                Span::raw(format!("  {}", command.description)),
+            ]));
+        }
+        lines.push(Spans::from(vec![
+            label("Activity"),
+            Span::raw(format!(
+                "{} renders, {} commands, {:.1}ms busy",
+                slot.stats.renders,
+                slot.stats.commands,
+                slot.stats.busy.as_secs_f64() * 1000.0
+            )),
+        ]));
+        if let Some(err) = &slot.last_error {
+            lines.push(Spans::from(vec![label("Error"), Span::styled(err.clone(), Style::default().fg(Color::Red))]));
+        }
+    }
+    let title = plugins.get(app.selected_plugin).map_or("Plugin".to_string(), |slot| format!("Plugin: {}", slot.name));
+    let detail = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .style(Style::default().fg(Color::Green))
+        .wrap(Wrap { trim: false });
+    f.render_widget(detail, plugin_chunks[0]);
 
-    let mut rng = rand::thread_rng();
+    let count = |state: &str| plugins.iter().filter(|slot| slot.state == state).count();
+    let enabled: Vec<_> = plugins.iter().filter(|slot| slot.is_enabled()).collect();
+    let memory: u64 = plugins.iter().filter_map(|slot| slot.memory_bytes()).sum();
     let registry_text = format!(
-        "Plugin Registry & System:\n\n📦 Registry Status:\n• Official plugins: {}\n• Community plugins: {}\n• Local plugins: {}\n\n🔧 System Features:\n• Hot reloading: ENABLED\n• Sandboxing: ENABLED\n• WASM support: ENABLED\n• JSON configs: ENABLED\n\n📊 Resource Usage:\n• Total memory: {:.1} MB\n• CPU overhead: {:.1}%\n• Active hooks: {}\n\n🚀 Experimental:\n• Quantum scheduler plugin\n• IPC graph visualizer\n• Syscall replay engine",
-        rng.gen_range(15..35),
-        rng.gen_range(45..85),
-        plugins_data.len(),
-        rng.gen_range(8.0..16.0),
-        rng.gen_range(2.0..8.0),
-        plugins_data.iter().map(|p| p.5.parse::<i32>().unwrap_or(0)).sum::<i32>()
+        "Registered: {}\nEnabled: {}  Disabled: {}\nUnloaded: {}  Failed: {}\n\nCollectors: {}\nPlugin tabs: {}\nAlert rules: {}\nCommands: {}\n\nCPU overhead: {:.2}%\nReported memory: {}",
+        plugins.len(),
+        count("ENABLED"),
+        count("DISABLED"),
+        count("UNLOADED"),
+        count("FAILED"),
+        enabled.len(),
+        app.plugins.tabs().len(),
+        enabled.iter().map(|slot| slot.rules().len()).sum::<usize>(),
+        enabled.iter().map(|slot| slot.commands().len()).sum::<usize>(),
+        enabled.iter().map(|slot| slot.stats.cpu_percent()).sum::<f64>(),
+        format_bytes(memory)
     );
 
     let registry_para = Paragraph::new(registry_text)
@@ -345,4 +680,4 @@ pub fn draw_plugin_system<B: Backend>(f: &mut Frame<B>, _system: &SystemState, a
         .wrap(Wrap { trim: true });
 
     f.render_widget(registry_para, plugin_chunks[1]);
-}
\ No newline at end of file
+}
@@ -0,0 +1,530 @@
+// Alert rules and notification sinks
+use crate::http;
+use crate::shell;
+use crate::system::SystemState;
+use chrono::{DateTime, Local};
+use crossterm::{execute, terminal::SetTitle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ALERT_HISTORY_LIMIT: usize = 100;
+const TITLE_FLASH: Duration = Duration::from_secs(5);
+const CONSOLE_TITLE: &str = "Redox OS Console";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    pub rules: Vec<AlertRule>,
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            // No default memory rule: memory figures are still simulated
+            rules: vec![AlertRule {
+                name: "high-cpu".to_string(),
+                metric: "cpu.usage".to_string(),
+                above: Some(90.0),
+                below: None,
+                severity: "WARN".to_string(),
+                message: None,
+            }],
+            sinks: vec![SinkConfig::Bell { flash_title: true }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: String,
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+fn default_severity() -> String {
+    "WARN".to_string()
+}
+
+impl AlertRule {
+    // Returns the crossed threshold when the rule is violated
+    fn check(&self, value: f64) -> Option<f64> {
+        match (self.above, self.below) {
+            (Some(limit), _) if value > limit => Some(limit),
+            (_, Some(limit)) if value < limit => Some(limit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    Command {
+        cmd: String,
+        #[serde(default = "default_command_timeout")]
+        timeout_secs: u64,
+    },
+    Webhook {
+        url: String,
+        #[serde(default = "default_webhook_timeout")]
+        timeout_secs: u64,
+    },
+    File {
+        path: PathBuf,
+    },
+    Bell {
+        #[serde(default)]
+        flash_title: bool,
+    },
+}
+
+fn default_command_timeout() -> u64 {
+    10
+}
+
+fn default_webhook_timeout() -> u64 {
+    5
+}
+
+impl SinkConfig {
+    pub fn describe(&self) -> String {
+        match self {
+            SinkConfig::Command { cmd, .. } => format!("command: {}", cmd),
+            SinkConfig::Webhook { url, .. } => format!("webhook: {}", url),
+            SinkConfig::File { path } => format!("file: {}", path.display()),
+            SinkConfig::Bell { flash_title: true } => "bell + title flash".to_string(),
+            SinkConfig::Bell { flash_title: false } => "bell".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub state: String,
+    pub severity: String,
+    pub metric: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub message: String,
+    pub timestamp: DateTime<Local>,
+}
+
+impl Alert {
+    fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ALERT_RULE", self.rule.clone()),
+            ("ALERT_STATE", self.state.clone()),
+            ("ALERT_SEVERITY", self.severity.clone()),
+            ("ALERT_METRIC", self.metric.clone()),
+            ("ALERT_VALUE", format!("{:.2}", self.value)),
+            ("ALERT_THRESHOLD", format!("{:.2}", self.threshold)),
+            ("ALERT_MESSAGE", self.message.clone()),
+            ("ALERT_TIMESTAMP", self.timestamp.to_rfc3339()),
+        ]
+    }
+}
+
+// Blocking deliveries handed to the sink worker thread
+enum Delivery {
+    Command {
+        index: usize,
+        cmd: String,
+        env: Vec<(&'static str, String)>,
+        timeout: Duration,
+    },
+    Webhook {
+        index: usize,
+        url: String,
+        payload: Vec<u8>,
+        timeout: Duration,
+    },
+}
+
+impl Delivery {
+    fn index(&self) -> usize {
+        match self {
+            Delivery::Command { index, .. } | Delivery::Webhook { index, .. } => *index,
+        }
+    }
+
+    fn run(self) -> (usize, Result<String, String>) {
+        match self {
+            Delivery::Command { index, cmd, env, timeout } => {
+                let mut command = shell::command(&cmd);
+                command.envs(env);
+                let result = shell::run(command, timeout).and_then(|output| {
+                    if output.status.success() {
+                        Ok("exit 0".to_string())
+                    } else {
+                        Err(format!("command failed with {}", output.status))
+                    }
+                });
+                (index, result)
+            }
+            Delivery::Webhook { index, url, payload, timeout } => {
+                let result = http::post_json(&url, &payload, timeout)
+                    .map_err(|e| e.to_string())
+                    .and_then(|response| {
+                        if (200..300).contains(&response.status) {
+                            Ok(format!("HTTP {}", response.status))
+                        } else {
+                            Err(format!("webhook returned HTTP {}", response.status))
+                        }
+                    });
+                (index, result)
+            }
+        }
+    }
+}
+
+pub struct AlertManager {
+    rules: Vec<AlertRule>,
+    sinks: Vec<SinkConfig>,
+    firing: HashMap<String, Alert>,
+    pub history: Vec<Alert>,
+    sink_status: Vec<String>,
+    deliveries_tx: Sender<Delivery>,
+    results_tx: Sender<(usize, Result<String, String>)>,
+    results_rx: Receiver<(usize, Result<String, String>)>,
+    title_reset_at: Option<Instant>,
+}
+
+impl AlertManager {
+    pub fn new(config: &AlertConfig) -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        let (deliveries_tx, deliveries_rx) = mpsc::channel::<Delivery>();
+
+        // One worker delivers commands and webhooks in order; it exits when the manager is dropped
+        let worker_tx = results_tx.clone();
+        thread::spawn(move || {
+            for delivery in deliveries_rx {
+                if worker_tx.send(delivery.run()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            rules: config.rules.clone(),
+            sinks: config.sinks.clone(),
+            firing: HashMap::new(),
+            history: Vec::new(),
+            sink_status: vec!["idle".to_string(); config.sinks.len()],
+            deliveries_tx,
+            results_tx,
+            results_rx,
+            title_reset_at: None,
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: AlertRule) {
+        self.remove_rule(&rule.name);
+        self.rules.push(rule);
+    }
+
+    // Drops the rule together with any alert it has firing, without notifying
+    pub fn remove_rule(&mut self, name: &str) {
+        self.rules.retain(|rule| rule.name != name);
+        self.firing.remove(name);
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Alert> {
+        self.firing.values()
+    }
+
+    pub fn sinks(&self) -> impl Iterator<Item = (&SinkConfig, &str)> {
+        self.sinks.iter().zip(self.sink_status.iter().map(|s| s.as_str()))
+    }
+
+    pub fn evaluate(&mut self, system: &mut SystemState) {
+        self.collect_results(system);
+
+        if let Some(reset_at) = self.title_reset_at {
+            if Instant::now() >= reset_at {
+                let _ = execute!(io::stdout(), SetTitle(CONSOLE_TITLE));
+                self.title_reset_at = None;
+            }
+        }
+
+        let mut transitions = Vec::new();
+        for rule in &self.rules {
+            let value = match system.metric_value(&rule.metric) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            match (rule.check(value), self.firing.contains_key(&rule.name)) {
+                (Some(threshold), false) => {
+                    let message = rule.message.clone().unwrap_or_else(|| {
+                        format!("{} is {:.1} (threshold {:.1})", rule.metric, value, threshold)
+                    });
+                    transitions.push(Alert {
+                        rule: rule.name.clone(),
+                        state: "FIRING".to_string(),
+                        severity: rule.severity.clone(),
+                        metric: rule.metric.clone(),
+                        value,
+                        threshold,
+                        message,
+                        timestamp: Local::now(),
+                    });
+                }
+                (None, true) => {
+                    if let Some(mut alert) = self.firing.remove(&rule.name) {
+                        alert.state = "RESOLVED".to_string();
+                        alert.value = value;
+                        alert.message = format!("{} back to {:.1}", rule.metric, value);
+                        alert.timestamp = Local::now();
+                        transitions.push(alert);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for alert in transitions {
+            if alert.state == "FIRING" {
+                self.firing.insert(alert.rule.clone(), alert.clone());
+                system.log(&alert.severity, "Alert", &format!("{}: {}", alert.rule, alert.message));
+            } else {
+                system.log("INFO", "Alert", &format!("{} resolved: {}", alert.rule, alert.message));
+            }
+            self.notify(alert);
+        }
+    }
+
+    // Fires an alert raised by a check outside the metric rules, once until it is resolved
+    pub fn raise(&mut self, system: &mut SystemState, alert: Alert) {
+        if self.firing.contains_key(&alert.rule) {
+            return;
+        }
+        system.log(&alert.severity, "Alert", &format!("{}: {}", alert.rule, alert.message));
+        self.firing.insert(alert.rule.clone(), alert.clone());
+        self.notify(alert);
+    }
+
+    pub fn resolve(&mut self, system: &mut SystemState, rule: &str, message: String) {
+        if let Some(mut alert) = self.firing.remove(rule) {
+            system.log("INFO", "Alert", &format!("{} resolved: {}", rule, message));
+            alert.state = "RESOLVED".to_string();
+            alert.message = message;
+            alert.timestamp = Local::now();
+            self.notify(alert);
+        }
+    }
+
+    pub fn notify(&mut self, alert: Alert) {
+        for index in 0..self.sinks.len() {
+            self.sink_status[index] = "sending".to_string();
+            self.dispatch(index, &alert);
+        }
+
+        self.history.push(alert);
+        if self.history.len() > ALERT_HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+    }
+
+    fn dispatch(&mut self, index: usize, alert: &Alert) {
+        let tx = self.results_tx.clone();
+        match self.sinks[index].clone() {
+            SinkConfig::Command { cmd, timeout_secs } => {
+                self.deliver(Delivery::Command {
+                    index,
+                    cmd,
+                    env: alert.env(),
+                    timeout: Duration::from_secs(timeout_secs),
+                });
+            }
+            SinkConfig::Webhook { url, timeout_secs } => {
+                self.deliver(Delivery::Webhook {
+                    index,
+                    url,
+                    payload: serde_json::to_vec(alert).unwrap_or_default(),
+                    timeout: Duration::from_secs(timeout_secs),
+                });
+            }
+            SinkConfig::File { path } => {
+                let result = serde_json::to_string(alert)
+                    .map_err(|e| e.to_string())
+                    .and_then(|line| {
+                        OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path)
+                            .and_then(|mut file| writeln!(file, "{}", line))
+                            .map_err(|e| e.to_string())
+                    })
+                    .map(|_| "appended".to_string());
+                let _ = tx.send((index, result));
+            }
+            SinkConfig::Bell { flash_title } => {
+                let mut stdout = io::stdout();
+                let mut result = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+                if flash_title && result.is_ok() {
+                    let title = format!("[{}] {}: {}", alert.severity, alert.rule, alert.state);
+                    result = execute!(stdout, SetTitle(title));
+                    self.title_reset_at = Some(Instant::now() + TITLE_FLASH);
+                }
+                let _ = tx.send((index, result.map(|_| "rang".to_string()).map_err(|e| e.to_string())));
+            }
+        }
+    }
+
+    fn deliver(&self, delivery: Delivery) {
+        if let Err(mpsc::SendError(delivery)) = self.deliveries_tx.send(delivery) {
+            let _ = self.results_tx.send((delivery.index(), Err("sink worker stopped".to_string())));
+        }
+    }
+
+    fn collect_results(&mut self, system: &mut SystemState) {
+        while let Ok((index, result)) = self.results_rx.try_recv() {
+            let status = match result {
+                Ok(status) => status,
+                Err(err) => {
+                    system.log("ERROR", "Alert", &format!("{} failed: {}", self.sinks[index].describe(), err));
+                    format!("error: {}", err)
+                }
+            };
+            self.sink_status[index] = format!("{} ({})", status, Local::now().format("%H:%M:%S"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    // Reads one HTTP request and answers it with the given status line
+    fn serve_once(listener: TcpListener, status: &'static str) -> thread::JoinHandle<(String, Vec<String>, Vec<u8>)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                headers.push(line);
+            }
+
+            let length = headers
+                .iter()
+                .find_map(|h| h.strip_prefix("Content-Length: "))
+                .and_then(|len| len.parse().ok())
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            (request_line.trim_end().to_string(), headers, body)
+        })
+    }
+
+    fn webhook_manager(url: String) -> AlertManager {
+        AlertManager::new(&AlertConfig {
+            rules: Vec::new(),
+            sinks: vec![SinkConfig::Webhook { url, timeout_secs: 5 }],
+        })
+    }
+
+    fn alert() -> Alert {
+        Alert {
+            rule: "high-cpu".to_string(),
+            state: "FIRING".to_string(),
+            severity: "WARN".to_string(),
+            metric: "cpu.usage".to_string(),
+            value: 97.5,
+            threshold: 90.0,
+            message: "cpu.usage is 97.5 (threshold 90.0)".to_string(),
+            timestamp: Local::now(),
+        }
+    }
+
+    #[test]
+    fn webhook_posts_alert_as_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve_once(listener, "204 No Content");
+
+        let mut manager = webhook_manager(format!("http://127.0.0.1:{}/hooks/alerts", port));
+        manager.notify(alert());
+
+        let (index, result) = manager.results_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(result, Ok("HTTP 204".to_string()));
+
+        let (request_line, headers, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /hooks/alerts HTTP/1.1");
+        assert!(headers.contains(&format!("Host: 127.0.0.1:{}", port)));
+        assert!(headers.contains(&"Content-Type: application/json".to_string()));
+        assert!(headers.contains(&format!("Content-Length: {}", body.len())));
+
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["rule"], "high-cpu");
+        assert_eq!(json["state"], "FIRING");
+        assert_eq!(json["severity"], "WARN");
+        assert_eq!(json["metric"], "cpu.usage");
+        assert_eq!(json["value"], 97.5);
+        assert_eq!(json["threshold"], 90.0);
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[test]
+    fn webhook_reports_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve_once(listener, "500 Internal Server Error");
+
+        let mut manager = webhook_manager(format!("http://127.0.0.1:{}/", port));
+        manager.notify(alert());
+
+        let (_, result) = manager.results_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(result, Err("webhook returned HTTP 500".to_string()));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn hung_command_does_not_block_later_sinks() {
+        let mut manager = AlertManager::new(&AlertConfig {
+            rules: Vec::new(),
+            sinks: vec![
+                SinkConfig::Command { cmd: "sleep 30".to_string(), timeout_secs: 1 },
+                SinkConfig::Command { cmd: "test \"$ALERT_RULE\" = high-cpu".to_string(), timeout_secs: 5 },
+            ],
+        });
+        let started = Instant::now();
+        manager.notify(alert());
+
+        let first = manager.results_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(first, (0, Err("timed out after 1.0s".to_string())));
+        let second = manager.results_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(second, (1, Ok("exit 0".to_string())));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,50 @@
+// Console configuration loaded from config.toml
+use crate::alerts::AlertConfig;
+use crate::history::HistoryConfig;
+use crate::netctl::NetworkConfig;
+use crate::packages::PackagesConfig;
+use crate::pipeline::RefreshConfig;
+use crate::plugins::PluginsConfig;
+use crate::probes::ProbeConfig;
+use crate::services::ServicesConfig;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub alerts: AlertConfig,
+    pub history: HistoryConfig,
+    pub network: NetworkConfig,
+    pub services: ServicesConfig,
+    pub packages: PackagesConfig,
+    pub plugins: PluginsConfig,
+    pub probes: Vec<ProbeConfig>,
+    pub refresh: RefreshConfig,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Config> {
+        match path {
+            Some(path) => Self::read(path),
+            None => match config_dir().map(|dir| dir.join("config.toml")) {
+                Some(path) if path.exists() => Self::read(&path),
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("redox-console"))
+}
@@ -0,0 +1,461 @@
+// Declarative dashboards: TOML or JSON files describing a tab of gauges, sparklines, tables and text
+use crate::metrics;
+use crate::plugins::Plugin;
+use crate::probes::first_number;
+use crate::shell;
+use crate::system::SystemState;
+use anyhow::{bail, Context};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Sparkline, Table, Widget, Wrap},
+};
+
+// Samples kept for sparklines fed by a command
+const COMMAND_HISTORY: usize = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardConfig {
+    // Tab title, the file name when unset
+    #[serde(default)]
+    pub title: Option<String>,
+    // How often shell commands are run again
+    #[serde(default = "default_refresh")]
+    pub refresh_secs: u64,
+    // A command still running after this long is killed
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    pub rows: Vec<RowConfig>,
+}
+
+fn default_refresh() -> u64 {
+    5
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowConfig {
+    // Fixed height in lines; rows without one share the remaining space
+    #[serde(default)]
+    pub height: Option<u16>,
+    pub widgets: Vec<WidgetConfig>,
+}
+
+// Where a widget's value comes from and when it turns yellow or red
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Binding {
+    pub metric: Option<String>,
+    // Shell command whose first number in the output is the value
+    pub command: Option<String>,
+    pub warn: Option<f64>,
+    pub crit: Option<f64>,
+    // Lower values are worse, e.g. free space
+    pub invert: bool,
+}
+
+impl Binding {
+    fn color(&self, value: f64) -> Color {
+        let crossed = |limit: Option<f64>| {
+            limit.is_some_and(|limit| if self.invert { value <= limit } else { value >= limit })
+        };
+        if crossed(self.crit) {
+            Color::Red
+        } else if crossed(self.warn) {
+            Color::Yellow
+        } else {
+            Color::Green
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WidgetConfig {
+    Gauge {
+        title: String,
+        #[serde(flatten)]
+        binding: Binding,
+        #[serde(default = "default_max")]
+        max: f64,
+        #[serde(default)]
+        unit: String,
+        // Share of the row width in percent; widgets without one split the rest
+        #[serde(default)]
+        width: Option<u16>,
+    },
+    Sparkline {
+        title: String,
+        #[serde(flatten)]
+        binding: Binding,
+        #[serde(default = "default_window")]
+        window_secs: i64,
+        #[serde(default)]
+        width: Option<u16>,
+    },
+    Table {
+        title: String,
+        // One row per metric with its value and status
+        #[serde(default)]
+        metrics: Vec<String>,
+        #[serde(default)]
+        warn: Option<f64>,
+        #[serde(default)]
+        crit: Option<f64>,
+        // Or the whitespace-separated columns of a command's output
+        #[serde(default)]
+        command: Option<String>,
+        // The first output line is the header
+        #[serde(default)]
+        header: bool,
+        #[serde(default)]
+        width: Option<u16>,
+    },
+    Text {
+        title: String,
+        // Static text where {metric.name} is replaced by the metric's value
+        #[serde(default)]
+        text: String,
+        #[serde(default)]
+        command: Option<String>,
+        #[serde(default)]
+        width: Option<u16>,
+    },
+}
+
+fn default_max() -> f64 {
+    100.0
+}
+
+fn default_window() -> i64 {
+    600
+}
+
+impl WidgetConfig {
+    fn width(&self) -> Option<u16> {
+        match self {
+            WidgetConfig::Gauge { width, .. }
+            | WidgetConfig::Sparkline { width, .. }
+            | WidgetConfig::Table { width, .. }
+            | WidgetConfig::Text { width, .. } => *width,
+        }
+    }
+
+    fn command(&self) -> Option<&str> {
+        match self {
+            WidgetConfig::Gauge { binding, .. } | WidgetConfig::Sparkline { binding, .. } => binding.command.as_deref(),
+            WidgetConfig::Table { command, .. } | WidgetConfig::Text { command, .. } => command.as_deref(),
+        }
+    }
+}
+
+pub fn read(path: &Path) -> anyhow::Result<DashboardConfig> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let config: DashboardConfig = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?,
+        _ => toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?,
+    };
+    if config.rows.iter().all(|row| row.widgets.is_empty()) {
+        bail!("{} defines no widgets", path.display());
+    }
+    Ok(config)
+}
+
+// Dashboard files in the directory, sorted so tabs keep their order
+pub fn discover(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml" || ext == "json"))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+    paths
+}
+
+fn run_command(command: &str, timeout: Duration) -> Result<String, String> {
+    let output = shell::run(shell::command(command), timeout)?;
+    if !output.status.success() {
+        return Err(format!("{}: {}", output.status, output.stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+fn format_value(value: f64) -> String {
+    if value.abs() >= 100.0 || value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+// Replaces {metric.name} with the metric's latest value, or "?" when unknown
+fn interpolate(text: &str, system: &SystemState) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => {
+                let name = &rest[start + 1..start + end];
+                out.push_str(&system.metric_value(name).map_or("?".to_string(), format_value));
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub struct Dashboard {
+    title: String,
+    config: DashboardConfig,
+    // Command -> its latest output
+    outputs: HashMap<String, Result<String, String>>,
+    history: HashMap<String, Vec<f64>>,
+    refreshed: Option<Instant>,
+    running: bool,
+    results_tx: Sender<Vec<(String, Result<String, String>)>>,
+    results_rx: Receiver<Vec<(String, Result<String, String>)>>,
+}
+
+impl Dashboard {
+    pub fn open(path: &Path) -> anyhow::Result<Dashboard> {
+        let config = read(path)?;
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let (results_tx, results_rx) = mpsc::channel();
+        Ok(Dashboard {
+            title: config.title.clone().unwrap_or(stem),
+            config,
+            outputs: HashMap::new(),
+            history: HashMap::new(),
+            refreshed: None,
+            running: false,
+            results_tx,
+            results_rx,
+        })
+    }
+
+    fn commands(&self) -> Vec<String> {
+        let mut commands: Vec<String> = Vec::new();
+        for command in self.config.rows.iter().flat_map(|row| row.widgets.iter().filter_map(|widget| widget.command())) {
+            if !commands.iter().any(|known| known == command) {
+                commands.push(command.to_string());
+            }
+        }
+        commands
+    }
+
+    fn value(&self, binding: &Binding, system: &SystemState) -> Option<f64> {
+        match (&binding.metric, &binding.command) {
+            (Some(metric), _) => system.metric_value(metric),
+            (None, Some(command)) => self.history.get(command).and_then(|values| values.last().copied()),
+            (None, None) => None,
+        }
+    }
+
+    fn render_widget(&self, widget: &WidgetConfig, system: &SystemState, area: Rect, buf: &mut Buffer) {
+        let block = |title: &str| {
+            Block::default().borders(Borders::ALL).title(title.to_string()).style(Style::default().fg(Color::Green))
+        };
+        match widget {
+            WidgetConfig::Gauge { title, binding, max, unit, .. } => {
+                let value = self.value(binding, system);
+                let ratio = value.map_or(0.0, |value| (value / max.max(f64::MIN_POSITIVE)).clamp(0.0, 1.0));
+                let label = value.map_or("no data".to_string(), |value| format!("{}{}", format_value(value), unit));
+                Gauge::default()
+                    .block(block(title))
+                    .gauge_style(Style::default().fg(value.map_or(Color::DarkGray, |value| binding.color(value))))
+                    .ratio(ratio)
+                    .label(label)
+                    .render(area, buf);
+            }
+            WidgetConfig::Sparkline { title, binding, window_secs, .. } => {
+                let values: Vec<f64> = match (&binding.metric, &binding.command) {
+                    (Some(metric), _) => {
+                        let now = Local::now().timestamp();
+                        let tier = metrics::tier_for(*window_secs);
+                        system.metrics.range(metric, tier, now, *window_secs).iter().map(|sample| sample.avg).collect()
+                    }
+                    (None, Some(command)) => self.history.get(command).cloned().unwrap_or_default(),
+                    (None, None) => Vec::new(),
+                };
+                // The newest samples that fit, scaled so fractions are not lost to u64
+                let inner = block(title).inner(area);
+                let shown = &values[values.len().saturating_sub(inner.width as usize)..];
+                let data: Vec<u64> = shown.iter().map(|value| (value.max(0.0) * 100.0) as u64).collect();
+                let latest = self.value(binding, system);
+                let title = match latest {
+                    Some(value) => format!("{} {}", title, format_value(value)),
+                    None => format!("{} (no data)", title),
+                };
+                Sparkline::default()
+                    .block(block(&title))
+                    .data(&data)
+                    .style(Style::default().fg(latest.map_or(Color::DarkGray, |value| binding.color(value))))
+                    .render(area, buf);
+            }
+            WidgetConfig::Table { title, metrics, warn, crit, command, header, .. } => {
+                let (head, rows): (Vec<String>, Vec<(Vec<String>, Color)>) = match command {
+                    Some(command) => match self.outputs.get(command) {
+                        Some(Ok(output)) => {
+                            let mut lines = output.lines().map(|line| line.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+                            let head = if *header { lines.next().unwrap_or_default() } else { Vec::new() };
+                            (head, lines.map(|cells| (cells, Color::Green)).collect())
+                        }
+                        Some(Err(err)) => (Vec::new(), vec![(vec![err.clone()], Color::Red)]),
+                        None => (Vec::new(), vec![(vec!["running...".to_string()], Color::DarkGray)]),
+                    },
+                    None => {
+                        let binding = Binding { warn: *warn, crit: *crit, ..Binding::default() };
+                        let rows = metrics
+                            .iter()
+                            .map(|metric| match system.metric_value(metric) {
+                                Some(value) => {
+                                    let color = binding.color(value);
+                                    let status = match color {
+                                        Color::Red => "CRIT",
+                                        Color::Yellow => "WARN",
+                                        _ => "OK",
+                                    };
+                                    (vec![metric.clone(), format_value(value), status.to_string()], color)
+                                }
+                                None => (vec![metric.clone(), "-".to_string(), "NO DATA".to_string()], Color::DarkGray),
+                            })
+                            .collect();
+                        (vec!["Metric".to_string(), "Value".to_string(), "Status".to_string()], rows)
+                    }
+                };
+                let columns = rows.iter().map(|(cells, _)| cells.len()).chain([head.len()]).max().unwrap_or(1).max(1);
+                let widths = vec![Constraint::Ratio(1, columns as u32); columns];
+                let header_row = Row::new(head.into_iter().map(|cell| {
+                    Cell::from(cell).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                }));
+                let rows = rows.into_iter().map(|(cells, color)| Row::new(cells).style(Style::default().fg(color)));
+                Table::new(rows).header(header_row).block(block(title)).widths(&widths).render(area, buf);
+            }
+            WidgetConfig::Text { title, text, command, .. } => {
+                let mut lines: Vec<Spans> = Vec::new();
+                if !text.is_empty() {
+                    lines.extend(interpolate(text, system).lines().map(|line| Spans::from(line.to_string())));
+                }
+                match command.as_ref().map(|command| self.outputs.get(command)) {
+                    Some(Some(Ok(output))) => lines.extend(output.lines().map(|line| Spans::from(line.to_string()))),
+                    Some(Some(Err(err))) => lines.push(Spans::from(Span::styled(err.clone(), Style::default().fg(Color::Red)))),
+                    Some(None) => lines.push(Spans::from(Span::styled("running...", Style::default().fg(Color::DarkGray)))),
+                    None => {}
+                }
+                Paragraph::new(lines)
+                    .block(block(title))
+                    .style(Style::default().fg(Color::Green))
+                    .wrap(Wrap { trim: false })
+                    .render(area, buf);
+            }
+        }
+    }
+}
+
+impl Plugin for Dashboard {
+    // Runs the dashboard's shell commands on a background thread every refresh_secs
+    fn collect(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+        while let Ok(results) = self.results_rx.try_recv() {
+            self.running = false;
+            for (command, result) in results {
+                if let Some(value) = result.as_ref().ok().and_then(|output| first_number(output)) {
+                    let history = self.history.entry(command.clone()).or_default();
+                    history.push(value);
+                    if history.len() > COMMAND_HISTORY {
+                        history.remove(0);
+                    }
+                }
+                self.outputs.insert(command, result);
+            }
+        }
+
+        let refresh = Duration::from_secs(self.config.refresh_secs.max(1));
+        if self.running || self.refreshed.is_some_and(|at| at.elapsed() < refresh) {
+            return Ok(());
+        }
+        let commands = self.commands();
+        if commands.is_empty() {
+            return Ok(());
+        }
+        self.running = true;
+        self.refreshed = Some(Instant::now());
+        let tx = self.results_tx.clone();
+        let timeout = Duration::from_secs(self.config.timeout_secs.max(1));
+        thread::spawn(move || {
+            let results = commands.into_iter().map(|command| {
+                let result = run_command(&command, timeout);
+                (command, result)
+            });
+            let _ = tx.send(results.collect());
+        });
+        Ok(())
+    }
+
+    fn tabs(&self) -> Vec<String> {
+        vec![self.title.clone()]
+    }
+
+    fn render(&mut self, _tab: &str, system: &SystemState, area: Rect, buf: &mut Buffer) {
+        let rows = &self.config.rows;
+        let constraints: Vec<Constraint> = rows
+            .iter()
+            .map(|row| match row.height {
+                Some(height) => Constraint::Length(height),
+                None => Constraint::Min(3),
+            })
+            .collect();
+        let row_areas = Layout::default().direction(Direction::Vertical).constraints(constraints).split(area);
+
+        for (row, row_area) in rows.iter().zip(row_areas) {
+            if row.widgets.is_empty() {
+                continue;
+            }
+            let fixed: u16 = row.widgets.iter().filter_map(|widget| widget.width()).sum();
+            let flexible = row.widgets.iter().filter(|widget| widget.width().is_none()).count() as u16;
+            let share = 100u16.saturating_sub(fixed).checked_div(flexible).unwrap_or(0);
+            let constraints: Vec<Constraint> = row
+                .widgets
+                .iter()
+                .map(|widget| Constraint::Percentage(widget.width().unwrap_or(share)))
+                .collect();
+            let areas = Layout::default().direction(Direction::Horizontal).constraints(constraints).split(row_area);
+            for (widget, widget_area) in row.widgets.iter().zip(areas) {
+                self.render_widget(widget, system, widget_area, buf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_output_and_failure() {
+        assert_eq!(run_command("echo 42", Duration::from_secs(5)).unwrap(), "42\n");
+        let err = run_command("echo oops >&2; exit 3", Duration::from_secs(5)).unwrap_err();
+        assert!(err.ends_with(": oops"), "{}", err);
+    }
+}
@@ -0,0 +1,350 @@
+// Directory size scanner for the disk usage explorer; scans run on background threads
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct DirEntrySize {
+    // Kept as the raw file name, so directories whose names are not UTF-8 can be entered
+    pub name: OsString,
+    pub size: u64,
+    pub is_dir: bool,
+    // Another filesystem is mounted here; it is not descended into
+    pub mount_point: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirListing {
+    pub entries: Vec<DirEntrySize>,
+    pub total: u64,
+    pub errors: u64,
+    pub scanned_at: Instant,
+}
+
+pub struct ScanProgress {
+    pub root: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+struct Scan {
+    root: PathBuf,
+    files: Arc<AtomicU64>,
+    bytes: Arc<AtomicU64>,
+    cancel: Arc<AtomicBool>,
+}
+
+type ScanResult = (PathBuf, Vec<(PathBuf, DirListing)>);
+
+pub struct DiskUsage {
+    cache: HashMap<PathBuf, DirListing>,
+    scans: Vec<Scan>,
+    results_tx: Sender<ScanResult>,
+    results_rx: Receiver<ScanResult>,
+}
+
+impl DiskUsage {
+    pub fn new() -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        Self {
+            cache: HashMap::new(),
+            scans: Vec::new(),
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn listing(&self, path: &Path) -> Option<&DirListing> {
+        self.cache.get(path)
+    }
+
+    // The running scan that will produce a listing for `path`, if any
+    pub fn progress(&self, path: &Path) -> Option<ScanProgress> {
+        self.scans.iter().find(|scan| path.starts_with(&scan.root)).map(|scan| ScanProgress {
+            root: scan.root.clone(),
+            files: scan.files.load(Ordering::Relaxed),
+            bytes: scan.bytes.load(Ordering::Relaxed),
+        })
+    }
+
+    // Starts a scan unless the path is cached or already covered by a running scan
+    pub fn request(&mut self, path: &Path) {
+        if self.cache.contains_key(path) || self.progress(path).is_some() {
+            return;
+        }
+        self.start(path);
+    }
+
+    // Drops the cached subtree and scans it again
+    pub fn rescan(&mut self, path: &Path) {
+        self.cache.retain(|cached, _| !cached.starts_with(path));
+        for scan in self.scans.iter().filter(|scan| scan.root.starts_with(path)) {
+            scan.cancel.store(true, Ordering::Relaxed);
+        }
+        self.scans.retain(|scan| !scan.root.starts_with(path));
+        self.start(path);
+    }
+
+    fn start(&mut self, path: &Path) {
+        let scan = Scan {
+            root: path.to_path_buf(),
+            files: Arc::new(AtomicU64::new(0)),
+            bytes: Arc::new(AtomicU64::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        let root = scan.root.clone();
+        let counters = (scan.files.clone(), scan.bytes.clone(), scan.cancel.clone());
+        let tx = self.results_tx.clone();
+        thread::spawn(move || {
+            let (files, bytes, cancel) = counters;
+            let mut walker = Walker { files, bytes, cancel, listings: Vec::new(), linked: HashSet::new() };
+            let device = device_of(&root);
+            walker.walk(&root, device);
+            if !walker.cancel.load(Ordering::Relaxed) {
+                let _ = tx.send((root, walker.listings));
+            }
+        });
+        self.scans.push(scan);
+    }
+
+    pub fn collect_results(&mut self) {
+        while let Ok((root, listings)) = self.results_rx.try_recv() {
+            self.scans.retain(|scan| scan.root != root);
+            // A parent scan that started earlier must not replace a newer rescan of a subdirectory
+            for (path, listing) in listings {
+                if self.cache.get(&path).is_some_and(|cached| cached.scanned_at > listing.scanned_at) {
+                    continue;
+                }
+                self.cache.insert(path, listing);
+            }
+        }
+    }
+}
+
+struct Walker {
+    files: Arc<AtomicU64>,
+    bytes: Arc<AtomicU64>,
+    cancel: Arc<AtomicBool>,
+    listings: Vec<(PathBuf, DirListing)>,
+    // (device, inode) of files with several hard links, so each is only counted once
+    linked: HashSet<(u64, u64)>,
+}
+
+impl Walker {
+    // Returns the allocated size of the directory and records a listing for it and every subdirectory
+    fn walk(&mut self, dir: &Path, device: Option<u64>) -> u64 {
+        let mut listing = DirListing { entries: Vec::new(), total: 0, errors: 0, scanned_at: Instant::now() };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                listing.errors += 1;
+                self.listings.push((dir.to_path_buf(), listing));
+                return 0;
+            }
+        };
+
+        for entry in entries {
+            if self.cancel.load(Ordering::Relaxed) {
+                return 0;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    listing.errors += 1;
+                    continue;
+                }
+            };
+            let metadata = match entry.path().symlink_metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => {
+                    listing.errors += 1;
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+            let is_dir = metadata.is_dir();
+            let mount_point = is_dir && device.is_some() && device_of(&path) != device;
+            let size = if is_dir && !mount_point {
+                allocated(&metadata) + self.walk(&path, device)
+            } else if hard_link(&metadata).is_some_and(|inode| !self.linked.insert(inode)) {
+                0
+            } else {
+                self.files.fetch_add(1, Ordering::Relaxed);
+                self.bytes.fetch_add(allocated(&metadata), Ordering::Relaxed);
+                allocated(&metadata)
+            };
+
+            listing.total += size;
+            listing.entries.push(DirEntrySize {
+                name: entry.file_name(),
+                size,
+                is_dir,
+                mount_point,
+            });
+        }
+
+        listing.entries.sort_by_key(|entry| std::cmp::Reverse(entry.size));
+        let total = listing.total;
+        self.listings.push((dir.to_path_buf(), listing));
+        total
+    }
+}
+
+// Disk blocks actually used, like du, so sparse files are not overcounted
+#[cfg(unix)]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+// Identity of a file with more than one hard link
+#[cfg(unix)]
+fn hard_link(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn hard_link(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.symlink_metadata().ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("redox-console-diskusage-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scan(usage: &mut DiskUsage, path: &Path) -> DirListing {
+        usage.request(path);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while usage.progress(path).is_some() {
+            assert!(Instant::now() < deadline, "scan of {} did not finish", path.display());
+            thread::sleep(Duration::from_millis(5));
+            usage.collect_results();
+        }
+        usage.listing(path).cloned().unwrap()
+    }
+
+    fn names(listing: &DirListing) -> Vec<String> {
+        listing.entries.iter().map(|entry| entry.name.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn sizes_roll_up_largest_first() {
+        let root = temp_dir("sizes");
+        fs::create_dir(root.join("big")).unwrap();
+        fs::write(root.join("big/data"), vec![1u8; 64 * 1024]).unwrap();
+        fs::write(root.join("small"), vec![1u8; 4 * 1024]).unwrap();
+
+        let mut usage = DiskUsage::new();
+        let listing = scan(&mut usage, &root);
+        assert_eq!(names(&listing), ["big", "small"]);
+        assert!(listing.entries[0].is_dir);
+        assert_eq!(listing.total, listing.entries.iter().map(|entry| entry.size).sum::<u64>());
+        assert_eq!(listing.errors, 0);
+
+        // Subdirectories come from the same scan
+        let big = usage.listing(&root.join("big")).unwrap();
+        assert_eq!(names(big), ["data"]);
+        assert!(listing.entries[0].size >= big.total);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_count_once() {
+        let root = temp_dir("links");
+        fs::write(root.join("original"), vec![1u8; 64 * 1024]).unwrap();
+        fs::hard_link(root.join("original"), root.join("link")).unwrap();
+        let single = allocated(&fs::metadata(root.join("original")).unwrap());
+        assert!(single > 0);
+
+        let listing = scan(&mut DiskUsage::new(), &root);
+        assert_eq!(listing.entries.len(), 2);
+        assert_eq!(listing.total, single);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_can_be_entered() {
+        use std::os::unix::ffi::OsStringExt;
+        let root = temp_dir("names");
+        let name = OsString::from_vec(b"caf\xe9".to_vec());
+        fs::create_dir(root.join(&name)).unwrap();
+        fs::write(root.join(&name).join("menu"), b"soup").unwrap();
+
+        let mut usage = DiskUsage::new();
+        let listing = scan(&mut usage, &root);
+        assert_eq!(listing.entries[0].name, name);
+        let child = root.join(&listing.entries[0].name);
+        assert_eq!(names(usage.listing(&child).unwrap()), ["menu"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn listing(entries: &[&str], scanned_at: Instant) -> DirListing {
+        let entries = entries
+            .iter()
+            .map(|name| DirEntrySize { name: OsString::from(name), size: 1, is_dir: false, mount_point: false })
+            .collect();
+        DirListing { entries, total: 1, errors: 0, scanned_at }
+    }
+
+    #[test]
+    fn older_parent_scan_keeps_newer_child_listing() {
+        let mut usage = DiskUsage::new();
+        let (parent, child) = (PathBuf::from("/data"), PathBuf::from("/data/logs"));
+        let before = Instant::now();
+        let after = before + Duration::from_secs(1);
+
+        // The child rescan finishes first, then the parent scan that read the child earlier
+        usage.results_tx.send((child.clone(), vec![(child.clone(), listing(&["new.log"], after))])).unwrap();
+        usage.collect_results();
+        let stale = vec![(child.clone(), listing(&["old.log"], before)), (parent.clone(), listing(&["logs"], before))];
+        usage.results_tx.send((parent.clone(), stale)).unwrap();
+        usage.collect_results();
+
+        assert_eq!(names(usage.listing(&child).unwrap()), ["new.log"]);
+        assert_eq!(names(usage.listing(&parent).unwrap()), ["logs"]);
+    }
+
+    #[test]
+    fn unreadable_directory_is_an_error() {
+        let root = temp_dir("missing");
+        let listing = scan(&mut DiskUsage::new(), &root.join("gone"));
+        assert_eq!(listing.errors, 1);
+        assert!(listing.entries.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+// Persistent metric history: append-only JSON-line segments with background compaction
+use crate::metrics::{ClosedSample, MetricStore, RESOLUTIONS};
+use anyhow::Context;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".log";
+const COMPACT_AFTER_SEGMENTS: usize = 4;
+const COMPACT_SUFFIX: &str = ".compact";
+
+static COMPACT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub dir: Option<PathBuf>,
+    pub segment_bytes: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None,
+            segment_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("redox-console"))
+}
+
+pub struct HistoryWriter {
+    dir: PathBuf,
+    segment_bytes: u64,
+    active: BufWriter<File>,
+    active_path: PathBuf,
+    active_len: u64,
+    compaction: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl HistoryWriter {
+    // Compacts existing segments, replays them into the store and opens a fresh segment
+    pub fn open(config: &HistoryConfig, store: &mut MetricStore) -> anyhow::Result<(HistoryWriter, usize)> {
+        let dir = config
+            .dir
+            .clone()
+            .or_else(|| data_dir().map(|dir| dir.join("history")))
+            .context("no history directory (set history.dir or $HOME)")?;
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        remove_stale_temp_files(&dir);
+
+        let segments = list_segments(&dir)?;
+        compact(&segments)?;
+
+        let mut restored = 0;
+        for segment in list_segments(&dir)? {
+            for closed in read_segment(&segment)? {
+                store.restore(closed);
+                restored += 1;
+            }
+        }
+        store.enable_outbox();
+
+        let (active, active_path) = new_segment(&dir)?;
+        Ok((
+            HistoryWriter {
+                dir,
+                segment_bytes: config.segment_bytes,
+                active,
+                active_path,
+                active_len: 0,
+                compaction: None,
+            },
+            restored,
+        ))
+    }
+
+    pub fn persist(&mut self, store: &mut MetricStore) -> anyhow::Result<()> {
+        let closed = store.drain_closed();
+        if closed.is_empty() {
+            return Ok(());
+        }
+
+        for sample in &closed {
+            let line = serde_json::to_string(sample)?;
+            writeln!(self.active, "{}", line)?;
+            self.active_len += line.len() as u64 + 1;
+        }
+        self.active.flush()?;
+
+        if self.active_len >= self.segment_bytes {
+            self.roll()?;
+        }
+        Ok(())
+    }
+
+    fn roll(&mut self) -> anyhow::Result<()> {
+        let (active, active_path) = new_segment(&self.dir)?;
+        self.active = active;
+        self.active_path = active_path;
+        self.active_len = 0;
+
+        let sealed: Vec<PathBuf> = list_segments(&self.dir)?
+            .into_iter()
+            .filter(|path| *path != self.active_path)
+            .collect();
+        // Segments sealed while a compaction runs wait for the next roll
+        let busy = self.compaction.as_ref().is_some_and(|handle| !handle.is_finished());
+        if sealed.len() >= COMPACT_AFTER_SEGMENTS && !busy {
+            self.compaction = Some(thread::spawn(move || compact(&sealed)));
+        }
+        Ok(())
+    }
+
+    // Reports the outcome of a finished background compaction once
+    pub fn compaction_error(&mut self) -> Option<anyhow::Error> {
+        if !self.compaction.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.compaction.take()?.join() {
+            Ok(result) => result.err(),
+            Err(_) => Some(anyhow::anyhow!("history compaction panicked")),
+        }
+    }
+}
+
+fn new_segment(dir: &Path) -> anyhow::Result<(BufWriter<File>, PathBuf)> {
+    let path = dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, Local::now().timestamp_millis(), SEGMENT_SUFFIX));
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    Ok((BufWriter::new(file), path))
+}
+
+fn list_segments(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut segments: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(SEGMENT_PREFIX) && name.ends_with(SEGMENT_SUFFIX))
+        })
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+fn read_segment(path: &Path) -> anyhow::Result<Vec<ClosedSample>> {
+    let file = File::open(path).with_context(|| format!("reading {}", path.display()))?;
+    // A torn final line from a crash is skipped rather than failing the whole segment
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+// Rewrites the given segments into the first one, dropping samples past their tier retention.
+// Unreadable segments are left in place for a later pass instead of failing the merge.
+fn compact(segments: &[PathBuf]) -> anyhow::Result<()> {
+    let target = match segments.first() {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+
+    let tmp = temp_path(target);
+    let result = write_compacted(segments, target, &tmp).and_then(|merged| {
+        fs::rename(&tmp, target).with_context(|| format!("replacing {}", target.display()))?;
+        Ok(merged)
+    });
+    let merged = match result {
+        Ok(merged) => merged,
+        Err(err) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(err);
+        }
+    };
+
+    // The merged samples now live in the target, so keep deleting past a failure and report the first one
+    let mut first_err = None;
+    for segment in merged.into_iter().skip(1) {
+        if let Err(err) = fs::remove_file(segment) {
+            first_err.get_or_insert_with(|| anyhow::Error::new(err).context(format!("removing {}", segment.display())));
+        }
+    }
+    first_err.map_or(Ok(()), Err)
+}
+
+// Returns the segments whose samples made it into the temp file
+fn write_compacted<'a>(segments: &'a [PathBuf], target: &Path, tmp: &Path) -> anyhow::Result<Vec<&'a PathBuf>> {
+    let now = Local::now().timestamp();
+    let mut out = BufWriter::new(File::create(tmp).with_context(|| format!("creating {}", tmp.display()))?);
+    let mut merged = Vec::new();
+    for segment in segments {
+        let samples = match read_segment(segment) {
+            Ok(samples) => samples,
+            Err(_) if segment != target => continue,
+            Err(err) => return Err(err),
+        };
+        for closed in samples {
+            let retention = RESOLUTIONS.get(closed.tier).map(|r| r.retention).unwrap_or(0);
+            if closed.sample.timestamp >= now - retention {
+                writeln!(out, "{}", serde_json::to_string(&closed)?)?;
+            }
+        }
+        merged.push(segment);
+    }
+    out.flush()?;
+    Ok(merged)
+}
+
+// Unique per process and per pass, so concurrent or crashed compactions never share a file
+fn temp_path(target: &Path) -> PathBuf {
+    let seq = COMPACT_SEQ.fetch_add(1, Ordering::Relaxed);
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}{}", process::id(), seq, COMPACT_SUFFIX));
+    target.with_file_name(name)
+}
+
+fn remove_stale_temp_files(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.to_str().is_some_and(|name| name.ends_with(COMPACT_SUFFIX)) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Sample;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("redox-console-history-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_segment(dir: &Path, index: usize, samples: &[(&str, usize, i64)]) -> PathBuf {
+        let path = dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, index, SEGMENT_SUFFIX));
+        let mut file = File::create(&path).unwrap();
+        for (metric, tier, timestamp) in samples {
+            let closed = ClosedSample {
+                metric: metric.to_string(),
+                tier: *tier,
+                sample: Sample { timestamp: *timestamp, min: 1.0, avg: 2.0, max: 3.0 },
+            };
+            writeln!(file, "{}", serde_json::to_string(&closed).unwrap()).unwrap();
+        }
+        path
+    }
+
+    fn metrics(path: &Path) -> Vec<String> {
+        read_segment(path).unwrap().into_iter().map(|closed| closed.metric).collect()
+    }
+
+    #[test]
+    fn compact_merges_into_first_segment() {
+        let dir = temp_dir("merge");
+        let now = Local::now().timestamp();
+        let segments = vec![
+            write_segment(&dir, 1, &[("cpu.usage", 1, now - 60), ("cpu.usage", 1, now - 7 * 60 * 60)]),
+            write_segment(&dir, 2, &[("memory.used_percent", 2, now - 2 * 24 * 60 * 60)]),
+            write_segment(&dir, 3, &[("disk.read", 1, now - 10)]),
+        ];
+
+        compact(&segments).unwrap();
+
+        assert_eq!(list_segments(&dir).unwrap(), vec![segments[0].clone()]);
+        // The 10s tier keeps six hours, the 1m tier a week
+        assert_eq!(metrics(&segments[0]), ["cpu.usage", "memory.used_percent", "disk.read"]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compact_keeps_deleting_after_failure() {
+        let dir = temp_dir("partial");
+        let now = Local::now().timestamp();
+        let first = write_segment(&dir, 1, &[("cpu.usage", 1, now)]);
+        // A directory reads as an empty segment but cannot be removed with remove_file
+        let stuck = dir.join(format!("{}{:020}{}", SEGMENT_PREFIX, 2, SEGMENT_SUFFIX));
+        fs::create_dir(&stuck).unwrap();
+        let last = write_segment(&dir, 3, &[("disk.read", 1, now)]);
+
+        let err = compact(&[first.clone(), stuck.clone(), last.clone()]).unwrap_err();
+        assert!(format!("{:#}", err).contains("removing"));
+
+        assert_eq!(metrics(&first), ["cpu.usage", "disk.read"]);
+        assert!(stuck.exists());
+        assert!(!last.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compact_temp_files_are_unique() {
+        let target = Path::new("/tmp/segment-1.log");
+        let first = temp_path(target);
+        let second = temp_path(target);
+        assert_ne!(first, second);
+        for path in [first, second] {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            assert!(name.starts_with("segment-1.log.") && name.ends_with(COMPACT_SUFFIX));
+            assert!(!name.ends_with(SEGMENT_SUFFIX));
+        }
+    }
+
+    #[test]
+    fn open_removes_stale_temp_files() {
+        let dir = temp_dir("stale");
+        let now = Local::now().timestamp();
+        write_segment(&dir, 1, &[("cpu.usage", 1, now)]);
+        let stale = dir.join(format!("segment-1.log.999.0{}", COMPACT_SUFFIX));
+        fs::write(&stale, "partial").unwrap();
+
+        let config = HistoryConfig { enabled: true, dir: Some(dir.clone()), segment_bytes: 1024 };
+        let mut store = MetricStore::new();
+        let (_writer, restored) = HistoryWriter::open(&config, &mut store).unwrap();
+
+        assert_eq!(restored, 1);
+        assert!(!stale.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
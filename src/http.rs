@@ -0,0 +1,176 @@
+// Minimal HTTP/1.1 client for webhooks and local repository checks
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub struct HttpUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+pub fn parse_url(url: &str) -> io::Result<HttpUrl> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported URL scheme: {}", url))
+    })?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], &rest[idx..]),
+        None => (rest, "/"),
+    };
+
+    let invalid_port = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid port in {}", url));
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        // IPv6 literal: [addr] or [addr]:port
+        let (host, rest) = bracketed.split_once(']').ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unterminated IPv6 address in {}", url))
+        })?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => port.parse().map_err(|_| invalid_port())?,
+            None if rest.is_empty() => 80,
+            None => return Err(invalid_port()),
+        };
+        (host, port)
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid_port())?),
+            None => (authority, 80),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("missing host in {}", url)));
+    }
+
+    Ok(HttpUrl {
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn request(
+    method: &str,
+    url: &str,
+    body: Option<(&str, &[u8])>,
+    timeout: Duration,
+) -> io::Result<HttpResponse> {
+    let url = parse_url(url)?;
+    let addr = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("cannot resolve {}", url.host)))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let host = if url.host.contains(':') {
+        format!("[{}]", url.host)
+    } else {
+        url.host.clone()
+    };
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: redox-console/{}\r\nConnection: close\r\n",
+        method,
+        url.path,
+        host,
+        url.port,
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some((content_type, payload)) = body {
+        head.push_str(&format!("Content-Type: {}\r\nContent-Length: {}\r\n", content_type, payload.len()));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    if let Some((_, payload)) = body {
+        stream.write_all(payload)?;
+    }
+    stream.flush()?;
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    parse_response(&raw)
+}
+
+pub fn post_json(url: &str, payload: &[u8], timeout: Duration) -> io::Result<HttpResponse> {
+    request("POST", url, Some(("application/json", payload)), timeout)
+}
+
+fn parse_response(raw: &[u8]) -> io::Result<HttpResponse> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let split = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| invalid("truncated HTTP response"))?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid("malformed HTTP status line"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Ok(HttpResponse { status, headers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_url_defaults() {
+        let url = parse_url("http://example.org").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path.as_str()), ("example.org", 80, "/"));
+
+        let url = parse_url("http://127.0.0.1:8080/hooks?x=1").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path.as_str()), ("127.0.0.1", 8080, "/hooks?x=1"));
+    }
+
+    #[test]
+    fn parse_url_ipv6() {
+        let url = parse_url("http://[::1]:8080/").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path.as_str()), ("::1", 8080, "/"));
+
+        let url = parse_url("http://[fe80::1]/alerts").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.path.as_str()), ("fe80::1", 80, "/alerts"));
+    }
+
+    #[test]
+    fn parse_url_rejects_invalid() {
+        assert!(parse_url("https://example.org/").is_err());
+        assert!(parse_url("http://:80/").is_err());
+        assert!(parse_url("http://host:port/").is_err());
+        assert!(parse_url("http://[::1/").is_err());
+        assert!(parse_url("http://[::1]x/").is_err());
+    }
+
+    #[test]
+    fn parse_response_status_and_headers() {
+        let response = parse_response(b"HTTP/1.1 304 Not Modified\r\nLast-Modified: Tue\r\n\r\n").unwrap();
+        assert_eq!(response.status, 304);
+        assert_eq!(response.header("last-modified"), Some("Tue"));
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use clap::Parser;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
@@ -5,29 +6,52 @@ use crossterm::{
 };
 use std::{
     io,
+    path::PathBuf,
     time::{Duration, Instant},
 };
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    symbols,
-    text::{Span, Spans},
-    widgets::{
-        Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row, Table, Tabs, Wrap,
-    },
-    Frame, Terminal,
+    Terminal,
 };
 
 mod system;
 mod ui;
 mod modules;
 mod advanced_modules;
+mod alerts;
+mod config;
+mod http;
+mod shell;
+mod procfs;
+mod metrics;
+mod history;
+mod netctl;
+mod diskusage;
+mod services;
+mod packages;
+mod repos;
+mod plugins;
+mod wasm;
+mod dashboards;
+mod probes;
+mod pipeline;
+mod monitor;
 
-use system::SystemState;
-use ui::{App, Tab};
+use config::Config;
+use ui::App;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Path to config.toml (defaults to ~/.config/redox-console/config.toml)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -36,7 +60,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run
-    let app = App::new();
+    let app = App::new(&config);
     let res = run_app(&mut terminal, app);
 
     // Restore terminal
@@ -56,19 +80,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+    let (frame_rate, tick_rate) = (app.frame_rate, app.tick_rate);
     let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(250);
+    let mut last_frame: Option<Instant> = None;
+    // When the oldest key not yet shown on screen was read
+    let mut input_at: Option<Instant> = None;
+    // Frames are only drawn after input, new samples or a tick, and at most once per frame_rate
+    let mut dirty = true;
 
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app))?;
+        if dirty && last_frame.is_none_or(|at| at.elapsed() >= frame_rate) {
+            let started = Instant::now();
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+            app.monitor.record_frame(started.elapsed(), input_at.take());
+            last_frame = Some(Instant::now());
+            dirty = false;
+        }
 
+        // A pending frame waits for frame_rate; otherwise sleep until the next tick or sample
+        let wake = match last_frame {
+            Some(at) if dirty => frame_rate.saturating_sub(at.elapsed()),
+            _ => app.pipeline.next_sample(frame_rate),
+        };
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| Duration::from_secs(0));
+            .unwrap_or_else(|| Duration::from_secs(0))
+            .min(wake);
 
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
+            let event = event::read()?;
+            if matches!(event, Event::Key(_) | Event::Resize(_, _)) {
+                dirty = true;
+            }
+            if matches!(event, Event::Key(_)) {
+                input_at.get_or_insert_with(Instant::now);
+            }
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press && app.confirm.is_some() {
+                    app.handle_confirm_key(key.code);
+                } else if key.kind == KeyEventKind::Press && app.prompt.is_some() {
+                    app.handle_prompt_key(key.code);
+                } else if key.kind == KeyEventKind::Press {
                     match key.code {
                         KeyCode::Char('q') => return Ok(()),
                         KeyCode::Char('1') => app.tabs.index = 0,
@@ -85,9 +137,57 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                         KeyCode::BackTab => app.previous_tab(),
                         KeyCode::Right => app.next_tab(),
                         KeyCode::Left => app.previous_tab(),
-                        KeyCode::Char('r') => app.system.refresh(),
-                        KeyCode::Char('n') => app.system.toggle_network(),
-                        KeyCode::Char('o') => app.system.toggle_orbital(),
+                        KeyCode::Char('+') | KeyCode::Char('=') if matches!(app.tabs.index, 2 | 4 | 10) => app.zoom_in(),
+                        KeyCode::Char('-') if matches!(app.tabs.index, 2 | 4 | 10) => app.zoom_out(),
+                        KeyCode::Char('[') if app.tabs.index == 10 => app.move_cursor(-1),
+                        KeyCode::Char(']') if app.tabs.index == 10 => app.move_cursor(1),
+                        KeyCode::Char('c') if app.tabs.index == 10 => app.clear_cursor(),
+                        KeyCode::Up if app.tabs.index == 4 && !app.show_connections => app.select_interface(-1),
+                        KeyCode::Down if app.tabs.index == 4 && !app.show_connections => app.select_interface(1),
+                        KeyCode::Char('v') if app.tabs.index == 4 => app.toggle_connections(),
+                        KeyCode::Char('/') if app.tabs.index == 4 && app.show_connections => app.open_prompt(),
+                        KeyCode::Up if app.tabs.index == 2 && app.explorer.is_some() => app.explorer_move(-1),
+                        KeyCode::Down if app.tabs.index == 2 && app.explorer.is_some() => app.explorer_move(1),
+                        KeyCode::Enter if app.tabs.index == 2 && app.explorer.is_some() => app.explorer_enter(),
+                        KeyCode::Backspace if app.tabs.index == 2 && app.explorer.is_some() => app.explorer_up(),
+                        KeyCode::Char('r') if app.tabs.index == 2 && app.explorer.is_some() => app.explorer_rescan(),
+                        KeyCode::Esc if app.tabs.index == 2 && app.explorer.is_some() => app.close_explorer(),
+                        KeyCode::Up if app.tabs.index == 2 && !app.show_disk_io => app.select_mount(-1),
+                        KeyCode::Down if app.tabs.index == 2 && !app.show_disk_io => app.select_mount(1),
+                        KeyCode::Enter if app.tabs.index == 2 && !app.show_disk_io => app.open_explorer(),
+                        KeyCode::Char('v') if app.tabs.index == 2 => app.show_disk_io = !app.show_disk_io,
+                        KeyCode::Char('p') if app.tabs.index == 2 => app.show_pseudo_fs = !app.show_pseudo_fs,
+                        KeyCode::Enter | KeyCode::Char('d') if app.tabs.index == 6 => app.toggle_package_detail(),
+                        KeyCode::Esc if app.tabs.index == 6 && app.show_package_detail => app.toggle_package_detail(),
+                        KeyCode::Char('/') | KeyCode::Char('s') if app.tabs.index == 6 => app.open_package_search(),
+                        KeyCode::Char('l') if app.tabs.index == 6 => app.clear_package_search(),
+                        KeyCode::Char('i') if app.tabs.index == 6 => app.open_package_install(),
+                        KeyCode::Char('u') if app.tabs.index == 6 => app.toggle_package_op("upgrade"),
+                        KeyCode::Char('r') if app.tabs.index == 6 => app.toggle_package_op("remove"),
+                        KeyCode::Char('c') if app.tabs.index == 6 => app.toggle_clean_cache(),
+                        KeyCode::Char('x') if app.tabs.index == 6 => app.clear_package_queue(),
+                        KeyCode::Char('a') if app.tabs.index == 6 => app.confirm_package_plan(),
+                        KeyCode::Char('m') if app.tabs.index == 6 => app.repos.check_now(),
+                        KeyCode::Up if app.tabs.index == 6 => app.select_package(-1),
+                        KeyCode::Down if app.tabs.index == 6 => app.select_package(1),
+                        KeyCode::PageUp if app.tabs.index == 6 => app.select_package(-20),
+                        KeyCode::PageDown if app.tabs.index == 6 => app.select_package(20),
+                        KeyCode::Up if app.tabs.index == 11 => app.select_service(-1),
+                        KeyCode::Down if app.tabs.index == 11 => app.select_service(1),
+                        KeyCode::Char('s') if app.tabs.index == 11 => app.request_service_action("start"),
+                        KeyCode::Char('x') if app.tabs.index == 11 => app.request_service_action("stop"),
+                        KeyCode::Char('r') if app.tabs.index == 11 => app.request_service_action("restart"),
+                        KeyCode::Up if app.tabs.index == 8 => app.select_plugin(-1),
+                        KeyCode::Down if app.tabs.index == 8 => app.select_plugin(1),
+                        KeyCode::Char('e') if app.tabs.index == 8 => app.toggle_plugin_enabled(),
+                        KeyCode::Char('l') if app.tabs.index == 8 => app.load_plugin(),
+                        KeyCode::Char('u') if app.tabs.index == 8 => app.unload_plugin(),
+                        KeyCode::Char(':') => app.open_plugin_command(),
+                        KeyCode::F(2) => app.show_monitor = !app.show_monitor,
+                        KeyCode::Char('r') => app.refresh(),
+                        KeyCode::Char('n') if app.tabs.index == 5 => app.notify_administrator(),
+                        KeyCode::Char('n') if app.tabs.index == 4 && !app.show_connections => app.toggle_selected_interface(),
+                        KeyCode::Char('o') => app.toggle_orbital(),
                         KeyCode::Esc => return Ok(()),
                         _ => {}
                     }
@@ -95,9 +195,16 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
             }
         }
 
+        if app.collect_samples() {
+            dirty = true;
+        }
         if last_tick.elapsed() >= tick_rate {
+            let (since_previous, started) = (last_tick.elapsed(), Instant::now());
             app.on_tick();
+            app.monitor.record_tick(since_previous, tick_rate, started.elapsed());
             last_tick = Instant::now();
+            // Ticks apply service, package, repository, plugin and alert results
+            dirty = true;
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,345 @@
+// Time-series store: per-metric ring buffers at several resolutions with min/avg/max rollups
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+pub struct Resolution {
+    pub name: &'static str,
+    pub step: i64,
+    pub retention: i64,
+}
+
+pub const RESOLUTIONS: [Resolution; 3] = [
+    Resolution { name: "1s", step: 1, retention: 10 * 60 },
+    Resolution { name: "10s", step: 10, retention: 6 * 60 * 60 },
+    Resolution { name: "1m", step: 60, retention: 7 * 24 * 60 * 60 },
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: i64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: i64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u32,
+}
+
+impl Bucket {
+    fn new(start: i64, value: f64) -> Self {
+        Self { start, min: value, max: value, sum: value, count: 1 }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn sample(&self) -> Sample {
+        Sample {
+            timestamp: self.start,
+            min: self.min,
+            avg: self.sum / self.count as f64,
+            max: self.max,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tier {
+    step: i64,
+    capacity: usize,
+    samples: VecDeque<Sample>,
+    pending: Option<Bucket>,
+}
+
+impl Tier {
+    fn new(resolution: &Resolution) -> Self {
+        let capacity = (resolution.retention / resolution.step) as usize;
+        Self {
+            step: resolution.step,
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+            pending: None,
+        }
+    }
+
+    // Returns the bucket that was closed by this value, if any
+    fn record(&mut self, timestamp: i64, value: f64) -> Option<Sample> {
+        let start = timestamp - timestamp.rem_euclid(self.step);
+        match &mut self.pending {
+            Some(bucket) if bucket.start == start => {
+                bucket.add(value);
+                None
+            }
+            Some(bucket) if bucket.start > start => None,
+            pending => {
+                let closed = pending.map(|bucket| bucket.sample());
+                *pending = Some(Bucket::new(start, value));
+                if let Some(sample) = closed {
+                    self.push(sample);
+                }
+                closed
+            }
+        }
+    }
+
+    fn push(&mut self, sample: Sample) {
+        if self.samples.back().is_some_and(|last| last.timestamp >= sample.timestamp) {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn since(&self, from: i64) -> Vec<Sample> {
+        let start = self.samples.partition_point(|s| s.timestamp < from);
+        self.samples
+            .range(start..)
+            .copied()
+            .chain(self.pending.map(|bucket| bucket.sample()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Series {
+    tiers: Vec<Tier>,
+    // Last recorded value; restored history leaves it unset
+    latest: Option<f64>,
+}
+
+impl Series {
+    fn new() -> Self {
+        Self {
+            tiers: RESOLUTIONS.iter().map(Tier::new).collect(),
+            latest: None,
+        }
+    }
+}
+
+// Finest tier that still retains the whole window
+pub fn tier_for(window: i64) -> usize {
+    RESOLUTIONS
+        .iter()
+        .position(|r| r.retention >= window)
+        .unwrap_or(RESOLUTIONS.len() - 1)
+}
+
+// Merges samples into at most `points` buckets, keeping the min/max envelope
+pub fn downsample(samples: &[Sample], points: usize) -> Vec<Sample> {
+    if points == 0 || samples.len() <= points {
+        return samples.to_vec();
+    }
+    let per_point = samples.len().div_ceil(points);
+    samples
+        .chunks(per_point)
+        .map(|chunk| Sample {
+            timestamp: chunk[0].timestamp,
+            min: chunk.iter().map(|s| s.min).fold(f64::INFINITY, f64::min),
+            avg: chunk.iter().map(|s| s.avg).sum::<f64>() / chunk.len() as f64,
+            max: chunk.iter().map(|s| s.max).fold(f64::NEG_INFINITY, f64::max),
+        })
+        .collect()
+}
+
+// A bucket that was closed in one of the rollup tiers, for persistence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedSample {
+    pub metric: String,
+    pub tier: usize,
+    pub sample: Sample,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MetricStore {
+    series: HashMap<String, Series>,
+    outbox: Option<Vec<ClosedSample>>,
+}
+
+impl MetricStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, name: &str, timestamp: i64, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let series = self.series.entry(name.to_string()).or_insert_with(Series::new);
+        series.latest = Some(value);
+        for (index, tier) in series.tiers.iter_mut().enumerate() {
+            let closed = tier.record(timestamp, value);
+            if let (Some(sample), Some(outbox)) = (closed, self.outbox.as_mut()) {
+                if index > 0 {
+                    outbox.push(ClosedSample { metric: name.to_string(), tier: index, sample });
+                }
+            }
+        }
+    }
+
+    // Starts collecting closed rollup buckets (10s and coarser) for drain_closed
+    pub fn enable_outbox(&mut self) {
+        self.outbox.get_or_insert_with(Vec::new);
+    }
+
+    pub fn drain_closed(&mut self) -> Vec<ClosedSample> {
+        self.outbox.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // Inserts an already rolled-up sample, e.g. replayed from disk
+    pub fn restore(&mut self, closed: ClosedSample) {
+        let series = self.series.entry(closed.metric).or_insert_with(Series::new);
+        if let Some(tier) = series.tiers.get_mut(closed.tier) {
+            tier.push(closed.sample);
+        }
+    }
+
+    pub fn latest(&self, name: &str) -> Option<f64> {
+        self.series.get(name).and_then(|series| series.latest)
+    }
+
+    pub fn latest_values(&self) -> HashMap<String, f64> {
+        self.series
+            .iter()
+            .filter_map(|(name, series)| Some((name.clone(), series.latest?)))
+            .collect()
+    }
+
+    // Samples of the given resolution tier covering the last `window` seconds
+    pub fn range(&self, name: &str, tier: usize, now: i64, window: i64) -> Vec<Sample> {
+        self.series
+            .get(name)
+            .and_then(|series| series.tiers.get(tier))
+            .map(|tier| tier.since(now - window))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: i64, value: f64) -> Sample {
+        Sample { timestamp, min: value, avg: value, max: value }
+    }
+
+    #[test]
+    fn restored_samples_are_not_latest() {
+        let mut store = MetricStore::new();
+        store.restore(ClosedSample { metric: "probe.web".to_string(), tier: 1, sample: sample(1_000, 42.0) });
+        assert_eq!(store.latest("probe.web"), None);
+        assert!(store.latest_values().is_empty());
+        assert_eq!(store.range("probe.web", 1, 1_010, 60).len(), 1);
+
+        store.record("probe.web", 2_000, 7.0);
+        assert_eq!(store.latest("probe.web"), Some(7.0));
+        assert_eq!(store.latest_values().get("probe.web"), Some(&7.0));
+    }
+
+    fn tier(step: i64, retention: i64) -> Tier {
+        Tier::new(&Resolution { name: "test", step, retention })
+    }
+
+    fn envelope(sample: &Sample) -> (i64, f64, f64, f64) {
+        (sample.timestamp, sample.min, sample.avg, sample.max)
+    }
+
+    #[test]
+    fn bucket_rolls_up_min_avg_max() {
+        let mut tier = tier(10, 100);
+        assert!(tier.record(100, 5.0).is_none());
+        assert!(tier.record(105, 1.0).is_none());
+        assert!(tier.record(109, 9.0).is_none());
+        let closed = tier.record(110, 4.0).unwrap();
+        assert_eq!(envelope(&closed), (100, 1.0, 5.0, 9.0));
+        assert_eq!(tier.samples.len(), 1);
+    }
+
+    #[test]
+    fn older_timestamps() {
+        let mut tier = tier(10, 100);
+        tier.record(100, 1.0);
+        tier.record(110, 2.0);
+        // Late but inside the open bucket still counts
+        assert!(tier.record(112, 4.0).is_none());
+        // A bucket that is already closed is not reopened
+        assert!(tier.record(105, 100.0).is_none());
+        assert!(tier.record(95, 100.0).is_none());
+
+        let samples = tier.since(0);
+        assert_eq!(samples.iter().map(envelope).collect::<Vec<_>>(), [(100, 1.0, 1.0, 1.0), (110, 2.0, 3.0, 4.0)]);
+
+        // Pushing a rollup that is not newer than the last one is ignored
+        tier.push(sample(100, 50.0));
+        assert_eq!(tier.samples.len(), 1);
+    }
+
+    #[test]
+    fn retention_evicts_oldest() {
+        let mut tier = tier(1, 3);
+        for timestamp in 0..6 {
+            tier.record(timestamp, timestamp as f64);
+        }
+        // 0..=4 are closed, 5 is pending
+        let kept: Vec<i64> = tier.samples.iter().map(|s| s.timestamp).collect();
+        assert_eq!(kept, [2, 3, 4]);
+    }
+
+    #[test]
+    fn since_includes_pending_bucket() {
+        let mut tier = tier(10, 100);
+        for timestamp in [100, 110, 120, 125] {
+            tier.record(timestamp, 1.0);
+        }
+        let timestamps = |from| tier.since(from).iter().map(|s| s.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps(0), [100, 110, 120]);
+        assert_eq!(timestamps(110), [110, 120]);
+        assert_eq!(timestamps(500), [120]);
+    }
+
+    #[test]
+    fn downsample_keeps_envelope() {
+        let samples: Vec<Sample> = (0..10).map(|i| sample(i, i as f64)).collect();
+        let points = downsample(&samples, 3);
+        assert_eq!(
+            points.iter().map(envelope).collect::<Vec<_>>(),
+            [(0, 0.0, 1.5, 3.0), (4, 4.0, 5.5, 7.0), (8, 8.0, 8.5, 9.0)]
+        );
+        assert_eq!(downsample(&samples, 10).len(), 10);
+        assert_eq!(downsample(&samples, 0).len(), 10);
+    }
+
+    #[test]
+    fn tier_for_window() {
+        assert_eq!(tier_for(60), 0);
+        assert_eq!(tier_for(10 * 60), 0);
+        assert_eq!(tier_for(10 * 60 + 1), 1);
+        assert_eq!(tier_for(6 * 60 * 60), 1);
+        assert_eq!(tier_for(6 * 60 * 60 + 1), 2);
+        assert_eq!(tier_for(30 * 24 * 60 * 60), 2);
+    }
+
+    #[test]
+    fn non_finite_values_are_dropped() {
+        let mut store = MetricStore::new();
+        store.record("cpu.usage", 100, f64::NAN);
+        store.record("cpu.usage", 101, f64::INFINITY);
+        assert_eq!(store.latest("cpu.usage"), None);
+        store.record("cpu.usage", 102, 3.0);
+        store.record("cpu.usage", 103, f64::NEG_INFINITY);
+        assert_eq!(store.latest("cpu.usage"), Some(3.0));
+        let samples = store.range("cpu.usage", 0, 103, 60);
+        assert_eq!(samples.iter().map(envelope).collect::<Vec<_>>(), [(102, 3.0, 3.0, 3.0)]);
+    }
+}
@@ -1,21 +1,23 @@
 // Advanced module functions for Redox OS Console Dashboard
+use crate::alerts::AlertManager;
 use crate::system::SystemState;
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
     widgets::{
-        Block, Borders, List, ListItem, Paragraph, Table, Row, Cell, Gauge, Wrap,
+        Block, Borders, List, ListItem, Paragraph, Table, Row, Cell, Wrap,
     },
     Frame,
 };
 use rand::Rng;
 
 pub fn draw_kernel_monitor<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+    let core_rows = (system.cpu_cores.len() as u16).clamp(2, 10);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Length(8), Constraint::Length(core_rows + 2), Constraint::Min(0)].as_ref())
         .split(area);
 
     // Kernel Metrics
@@ -29,11 +31,11 @@ pub fn draw_kernel_monitor<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
     let context_switches = rng.gen_range(400..800);
     let scheduler_queue_depth = rng.gen_range(2..8);
 
-    let kernel_metrics = vec![
+    let kernel_metrics = [
         format!("Syscalls/sec: {}", syscalls_per_sec),
         format!("Context Switches/sec: {}", context_switches),
         format!("Scheduler Queue Depth: {}", scheduler_queue_depth),
-        format!("Kernel Panic Count: 0"),
+        "Kernel Panic Count: 0".to_string(),
         format!("Uptime: {}", system.get_uptime_string()),
     ];
 
@@ -53,7 +55,7 @@ pub fn draw_kernel_monitor<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
     f.render_widget(kernel_list, kernel_chunks[0]);
 
     // Interactive Actions
-    let actions = vec![
+    let actions = [
         "[k] View Kernel Panic Trace",
         "[s] Toggle Scheduler Visualizer", 
         "[d] Dump Kernel Debug Info",
@@ -76,22 +78,8 @@ pub fn draw_kernel_monitor<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
 
     f.render_widget(actions_list, kernel_chunks[1]);
 
-    // Scheduler Visualization
-    let scheduler_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(25); 4].as_ref())
-        .split(chunks[1]);
-
-    for i in 0..4 {
-        let core_load = rng.gen_range(10..90) as f64 / 100.0;
-        let gauge = Gauge::default()
-            .block(Block::default().borders(Borders::ALL).title(format!("Core {}", i)).style(Style::default().fg(Color::Green)))
-            .gauge_style(Style::default().fg(if core_load > 0.8 { Color::Red } else { Color::Green }))
-            .ratio(core_load)
-            .label(format!("{:.1}%", core_load * 100.0));
-
-        f.render_widget(gauge, scheduler_chunks[i]);
-    }
+    // Per-core CPU utilization
+    draw_cpu_cores(f, system, chunks[1]);
 
     // System Call Monitor
     let syscall_text = format!(
@@ -113,48 +101,143 @@ pub fn draw_kernel_monitor<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
     f.render_widget(syscall_para, chunks[2]);
 }
 
+fn core_color(load: f32) -> Color {
+    if load > 80.0 { Color::Red }
+    else if load > 50.0 { Color::LightRed }
+    else if load > 25.0 { Color::Yellow }
+    else { Color::Green }
+}
+
+fn draw_cpu_cores<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+    let legend = [
+        ("user", Color::Green),
+        ("sys", Color::Red),
+        ("iowait", Color::Yellow),
+        ("irq", Color::Magenta),
+        ("steal", Color::Cyan),
+    ];
+    let heat_legend = [
+        ("<25%", Color::Green),
+        ("<50%", Color::Yellow),
+        ("<80%", Color::LightRed),
+        (">80%", Color::Red),
+    ];
+
+    let rows = area.height.saturating_sub(2).max(1) as usize;
+    let width = area.width.saturating_sub(2) as usize;
+    let columns = system.cpu_cores.len().div_ceil(rows).max(1);
+    let column_width = width / columns;
+    let stacked = column_width >= 24;
+
+    let mut title = vec![Span::raw(format!("CPU Cores ({}) ", system.cpu_cores.len()))];
+    for (name, color) in if stacked { &legend[..] } else { &heat_legend[..] } {
+        title.push(Span::styled("█", Style::default().fg(*color)));
+        title.push(Span::raw(format!("{} ", name)));
+    }
+    let block = Block::default().borders(Borders::ALL).title(Spans::from(title)).style(Style::default().fg(Color::Green));
+
+    if system.cpu_cores.is_empty() {
+        let para = Paragraph::new("Per-core statistics unavailable (/proc/stat not readable)")
+            .block(block)
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(para, area);
+        return;
+    }
+
+    let lines: Vec<Spans> = if stacked {
+        // Stacked bars: one row per core, split by time category
+        let bar_width = column_width - 15;
+        (0..rows)
+            .map(|row| {
+                let mut spans = Vec::new();
+                for core in system.cpu_cores.iter().skip(row).step_by(rows) {
+                    let mut used = 0;
+                    spans.push(Span::raw(format!("{:>6} [", core.name)));
+                    for (value, (_, color)) in [core.user, core.system, core.iowait, core.irq, core.steal].iter().zip(legend) {
+                        let cells = ((value / 100.0) * bar_width as f32).round() as usize;
+                        let cells = cells.min(bar_width - used);
+                        used += cells;
+                        spans.push(Span::styled("█".repeat(cells), Style::default().fg(color)));
+                    }
+                    spans.push(Span::raw(" ".repeat(bar_width - used)));
+                    spans.push(Span::styled(
+                        format!("]{:>4.0}% ", core.busy()),
+                        Style::default().fg(core_color(core.busy())),
+                    ));
+                }
+                Spans::from(spans)
+            })
+            .collect()
+    } else {
+        // Heatmap: one colored cell per core, scales to hundreds of cores
+        let cell_width = if system.cpu_cores.len().div_ceil((width / 3).max(1)) <= rows { 3 } else { 1 };
+        let per_row = (width / cell_width).max(1);
+        system
+            .cpu_cores
+            .chunks(per_row)
+            .map(|cores| {
+                Spans::from(
+                    cores
+                        .iter()
+                        .map(|core| {
+                            Span::styled(
+                                if cell_width == 3 { "██ " } else { "█" },
+                                Style::default().fg(core_color(core.busy())),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect()
+    };
+
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
 pub fn draw_filesystem_inspector<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(10), Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Min(0), Constraint::Length(7)].as_ref())
         .split(area);
 
-    // RedoxFS Metrics Table
-    let header_cells = ["Mount", "Type", "Read Latency", "Write Latency", "Hash Status", "Snapshots"]
+    // Block device I/O table
+    let header_cells = ["Device", "r/s", "w/s", "rKB/s", "wKB/s", "r_await", "w_await", "Queue", "Util%"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let mut rng = rand::thread_rng();
-    let rows = system.filesystems.iter().map(|fs| {
-        let read_latency = format!("{:.2}ms", rng.gen_range(0.1..2.0));
-        let write_latency = format!("{:.2}ms", rng.gen_range(0.5..3.0));
-        let hash_status = if rng.gen_bool(0.9) { "VERIFIED" } else { "PENDING" };
-        let snapshots = rng.gen_range(3..15);
-
+    let rows = system.disks.iter().map(|disk| {
         let cells = vec![
-            Cell::from(fs.mount.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(fs.fs_type.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(read_latency).style(Style::default().fg(Color::Green)),
-            Cell::from(write_latency).style(Style::default().fg(Color::Green)),
-            Cell::from(hash_status).style(Style::default().fg(
-                if hash_status == "VERIFIED" { Color::Green } else { Color::Red }
+            Cell::from(disk.name.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}", disk.read_iops)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}", disk.write_iops)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}", disk.read_rate / 1024.0)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}", disk.write_rate / 1024.0)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.2}ms", disk.read_await_ms)).style(Style::default().fg(latency_color(disk.read_await_ms))),
+            Cell::from(format!("{:.2}ms", disk.write_await_ms)).style(Style::default().fg(latency_color(disk.write_await_ms))),
+            Cell::from(format!("{:.2}", disk.queue_depth)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}%", disk.util_percent)).style(Style::default().fg(
+                if disk.util_percent > 90.0 { Color::Red }
+                else if disk.util_percent > 70.0 { Color::Yellow }
+                else { Color::Green }
             )),
-            Cell::from(snapshots.to_string()).style(Style::default().fg(Color::Green)),
         ];
         Row::new(cells).height(1)
     });
 
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("RedoxFS Inspector").style(Style::default().fg(Color::Green)))
+        .block(Block::default().borders(Borders::ALL).title("Block Device I/O [v=Mounts]").style(Style::default().fg(Color::Green)))
         .widths(&[
             Constraint::Length(12),
-            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(8),
             Constraint::Length(12),
-            Constraint::Length(13),
             Constraint::Length(12),
-            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(7),
         ]);
 
     f.render_widget(table, chunks[0]);
@@ -165,12 +248,12 @@ pub fn draw_filesystem_inspector<B: Backend>(f: &mut Frame<B>, system: &SystemSt
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
         .split(chunks[1]);
 
-    let actions = vec![
+    let actions = [
         "[c] Create Snapshot",
         "[r] Rollback to Snapshot",
         "[i] Run Integrity Scan",
         "[d] Defragment Filesystem",
-        "[v] Verify Hash Trees",
+        "[h] Verify Hash Trees",
     ];
 
     let action_items: Vec<ListItem> = actions
@@ -189,12 +272,16 @@ pub fn draw_filesystem_inspector<B: Backend>(f: &mut Frame<B>, system: &SystemSt
     f.render_widget(actions_list, fs_actions_chunks[0]);
 
     // FS Performance
-    let perf_metrics = vec![
+    let busiest = system.disks.iter().max_by(|a, b| a.util_percent.total_cmp(&b.util_percent));
+    let perf_metrics = [
         format!("Total I/O Operations: {}/s", system.fs_reads + system.fs_writes),
-        format!("Cache Hit Ratio: {:.1}%", rng.gen_range(85.0..98.0)),
-        format!("Free Space: {:.1} GB", rng.gen_range(10.0..50.0)),
-        format!("Fragmentation: {:.1}%", rng.gen_range(5.0..25.0)),
-        format!("Active Transactions: {}", rng.gen_range(0..10)),
+        format!("Read Throughput: {:.1} KB/s", system.disks.iter().map(|d| d.read_rate).sum::<f64>() / 1024.0),
+        format!("Write Throughput: {:.1} KB/s", system.disks.iter().map(|d| d.write_rate).sum::<f64>() / 1024.0),
+        format!("In Flight: {}", system.disks.iter().map(|d| d.in_flight).sum::<u64>()),
+        match busiest {
+            Some(disk) => format!("Busiest Device: {} ({:.1}%)", disk.name, disk.util_percent),
+            None => "Busiest Device: -".to_string(),
+        },
     ];
 
     let perf_items: Vec<ListItem> = perf_metrics
@@ -211,23 +298,19 @@ pub fn draw_filesystem_inspector<B: Backend>(f: &mut Frame<B>, system: &SystemSt
         .block(Block::default().borders(Borders::ALL).title("FS Performance").style(Style::default().fg(Color::Green)));
 
     f.render_widget(perf_list, fs_actions_chunks[1]);
+}
 
-    // Snapshot Manager
-    let snapshot_text = format!(
-        "Snapshot Management:\n\n• snapshot_001 (2025-08-20 14:30) - 2.1GB\n• snapshot_002 (2025-08-21 09:15) - 2.3GB\n• snapshot_003 (2025-08-21 13:45) - 2.4GB\n\nAuto-snapshots: ENABLED\nRetention policy: 30 days\nCompression: LZ4\n\nDisk usage by snapshots: {:.1}GB\nDeduplication ratio: {:.1}%",
-        rng.gen_range(15.0..30.0),
-        rng.gen_range(60.0..85.0)
-    );
-
-    let snapshot_para = Paragraph::new(snapshot_text)
-        .block(Block::default().borders(Borders::ALL).title("Snapshot Manager").style(Style::default().fg(Color::Green)))
-        .style(Style::default().fg(Color::Green))
-        .wrap(Wrap { trim: true });
-
-    f.render_widget(snapshot_para, chunks[2]);
+fn latency_color(ms: f64) -> Color {
+    if ms > 50.0 {
+        Color::Red
+    } else if ms > 10.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
 }
 
-pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, alerts: &AlertManager, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(12), Constraint::Min(0)].as_ref())
@@ -239,8 +322,7 @@ pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let mut rng = rand::thread_rng();
-    let security_data = vec![
+    let security_data = [
         ("init", "1", "CAP_SYS_ADMIN", "DISABLED", "0", "LOW"),
         ("ion", "42", "CAP_NET_BIND", "ENABLED", "0", "LOW"),
         ("webserver", "156", "CAP_NET_BIND", "ENABLED", "2", "MEDIUM"),
@@ -285,13 +367,13 @@ pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
 
     f.render_widget(table, chunks[0]);
 
-    // Security Actions and Alerts
+    // Security Actions, Alerts and Notification Sinks
     let security_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(40), Constraint::Percentage(30)].as_ref())
         .split(chunks[1]);
 
-    let security_actions = vec![
+    let security_actions = [
         "[a] View Audit Logs",
         "[r] Revoke Capability",
         "[s] Sandbox Process",
@@ -315,7 +397,7 @@ pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
 
     f.render_widget(actions_list, security_chunks[0]);
 
-    let security_alerts = vec![
+    let security_alerts = [
         "⚠ HIGH: Process 234 using suspicious syscalls",
         "⚠ MEDIUM: Webserver has 2 capability violations", 
         "✓ INFO: All critical processes sandboxed",
@@ -324,22 +406,45 @@ pub fn draw_security_audit<B: Backend>(f: &mut Frame<B>, system: &SystemState, a
         "⚠ MEDIUM: Unusual network activity detected",
     ];
 
-    let alert_items: Vec<ListItem> = security_alerts
+    let live_alerts = alerts.active().map(|alert| {
+        let color = if alert.severity == "WARN" { Color::Yellow } else { Color::Red };
+        ListItem::new(vec![Spans::from(Span::styled(
+            format!("⚠ {}: {} - {}", alert.severity, alert.rule, alert.message),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ))])
+    });
+
+    let alert_items: Vec<ListItem> = live_alerts.chain(security_alerts
         .iter()
         .map(|alert| {
-            let color = if alert.contains("HIGH") { Color::Red }
-                       else if alert.contains("MEDIUM") { Color::Red } 
+            let color = if alert.contains("HIGH") || alert.contains("MEDIUM") { Color::Red }
                        else { Color::Green };
             
             ListItem::new(vec![Spans::from(Span::styled(
                 *alert,
                 Style::default().fg(color),
             ))])
-        })
+        }))
         .collect();
 
     let alerts_list = List::new(alert_items)
         .block(Block::default().borders(Borders::ALL).title("Security Alerts").style(Style::default().fg(Color::Green)));
 
     f.render_widget(alerts_list, security_chunks[1]);
+
+    let sink_items: Vec<ListItem> = alerts
+        .sinks()
+        .map(|(sink, status)| {
+            let color = if status.starts_with("error") { Color::Red } else { Color::Green };
+            ListItem::new(vec![
+                Spans::from(Span::styled(sink.describe(), Style::default().fg(Color::Green))),
+                Spans::from(Span::styled(format!("  └ {}", status), Style::default().fg(color))),
+            ])
+        })
+        .collect();
+
+    let sinks_list = List::new(sink_items)
+        .block(Block::default().borders(Borders::ALL).title("Notification Sinks").style(Style::default().fg(Color::Green)));
+
+    f.render_widget(sinks_list, security_chunks[2]);
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+// The console's own overhead: CPU, memory, frame and tick timing and input latency
+use crate::procfs::{self, SelfUsage};
+use crate::system::SystemState;
+use chrono::Local;
+use std::time::{Duration, Instant};
+
+pub struct SelfMonitor {
+    pub usage: Option<SelfUsage>,
+    pub cpu_percent: f64,
+    prev_cpu: Option<(Instant, f64)>,
+    pub frames: u64,
+    pub last_frame: Duration,
+    pub max_frame: Duration,
+    frame_total: Duration,
+    pub ticks: u64,
+    pub last_tick: Duration,
+    pub dropped_ticks: u64,
+    // Time from reading a key to the frame that shows its effect
+    pub input_latency: Duration,
+    pub max_input_latency: Duration,
+}
+
+impl SelfMonitor {
+    pub fn new() -> Self {
+        Self {
+            usage: None,
+            cpu_percent: 0.0,
+            prev_cpu: None,
+            frames: 0,
+            last_frame: Duration::ZERO,
+            max_frame: Duration::ZERO,
+            frame_total: Duration::ZERO,
+            ticks: 0,
+            last_tick: Duration::ZERO,
+            dropped_ticks: 0,
+            input_latency: Duration::ZERO,
+            max_input_latency: Duration::ZERO,
+        }
+    }
+
+    pub fn average_frame(&self) -> Duration {
+        self.frame_total.checked_div(self.frames as u32).unwrap_or_default()
+    }
+
+    pub fn record_frame(&mut self, elapsed: Duration, input_at: Option<Instant>) {
+        self.frames += 1;
+        self.last_frame = elapsed;
+        self.max_frame = self.max_frame.max(elapsed);
+        self.frame_total += elapsed;
+        if let Some(at) = input_at {
+            self.input_latency = at.elapsed();
+            self.max_input_latency = self.max_input_latency.max(self.input_latency);
+        }
+    }
+
+    // Every whole tick_rate beyond the first since the previous tick is a tick that never ran
+    pub fn record_tick(&mut self, since_previous: Duration, tick_rate: Duration, elapsed: Duration) {
+        self.ticks += 1;
+        self.last_tick = elapsed;
+        let periods = since_previous.as_nanos() / tick_rate.as_nanos().max(1);
+        self.dropped_ticks += periods.saturating_sub(1) as u64;
+    }
+
+    // CPU% is the CPU time used since the previous reading over the wall time between them
+    fn record_cpu(&mut self, now: Instant, cpu_seconds: f64) {
+        if let Some((at, previous)) = self.prev_cpu {
+            let wall = now.duration_since(at).as_secs_f64();
+            if wall > 0.0 {
+                self.cpu_percent = (cpu_seconds - previous).max(0.0) / wall * 100.0;
+            }
+        }
+        self.prev_cpu = Some((now, cpu_seconds));
+    }
+
+    // Reads /proc/self once per tick and records console.* metrics so the overhead can be charted and alerted on
+    pub fn sample(&mut self, system: &mut SystemState) {
+        let usage = match procfs::read_self_usage() {
+            Ok(usage) => usage,
+            Err(_) => return,
+        };
+        self.record_cpu(Instant::now(), usage.cpu_seconds);
+        self.usage = Some(usage);
+
+        let timestamp = Local::now().timestamp();
+        system.metrics.record("console.cpu_percent", timestamp, self.cpu_percent);
+        system.metrics.record("console.rss_mb", timestamp, usage.rss_bytes as f64 / (1024.0 * 1024.0));
+        system.metrics.record("console.frame_ms", timestamp, self.last_frame.as_secs_f64() * 1000.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_ticks() {
+        let mut monitor = SelfMonitor::new();
+        let second = Duration::from_secs(1);
+        monitor.record_tick(second, second, Duration::from_millis(3));
+        monitor.record_tick(Duration::from_millis(1900), second, Duration::from_millis(4));
+        assert_eq!(monitor.dropped_ticks, 0);
+
+        // 3.5 periods since the previous tick: two ticks never ran
+        monitor.record_tick(Duration::from_millis(3500), second, Duration::from_millis(5));
+        assert_eq!(monitor.dropped_ticks, 2);
+        assert_eq!(monitor.ticks, 3);
+        assert_eq!(monitor.last_tick, Duration::from_millis(5));
+
+        // A zero tick rate must not divide by zero
+        monitor.record_tick(second, Duration::ZERO, Duration::ZERO);
+        assert_eq!(monitor.ticks, 4);
+    }
+
+    #[test]
+    fn cpu_percent_from_deltas() {
+        let mut monitor = SelfMonitor::new();
+        let start = Instant::now();
+        monitor.record_cpu(start, 10.0);
+        assert_eq!(monitor.cpu_percent, 0.0);
+
+        monitor.record_cpu(start + Duration::from_secs(2), 10.5);
+        assert_eq!(monitor.cpu_percent, 25.0);
+        monitor.record_cpu(start + Duration::from_secs(3), 12.0);
+        assert_eq!(monitor.cpu_percent, 150.0);
+
+        // No wall time passed: keep the last value; a counter going back reads as idle
+        monitor.record_cpu(start + Duration::from_secs(3), 20.0);
+        assert_eq!(monitor.cpu_percent, 150.0);
+        monitor.record_cpu(start + Duration::from_secs(4), 1.0);
+        assert_eq!(monitor.cpu_percent, 0.0);
+    }
+}
@@ -0,0 +1,243 @@
+// Interface up/down through an external command, with privilege checks and a dry-run mode
+use crate::system::SystemState;
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    // Whitespace-separated argv; {interface} and {state} (up/down) are substituted, no shell is involved
+    pub link_command: String,
+    // Run link_command without root, e.g. when it goes through sudo -n or a setuid helper
+    pub privileged_helper: bool,
+    pub dry_run: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            link_command: "ip link set dev {interface} {state}".to_string(),
+            privileged_helper: false,
+            dry_run: false,
+        }
+    }
+}
+
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        unsafe { libc::geteuid() == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+// What a privileged change does: only logged, run directly as root, run through a helper, or refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Privilege {
+    DryRun,
+    Root,
+    Helper,
+    ReadOnly,
+}
+
+impl Privilege {
+    pub fn new(dry_run: bool, root: bool, helper: bool) -> Self {
+        if dry_run {
+            Privilege::DryRun
+        } else if root {
+            Privilege::Root
+        } else if helper {
+            Privilege::Helper
+        } else {
+            Privilege::ReadOnly
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Privilege::DryRun => "dry-run",
+            Privilege::Root => "root",
+            Privilege::Helper => "helper",
+            Privilege::ReadOnly => "read-only",
+        }
+    }
+}
+
+pub struct LinkController {
+    config: NetworkConfig,
+    root: bool,
+    results_tx: Sender<(String, Result<String, String>)>,
+    results_rx: Receiver<(String, Result<String, String>)>,
+}
+
+impl LinkController {
+    pub fn new(config: &NetworkConfig) -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        Self {
+            config: config.clone(),
+            root: is_root(),
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn mode(&self) -> Privilege {
+        Privilege::new(self.config.dry_run, self.root, self.config.privileged_helper)
+    }
+
+    pub fn set_link(&mut self, system: &mut SystemState, interface: &str, up: bool) {
+        let state = if up { "up" } else { "down" };
+        let argv: Vec<String> = self
+            .config
+            .link_command
+            .split_whitespace()
+            .map(|arg| arg.replace("{interface}", interface).replace("{state}", state))
+            .collect();
+
+        match self.mode() {
+            Privilege::ReadOnly => {
+                system.log(
+                    "WARN",
+                    "Network",
+                    &format!(
+                        "Read-only: cannot bring {} {} without root (set network.privileged_helper or network.dry_run)",
+                        interface, state
+                    ),
+                );
+                return;
+            }
+            Privilege::DryRun => {
+                system.log("INFO", "Network", &format!("Dry run: {}", argv.join(" ")));
+                return;
+            }
+            Privilege::Root | Privilege::Helper => {}
+        }
+
+        let (program, args) = match argv.split_first() {
+            Some(split) => split,
+            None => {
+                system.log("ERROR", "Network", "network.link_command is empty");
+                return;
+            }
+        };
+        system.log("INFO", "Network", &format!("Running: {}", argv.join(" ")));
+
+        let tx = self.results_tx.clone();
+        let (program, args) = (program.clone(), args.to_vec());
+        let label = format!("{} {}", interface, state);
+        thread::spawn(move || {
+            let result = Command::new(&program)
+                .args(&args)
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("{}: {}", program, e))
+                .and_then(|output| {
+                    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                    if output.status.success() {
+                        Ok("ok".to_string())
+                    } else if stderr.is_empty() {
+                        Err(format!("{} failed with {}", program, output.status))
+                    } else {
+                        Err(stderr)
+                    }
+                });
+            let _ = tx.send((label, result));
+        });
+    }
+
+    pub fn collect_results(&mut self, system: &mut SystemState) {
+        while let Ok((label, result)) = self.results_rx.try_recv() {
+            match result {
+                Ok(status) => system.log("INFO", "Network", &format!("{}: {}", label, status)),
+                Err(err) => system.log("ERROR", "Network", &format!("{} failed: {}", label, err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    // Not root, whatever user the tests run as
+    fn controller(link_command: &str, privileged_helper: bool, dry_run: bool) -> LinkController {
+        let mut links = LinkController::new(&NetworkConfig { link_command: link_command.to_string(), privileged_helper, dry_run });
+        links.root = false;
+        links
+    }
+
+    fn last_log(system: &SystemState) -> (String, String) {
+        let entry = system.logs.last().unwrap();
+        (entry.level.clone(), entry.message.clone())
+    }
+
+    // Waits for the background command and returns the log line it produced
+    fn result_log(links: &mut LinkController, system: &mut SystemState) -> (String, String) {
+        let logs = system.logs.len();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while system.logs.len() == logs {
+            assert!(Instant::now() < deadline, "link command did not finish");
+            std::thread::sleep(Duration::from_millis(5));
+            links.collect_results(system);
+        }
+        last_log(system)
+    }
+
+    #[test]
+    fn privilege_modes() {
+        assert_eq!(Privilege::new(true, true, true), Privilege::DryRun);
+        assert_eq!(Privilege::new(false, true, false), Privilege::Root);
+        assert_eq!(Privilege::new(false, false, true), Privilege::Helper);
+        assert_eq!(Privilege::new(false, false, false), Privilege::ReadOnly);
+        assert_eq!(controller("true", false, false).mode().name(), "read-only");
+    }
+
+    #[test]
+    fn read_only_refuses() {
+        let mut system = SystemState::new();
+        let mut links = controller("touch /nonexistent/{interface}", false, false);
+        links.set_link(&mut system, "eth0", false);
+        let (level, message) = last_log(&system);
+        assert_eq!(level, "WARN");
+        assert!(message.starts_with("Read-only: cannot bring eth0 down without root"), "{}", message);
+        assert!(links.results_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn dry_run_logs_the_command() {
+        let mut system = SystemState::new();
+        let mut links = controller("ip link set dev {interface} {state}", true, true);
+        links.set_link(&mut system, "wlan0", true);
+        assert_eq!(last_log(&system), ("INFO".to_string(), "Dry run: ip link set dev wlan0 up".to_string()));
+    }
+
+    #[test]
+    fn helper_runs_the_command() {
+        let mut system = SystemState::new();
+        let mut links = controller("test {interface} = eth0", true, false);
+        links.set_link(&mut system, "eth0", false);
+        assert_eq!(last_log(&system).1, "Running: test eth0 = eth0");
+        assert_eq!(result_log(&mut links, &mut system), ("INFO".to_string(), "eth0 down: ok".to_string()));
+
+        links.set_link(&mut system, "eth1", true);
+        let (level, message) = result_log(&mut links, &mut system);
+        assert_eq!(level, "ERROR");
+        assert!(message.starts_with("eth1 up failed: test failed with"), "{}", message);
+
+        // Standard error is reported when there is some
+        let mut links = controller("ls /nonexistent/{interface}", true, false);
+        links.set_link(&mut system, "eth0", true);
+        let (_, message) = result_log(&mut links, &mut system);
+        assert!(message.starts_with("eth0 up failed: ls:"), "{}", message);
+
+        let mut links = controller("  ", true, false);
+        links.set_link(&mut system, "eth0", true);
+        assert_eq!(last_log(&system), ("ERROR".to_string(), "network.link_command is empty".to_string()));
+    }
+}
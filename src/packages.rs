@@ -0,0 +1,1138 @@
+// Package inventory backends: dpkg status files, the rpm database and Redox pkg TOML metadata
+use crate::netctl::{is_root, Privilege};
+use crate::pipeline::backoff;
+use crate::repos::RepositoryConfig;
+use crate::system::{Package, SystemState};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackagesConfig {
+    // "auto", "dpkg", "rpm" or "redox"
+    pub backend: String,
+    // Filesystem root holding the package database, e.g. a mounted image
+    pub root: PathBuf,
+    // Local Redox repository directory with a repo.toml index
+    pub repo: Option<PathBuf>,
+    // Only log the commands a plan would run
+    pub dry_run: bool,
+    // Command prefix used when not root, e.g. "sudo -n"
+    pub helper: Option<String>,
+    // Mirrors checked for reachability, index age and signatures
+    pub repositories: Vec<RepositoryConfig>,
+}
+
+impl Default for PackagesConfig {
+    fn default() -> Self {
+        Self {
+            backend: "auto".to_string(),
+            root: PathBuf::from("/"),
+            repo: None,
+            dry_run: false,
+            helper: None,
+            repositories: Vec::new(),
+        }
+    }
+}
+
+pub trait PackageBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn installed(&self) -> anyhow::Result<Vec<Package>>;
+    // Latest version of each package in the local repository index, with its size and dependencies
+    fn available(&self) -> anyhow::Result<HashMap<String, Package>> {
+        Ok(HashMap::new())
+    }
+    // Argv of each step that carries out the plan with the native tool
+    fn commands(&self, plan: &Plan) -> Vec<Vec<String>>;
+}
+
+pub fn open_backend(config: &PackagesConfig) -> Option<Arc<dyn PackageBackend>> {
+    let root = config.root.clone();
+    let dpkg = Arc::new(DpkgBackend { root: root.clone() });
+    let rpm = Arc::new(RpmBackend { root: root.clone() });
+    let redox = Arc::new(RedoxBackend { root: root.clone(), repo: config.repo.clone() });
+    match config.backend.as_str() {
+        "dpkg" => Some(dpkg),
+        "rpm" => Some(rpm),
+        "redox" => Some(redox),
+        _ if root.join("var/lib/dpkg/status").exists() => Some(dpkg),
+        _ if root.join("var/lib/rpm").is_dir() || root.join("usr/lib/sysimage/rpm").is_dir() => Some(rpm),
+        _ if root.join("pkg").is_dir() => Some(redox),
+        _ => None,
+    }
+}
+
+// Debian-style version ordering: epoch, upstream version, then revision after the last '-'; each
+// part compares alternating non-digit and numeric runs, where '~' sorts first
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (epoch_a, a) = split_epoch(a);
+    let (epoch_b, b) = split_epoch(b);
+    let (upstream_a, revision_a) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (upstream_b, revision_b) = b.rsplit_once('-').unwrap_or((b, ""));
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| compare_fragments(upstream_a, upstream_b))
+        .then_with(|| compare_fragments(revision_a, revision_b))
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if !epoch.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    }
+}
+
+fn compare_fragments(mut a: &str, mut b: &str) -> Ordering {
+    fn weight(c: Option<char>) -> i32 {
+        match c {
+            Some('~') => -1,
+            None => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(c) => c as i32 + 256,
+        }
+    }
+    fn split(s: &str, digits: bool) -> (&str, &str) {
+        let end = s.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(s.len());
+        s.split_at(end)
+    }
+
+    while !a.is_empty() || !b.is_empty() {
+        let (text_a, rest_a) = split(a, false);
+        let (text_b, rest_b) = split(b, false);
+        let (mut chars_a, mut chars_b) = (text_a.chars(), text_b.chars());
+        loop {
+            let (x, y) = (chars_a.next(), chars_b.next());
+            if x.is_none() && y.is_none() {
+                break;
+            }
+            match weight(x).cmp(&weight(y)) {
+                Ordering::Equal => {}
+                order => return order,
+            }
+        }
+
+        let (num_a, rest_a) = split(rest_a, true);
+        let (num_b, rest_b) = split(rest_b, true);
+        let (num_a, num_b) = (num_a.trim_start_matches('0'), num_b.trim_start_matches('0'));
+        match num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b)) {
+            Ordering::Equal => {}
+            order => return order,
+        }
+        a = rest_a;
+        b = rest_b;
+    }
+    Ordering::Equal
+}
+
+// Dpkg
+
+pub struct DpkgBackend {
+    root: PathBuf,
+}
+
+impl PackageBackend for DpkgBackend {
+    fn name(&self) -> &'static str {
+        "dpkg"
+    }
+
+    fn installed(&self) -> anyhow::Result<Vec<Package>> {
+        let path = self.root.join("var/lib/dpkg/status");
+        let text = fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(parse_dpkg_status(&text))
+    }
+
+    // apt's downloaded Packages indexes; compressed ones (lz4, gz) are skipped
+    fn available(&self) -> anyhow::Result<HashMap<String, Package>> {
+        let mut available = HashMap::new();
+        let lists = match fs::read_dir(self.root.join("var/lib/apt/lists")) {
+            Ok(lists) => lists,
+            Err(_) => return Ok(available),
+        };
+        for entry in lists.flatten() {
+            if !entry.file_name().to_string_lossy().ends_with("_Packages") {
+                continue;
+            }
+            let text = fs::read_to_string(entry.path())?;
+            for package in control_paragraphs(&text).filter_map(|fields| control_package(&fields)) {
+                newest(&mut available, package);
+            }
+        }
+        Ok(available)
+    }
+
+    fn commands(&self, plan: &Plan) -> Vec<Vec<String>> {
+        let apt = ["env", "DEBIAN_FRONTEND=noninteractive", "apt-get", "-y"];
+        let mut commands = Vec::new();
+        if !plan.remove.is_empty() {
+            commands.push(command(&[&apt[..], &["remove"]].concat(), plan.remove.iter().map(|p| &p.name)));
+        }
+        if !plan.install.is_empty() || !plan.upgrade.is_empty() {
+            let names = plan.install.iter().map(|p| &p.name).chain(plan.upgrade.iter().map(|u| &u.name));
+            commands.push(command(&[&apt[..], &["install"]].concat(), names));
+        }
+        if plan.clean_cache {
+            commands.push(command(&["apt-get", "clean"], std::iter::empty()));
+        }
+        commands
+    }
+}
+
+// Splits deb822 control data into paragraphs of single-line fields; continuation lines are dropped
+fn control_paragraphs(text: &str) -> impl Iterator<Item = HashMap<&str, &str>> {
+    text.split("\n\n").map(|paragraph| {
+        paragraph
+            .lines()
+            .filter(|line| !line.starts_with(' ') && !line.starts_with('\t'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key, value.trim()))
+            .collect()
+    })
+}
+
+pub fn parse_dpkg_status(text: &str) -> Vec<Package> {
+    control_paragraphs(text)
+        .filter(|fields| fields.get("Status").is_some_and(|status| status.ends_with(" installed")))
+        .filter_map(|fields| control_package(&fields))
+        .collect()
+}
+
+fn control_package(fields: &HashMap<&str, &str>) -> Option<Package> {
+    let depends = ["Pre-Depends", "Depends"]
+        .iter()
+        .filter_map(|key| fields.get(key))
+        .flat_map(|list| list.split(','))
+        .filter_map(|dependency| {
+            // First alternative, without version constraint or architecture qualifier
+            let name = dependency.split('|').next()?.split_whitespace().next()?;
+            Some(name.split(':').next().unwrap_or(name).to_string())
+        });
+    Some(Package {
+        name: fields.get("Package")?.to_string(),
+        version: fields.get("Version").unwrap_or(&"-").to_string(),
+        size_bytes: fields.get("Installed-Size").and_then(|kb| kb.parse::<u64>().ok()).map(|kb| kb * 1024),
+        depends: dedup(depends),
+        description: fields.get("Description").unwrap_or(&"").to_string(),
+        update: None,
+    })
+}
+
+// Rpm
+
+pub struct RpmBackend {
+    root: PathBuf,
+}
+
+impl PackageBackend for RpmBackend {
+    fn name(&self) -> &'static str {
+        "rpm"
+    }
+
+    fn installed(&self) -> anyhow::Result<Vec<Package>> {
+        let output = Command::new("rpm")
+            .arg("--root")
+            .arg(&self.root)
+            .args([
+                "-qa",
+                "--queryformat",
+                "%{NAME}\\t%|EPOCH?{%{EPOCH}:}:{}|%{VERSION}-%{RELEASE}\\t%{SIZE}\\t%{SUMMARY}\\t[%{REQUIRENAME} ]\\n",
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .context("running rpm")?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(parse_rpm_query(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn commands(&self, plan: &Plan) -> Vec<Vec<String>> {
+        native_commands(plan, &["dnf", "-y", "remove"], &["dnf", "-y", "install"], &["dnf", "-y", "upgrade"], &["dnf", "clean", "packages"])
+    }
+}
+
+pub fn parse_rpm_query(text: &str) -> Vec<Package> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.to_string();
+            let version = fields.next()?.to_string();
+            let size_bytes = fields.next()?.parse().ok();
+            let description = fields.next().unwrap_or("").to_string();
+            // Capabilities such as rpmlib(...), sonames and file paths are not package names
+            let depends = fields
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .filter(|capability| !capability.contains('(') && !capability.starts_with('/'))
+                .map(|capability| capability.to_string());
+            Some(Package { name, version, size_bytes, depends: dedup(depends), description, update: None })
+        })
+        .collect()
+}
+
+// Redox pkg: installed metadata in <root>/pkg/<name>.toml, repository index in <repo>/repo.toml
+
+#[derive(Debug, Deserialize)]
+struct RedoxPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    depends: Vec<String>,
+    storage_size: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RedoxRepo {
+    #[serde(default)]
+    packages: HashMap<String, String>,
+}
+
+pub struct RedoxBackend {
+    root: PathBuf,
+    repo: Option<PathBuf>,
+}
+
+impl PackageBackend for RedoxBackend {
+    fn name(&self) -> &'static str {
+        "redox"
+    }
+
+    fn installed(&self) -> anyhow::Result<Vec<Package>> {
+        let dir = self.root.join("pkg");
+        let mut packages = Vec::new();
+        for entry in fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                let package = read_toml::<RedoxPackage>(&path)?;
+                packages.push(Package {
+                    name: package.name,
+                    version: package.version,
+                    size_bytes: package.storage_size,
+                    depends: package.depends,
+                    description: String::new(),
+                    update: None,
+                });
+            }
+        }
+        Ok(packages)
+    }
+
+    // Per-package <repo>/<name>.toml metadata adds sizes and dependencies when present
+    fn available(&self) -> anyhow::Result<HashMap<String, Package>> {
+        let repo = match &self.repo {
+            Some(repo) => repo,
+            None => return Ok(HashMap::new()),
+        };
+        let index = read_toml::<RedoxRepo>(&repo.join("repo.toml"))?;
+        Ok(index
+            .packages
+            .into_iter()
+            .map(|(name, version)| {
+                let metadata = read_toml::<RedoxPackage>(&repo.join(format!("{}.toml", name))).ok();
+                let package = Package {
+                    name: name.clone(),
+                    version,
+                    size_bytes: metadata.as_ref().and_then(|metadata| metadata.storage_size),
+                    depends: metadata.map(|metadata| metadata.depends).unwrap_or_default(),
+                    description: String::new(),
+                    update: None,
+                };
+                (name, package)
+            })
+            .collect())
+    }
+
+    fn commands(&self, plan: &Plan) -> Vec<Vec<String>> {
+        native_commands(plan, &["pkg", "uninstall"], &["pkg", "install"], &["pkg", "upgrade"], &["pkg", "clean"])
+    }
+}
+
+fn command<'a>(program: &[&str], names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    program.iter().map(|arg| arg.to_string()).chain(names.cloned()).collect()
+}
+
+// Separate remove, install, upgrade and clean steps for tools that take each as a subcommand
+fn native_commands(plan: &Plan, remove: &[&str], install: &[&str], upgrade: &[&str], clean: &[&str]) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    if !plan.remove.is_empty() {
+        commands.push(command(remove, plan.remove.iter().map(|p| &p.name)));
+    }
+    if !plan.install.is_empty() {
+        commands.push(command(install, plan.install.iter().map(|p| &p.name)));
+    }
+    if !plan.upgrade.is_empty() {
+        commands.push(command(upgrade, plan.upgrade.iter().map(|u| &u.name)));
+    }
+    if plan.clean_cache {
+        commands.push(command(clean, std::iter::empty()));
+    }
+    commands
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+}
+
+fn newest(available: &mut HashMap<String, Package>, package: Package) {
+    match available.get(&package.name) {
+        Some(known) if compare_versions(&known.version, &package.version) != Ordering::Less => {}
+        _ => {
+            available.insert(package.name.clone(), package);
+        }
+    }
+}
+
+fn dedup(names: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names {
+        if !unique.contains(&name) {
+            unique.push(name);
+        }
+    }
+    unique
+}
+
+// Dependency graph over the installed packages
+
+pub struct PackageGraph<'a> {
+    by_name: HashMap<&'a str, &'a Package>,
+    dependents: HashMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> PackageGraph<'a> {
+    pub fn new(packages: &'a [Package]) -> Self {
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for package in packages {
+            for dependency in &package.depends {
+                dependents.entry(dependency.as_str()).or_default().push(&package.name);
+            }
+        }
+        Self {
+            by_name: packages.iter().map(|package| (package.name.as_str(), package)).collect(),
+            dependents,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'a Package> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn dependents(&self, name: &str) -> &[&'a str] {
+        self.dependents.get(name).map_or(&[], |names| names.as_slice())
+    }
+
+    // The package and everything it transitively depends on that is installed
+    pub fn closure(&self, name: &str) -> Vec<&'a Package> {
+        let mut closure = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            if let Some(package) = self.get(name).filter(|package| seen.insert(package.name.as_str())) {
+                closure.push(package);
+                pending.extend(package.depends.iter().map(|name| name.as_str()));
+            }
+        }
+        closure
+    }
+
+    // Groups of packages in the closure that depend on each other circularly (strongly connected components)
+    pub fn cycles(&self, name: &str) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan { graph: self, index: HashMap::new(), low: HashMap::new(), stack: Vec::new(), cycles: Vec::new() };
+        if let Some(package) = self.get(name) {
+            tarjan.visit(&package.name);
+        }
+        tarjan.cycles
+    }
+}
+
+struct Tarjan<'g, 'a> {
+    graph: &'g PackageGraph<'a>,
+    index: HashMap<&'a str, usize>,
+    low: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    cycles: Vec<Vec<String>>,
+}
+
+impl<'a> Tarjan<'_, 'a> {
+    fn visit(&mut self, name: &'a str) {
+        let index = self.index.len();
+        self.index.insert(name, index);
+        self.low.insert(name, index);
+        self.stack.push(name);
+
+        let depends = self.graph.get(name).map_or(&[][..], |package| package.depends.as_slice());
+        for dependency in depends {
+            let dependency = match self.graph.get(dependency) {
+                Some(package) => package.name.as_str(),
+                None => continue,
+            };
+            if !self.index.contains_key(dependency) {
+                self.visit(dependency);
+                let low = self.low[name].min(self.low[dependency]);
+                self.low.insert(name, low);
+            } else if self.stack.contains(&dependency) {
+                let low = self.low[name].min(self.index[dependency]);
+                self.low.insert(name, low);
+            }
+        }
+
+        if self.low[name] == index {
+            let start = self.stack.iter().rposition(|node| *node == name).unwrap_or(0);
+            let mut component: Vec<String> = self.stack.drain(start..).map(String::from).collect();
+            let self_loop = depends.iter().any(|dependency| dependency == name);
+            if component.len() > 1 || self_loop {
+                component.sort();
+                self.cycles.push(component);
+            }
+        }
+    }
+}
+
+// Operations queue and dry-run planning
+
+// action is "install", "upgrade", "remove" or "clean"
+pub struct QueuedOp {
+    pub action: &'static str,
+    pub package: String,
+}
+
+pub struct Upgrade {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Default)]
+pub struct Plan {
+    pub install: Vec<Package>,
+    pub upgrade: Vec<Upgrade>,
+    pub remove: Vec<Package>,
+    // Requested or required packages that are neither installed nor in the index
+    pub missing: Vec<String>,
+    // Packages being removed that an install or upgrade still needs
+    pub conflicts: Vec<String>,
+    pub clean_cache: bool,
+    pub size_delta: i64,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty() && self.upgrade.is_empty() && self.remove.is_empty() && !self.clean_cache
+    }
+}
+
+// What the queue would change: removals take their installed dependents along, and installs and
+// upgrades pull in dependencies from the index that are not installed yet
+pub fn plan(installed: &[Package], index: &HashMap<String, Package>, queue: &[QueuedOp]) -> Plan {
+    let graph = PackageGraph::new(installed);
+    let size = |package: &Package| package.size_bytes.unwrap_or(0) as i64;
+    let mut plan = Plan { clean_cache: queue.iter().any(|op| op.action == "clean"), ..Plan::default() };
+
+    let mut pending: Vec<&str> = queue.iter().filter(|op| op.action == "remove").map(|op| op.package.as_str()).collect();
+    while let Some(name) = pending.pop() {
+        if let Some(package) = graph.get(name).filter(|package| !plan.remove.iter().any(|p| p.name == package.name)) {
+            plan.size_delta -= size(package);
+            plan.remove.push(package.clone());
+            pending.extend(graph.dependents(name));
+        }
+    }
+
+    let mut required: Vec<String> = Vec::new();
+    let removing = |plan: &Plan, name: &str| plan.remove.iter().any(|p| p.name == name);
+    for op in queue.iter().filter(|op| op.action == "upgrade") {
+        let (current, available) = match (graph.get(&op.package), index.get(&op.package)) {
+            (Some(current), Some(available)) => (current, available),
+            _ => continue,
+        };
+        if removing(&plan, &current.name) {
+            plan.conflicts.push(current.name.clone());
+            continue;
+        }
+        if compare_versions(&available.version, &current.version) == Ordering::Greater {
+            plan.size_delta += size(available) - size(current);
+            plan.upgrade.push(Upgrade { name: current.name.clone(), from: current.version.clone(), to: available.version.clone() });
+            required.extend(available.depends.iter().cloned());
+        }
+    }
+
+    required.extend(queue.iter().filter(|op| op.action == "install").map(|op| op.package.clone()));
+    required.reverse();
+    while let Some(name) = required.pop() {
+        if removing(&plan, &name) {
+            if !plan.conflicts.contains(&name) {
+                plan.conflicts.push(name);
+            }
+            continue;
+        }
+        if graph.get(&name).is_some() || plan.install.iter().any(|p| p.name == name) {
+            continue;
+        }
+        match index.get(&name) {
+            Some(package) => {
+                plan.size_delta += size(package);
+                plan.install.push(package.clone());
+                required.extend(package.depends.iter().rev().cloned());
+            }
+            None if !plan.missing.contains(&name) => plan.missing.push(name),
+            None => {}
+        }
+    }
+    plan
+}
+
+enum OpEvent {
+    Step(usize, usize, String),
+    Output(String),
+    Finished(anyhow::Result<()>),
+}
+
+pub struct OpRun {
+    pub step: usize,
+    pub steps: usize,
+    pub command: String,
+}
+
+const MAX_OUTPUT_LINES: usize = 500;
+
+fn run_streaming(argv: &[String], tx: &Sender<OpEvent>) -> anyhow::Result<()> {
+    let (program, args) = argv.split_first().context("empty command")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("starting {}", program))?;
+
+    let stderr = child.stderr.take();
+    let stderr_tx = tx.clone();
+    let stderr_reader = thread::spawn(move || {
+        if let Some(stderr) = stderr {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let _ = stderr_tx.send(OpEvent::Output(line));
+            }
+        }
+    });
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = tx.send(OpEvent::Output(line));
+        }
+    }
+    let _ = stderr_reader.join();
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+// Manager
+
+struct Inventory {
+    packages: Vec<Package>,
+    index: HashMap<String, Package>,
+}
+
+pub struct PackageManager {
+    backend: Option<Arc<dyn PackageBackend>>,
+    config: PackagesConfig,
+    pub index: HashMap<String, Package>,
+    interval: Duration,
+    pub loaded_at: Option<Instant>,
+    loading: bool,
+    failures: u32,
+    results_tx: Sender<anyhow::Result<Inventory>>,
+    results_rx: Receiver<anyhow::Result<Inventory>>,
+    pub queue: Vec<QueuedOp>,
+    pub plan: Plan,
+    pub run: Option<OpRun>,
+    pub output: Vec<String>,
+    events_tx: Sender<OpEvent>,
+    events_rx: Receiver<OpEvent>,
+}
+
+impl PackageManager {
+    pub fn new(config: &PackagesConfig, interval: Duration) -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        let (events_tx, events_rx) = mpsc::channel();
+        Self {
+            backend: open_backend(config),
+            config: config.clone(),
+            index: HashMap::new(),
+            interval,
+            loaded_at: None,
+            loading: false,
+            failures: 0,
+            results_tx,
+            results_rx,
+            queue: Vec::new(),
+            plan: Plan::default(),
+            run: None,
+            output: Vec::new(),
+            events_tx,
+            events_rx,
+        }
+    }
+
+    pub fn mode(&self) -> Privilege {
+        Privilege::new(self.config.dry_run, is_root(), self.config.helper.is_some())
+    }
+
+    pub fn queued(&self, package: &str) -> Option<&'static str> {
+        self.queue.iter().find(|op| op.package == package).map(|op| op.action)
+    }
+
+    // Queues the action for the package, or unqueues it when it is already queued
+    pub fn toggle(&mut self, action: &'static str, package: &str, installed: &[Package]) {
+        let queued = self.queued(package);
+        self.queue.retain(|op| op.package != package);
+        if queued != Some(action) {
+            self.queue.push(QueuedOp { action, package: package.to_string() });
+        }
+        self.replan(installed);
+    }
+
+    pub fn queue_install(&mut self, names: &str, installed: &[Package]) {
+        for name in names.split_whitespace() {
+            self.queue.retain(|op| op.package != name);
+            self.queue.push(QueuedOp { action: "install", package: name.to_string() });
+        }
+        self.replan(installed);
+    }
+
+    pub fn clear_queue(&mut self, installed: &[Package]) {
+        self.queue.clear();
+        self.replan(installed);
+    }
+
+    pub fn replan(&mut self, installed: &[Package]) {
+        self.plan = plan(installed, &self.index, &self.queue);
+    }
+
+    // Runs the planned commands on a background thread after the user confirmed the plan
+    pub fn apply(&mut self, system: &mut SystemState) {
+        let backend = match &self.backend {
+            Some(backend) if self.run.is_none() && !self.plan.is_empty() => backend.clone(),
+            _ => return,
+        };
+        let helper: Vec<String> = self.config.helper.iter().flat_map(|helper| helper.split_whitespace()).map(String::from).collect();
+        let commands: Vec<Vec<String>> = backend
+            .commands(&self.plan)
+            .into_iter()
+            .map(|argv| match self.mode() {
+                Privilege::Helper => helper.iter().cloned().chain(argv).collect(),
+                _ => argv,
+            })
+            .collect();
+        match self.mode() {
+            Privilege::ReadOnly => {
+                system.log("WARN", "Packages", "Read-only: package changes need root (set packages.helper or packages.dry_run)");
+                return;
+            }
+            Privilege::DryRun => {
+                for argv in &commands {
+                    system.log("INFO", "Packages", &format!("Dry run: {}", argv.join(" ")));
+                }
+                self.output = commands.iter().map(|argv| format!("Dry run: {}", argv.join(" "))).collect();
+                return;
+            }
+            Privilege::Root | Privilege::Helper => {}
+        }
+        // The native tools always act on the running system
+        if self.config.root != Path::new("/") {
+            system.log("WARN", "Packages", &format!("Read-only: packages under {} are not modified", self.config.root.display()));
+            return;
+        }
+
+        self.output.clear();
+        self.run = Some(OpRun { step: 0, steps: commands.len(), command: String::new() });
+        let tx = self.events_tx.clone();
+        thread::spawn(move || {
+            let steps = commands.len();
+            for (i, argv) in commands.iter().enumerate() {
+                let _ = tx.send(OpEvent::Step(i + 1, steps, argv.join(" ")));
+                if let Err(err) = run_streaming(argv, &tx) {
+                    let _ = tx.send(OpEvent::Finished(Err(err)));
+                    return;
+                }
+            }
+            let _ = tx.send(OpEvent::Finished(Ok(())));
+        });
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.as_ref().map_or("simulated", |backend| backend.name())
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    // Reads the database on a background thread; rpm -qa can take a few seconds
+    pub fn refresh(&mut self) {
+        let backend = match &self.backend {
+            Some(backend) if !self.loading => backend.clone(),
+            _ => return,
+        };
+        if self.loaded_at.is_some_and(|at| at.elapsed() < backoff(self.interval, self.failures)) {
+            return;
+        }
+        self.loading = true;
+        let tx = self.results_tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(load_inventory(backend.as_ref()));
+        });
+    }
+
+    pub fn collect_results(&mut self, system: &mut SystemState) {
+        while let Ok(result) = self.results_rx.try_recv() {
+            self.loading = false;
+            self.loaded_at = Some(Instant::now());
+            match result {
+                Ok(inventory) => {
+                    if self.failures > 0 {
+                        system.log("INFO", "Packages", &format!("{} backend recovered", self.backend_name()));
+                        system.subsystem_status.insert("Package Manager".to_string(), "ONLINE".to_string());
+                    }
+                    self.failures = 0;
+                    self.index = inventory.index;
+                    system.packages = inventory.packages;
+                    self.replan(&system.packages);
+                }
+                Err(err) => {
+                    // Keep the backend and retry less often, so a transient failure is not permanent
+                    self.failures += 1;
+                    let retry = backoff(self.interval, self.failures);
+                    let message = format!("{} backend failed: {:#}; retrying in {}s", self.backend_name(), err, retry.as_secs());
+                    system.log("ERROR", "Packages", &message);
+                    system.subsystem_status.insert("Package Manager".to_string(), "DEGRADED".to_string());
+                }
+            }
+        }
+
+        while let Ok(event) = self.events_rx.try_recv() {
+            match event {
+                OpEvent::Step(step, steps, command) => {
+                    system.log("INFO", "Packages", &format!("[{}/{}] Running: {}", step, steps, command));
+                    self.run = Some(OpRun { step, steps, command });
+                }
+                OpEvent::Output(line) => {
+                    system.log("INFO", "Packages", &line);
+                    self.output.push(line);
+                    if self.output.len() > MAX_OUTPUT_LINES {
+                        self.output.remove(0);
+                    }
+                }
+                OpEvent::Finished(result) => {
+                    self.run = None;
+                    match result {
+                        Ok(()) => {
+                            system.log("INFO", "Packages", "Package operations finished");
+                            self.queue.clear();
+                        }
+                        Err(err) => system.log("ERROR", "Packages", &format!("Package operations failed: {:#}", err)),
+                    }
+                    // Re-read the database so the list and plan reflect what actually changed
+                    self.loaded_at = None;
+                    self.replan(&system.packages);
+                }
+            }
+        }
+    }
+}
+
+fn load_inventory(backend: &dyn PackageBackend) -> anyhow::Result<Inventory> {
+    let mut packages = backend.installed()?;
+    let index = backend.available()?;
+    for package in &mut packages {
+        package.update = index
+            .get(&package.name)
+            .filter(|available| compare_versions(&available.version, &package.version) == Ordering::Greater)
+            .map(|available| available.version.clone());
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(Inventory { packages, index })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_ordering() {
+        // Expected results checked against dpkg --compare-versions
+        let cases = [
+            ("1.0~rc1", "1.0", Ordering::Less),
+            ("1:0.9", "2.0", Ordering::Greater),
+            ("1.0a", "1.0+", Ordering::Less),
+            ("1.0", "1.0", Ordering::Equal),
+            ("1.00", "1.0", Ordering::Equal),
+            ("1.2", "1.10", Ordering::Less),
+            ("1.0", "1.0a", Ordering::Less),
+            ("1.0~~", "1.0~", Ordering::Less),
+            ("1.0~~a", "1.0~", Ordering::Less),
+            ("2.0-1", "2.0-2", Ordering::Less),
+            ("1.0-1", "1.0a-1", Ordering::Less),
+            ("1.0-10", "1.0-9", Ordering::Greater),
+            ("0:1.0", "1.0", Ordering::Equal),
+            ("1.0", "1.0-0", Ordering::Equal),
+            ("1.0-1~bpo1", "1.0-1", Ordering::Less),
+            ("2:1.0", "1:9.9", Ordering::Greater),
+            ("1.0.1", "1.0-1", Ordering::Greater),
+            ("7.4.052-1ubuntu3", "7.4.052-1ubuntu3.1", Ordering::Less),
+            ("1:2.38.1-5.fc39", "1:2.38.1-4.fc39", Ordering::Greater),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(compare_versions(a, b), expected, "{} vs {}", a, b);
+            assert_eq!(compare_versions(b, a), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+
+    const DPKG_STATUS: &str = "\
+Package: libc6
+Status: install ok installed
+Priority: optional
+Installed-Size: 13220
+Architecture: amd64
+Version: 2.36-9+deb12u4
+Depends: libgcc-s1
+Description: GNU C Library: Shared libraries
+ Contains the standard libraries that are used by nearly all programs on
+ the system.
+
+Package: curl
+Status: install ok installed
+Installed-Size: 500
+Version: 7.88.1-10+deb12u5
+Pre-Depends: libc6 (>= 2.34)
+Depends: libc6 (>= 2.17), libcurl4 (= 7.88.1-10+deb12u5), zlib1g:amd64 (>= 1:1.1.4), default-mta | mail-transport-agent
+Description: command line tool for transferring data with URL syntax
+
+Package: old-config
+Status: deinstall ok config-files
+Version: 1.0-1
+Description: removed, only configuration left
+
+Package: vim-tiny
+Status: hold ok installed
+Version: 2:9.0.1378-2
+";
+
+    #[test]
+    fn dpkg_status_installed_packages() {
+        let packages = parse_dpkg_status(DPKG_STATUS);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["libc6", "curl", "vim-tiny"]);
+
+        let libc = &packages[0];
+        assert_eq!(libc.version, "2.36-9+deb12u4");
+        assert_eq!(libc.size_bytes, Some(13220 * 1024));
+        assert_eq!(libc.depends, ["libgcc-s1"]);
+        // Continuation lines of the long description are dropped
+        assert_eq!(libc.description, "GNU C Library: Shared libraries");
+
+        // Constraints, architecture qualifiers and later alternatives are stripped; duplicates collapse
+        assert_eq!(packages[1].depends, ["libc6", "libcurl4", "zlib1g", "default-mta"]);
+
+        let vim = &packages[2];
+        assert_eq!(vim.version, "2:9.0.1378-2");
+        assert_eq!(vim.size_bytes, None);
+        assert!(vim.depends.is_empty());
+    }
+
+    #[test]
+    fn rpm_query_lines() {
+        let text = "\
+bash\t5.2.15-5.fc39\t8036235\tThe GNU Bourne Again shell\tfilesystem libc.so.6()(64bit) /bin/sh rpmlib(CompressedFileNames) ncurses-libs filesystem \n\
+shadow-utils\t2:4.14.0-2.fc39\t3901345\tUtilities for managing accounts and shadow password files\tlibacl.so.1()(64bit) setup \n\
+gpg-pubkey\t18b8e74c-62f2920f\t0\tgpg(Fedora (39) <fedora-39-primary@fedoraproject.org>)\t\n\
+truncated\n";
+        let packages = parse_rpm_query(text);
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["bash", "shadow-utils", "gpg-pubkey"]);
+
+        let bash = &packages[0];
+        assert_eq!(bash.version, "5.2.15-5.fc39");
+        assert_eq!(bash.size_bytes, Some(8036235));
+        assert_eq!(bash.description, "The GNU Bourne Again shell");
+        // Sonames, file paths and rpmlib capabilities are not packages
+        assert_eq!(bash.depends, ["filesystem", "ncurses-libs"]);
+
+        assert_eq!(packages[1].version, "2:4.14.0-2.fc39");
+        assert_eq!(packages[1].depends, ["setup"]);
+        assert!(packages[2].depends.is_empty());
+    }
+
+    fn package(name: &str, version: &str, size_kb: u64, depends: &[&str]) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            size_bytes: Some(size_kb * 1024),
+            depends: depends.iter().map(|name| name.to_string()).collect(),
+            description: String::new(),
+            update: None,
+        }
+    }
+
+    fn names(packages: &[&Package]) -> Vec<String> {
+        let mut names: Vec<String> = packages.iter().map(|p| p.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    fn cyclic_packages() -> Vec<Package> {
+        vec![
+            package("app", "1.0", 10, &["libfoo", "libbar", "missing"]),
+            package("libfoo", "1.0", 10, &["libbar"]),
+            package("libbar", "1.0", 10, &["libbaz"]),
+            package("libbaz", "1.0", 10, &["libfoo", "libc"]),
+            package("libc", "1.0", 10, &["libc"]),
+            package("tool", "1.0", 10, &["libc"]),
+        ]
+    }
+
+    #[test]
+    fn graph_closure_and_dependents() {
+        let packages = cyclic_packages();
+        let graph = PackageGraph::new(&packages);
+
+        assert_eq!(names(&graph.closure("app")), ["app", "libbar", "libbaz", "libc", "libfoo"]);
+        assert_eq!(names(&graph.closure("tool")), ["libc", "tool"]);
+        assert!(graph.closure("missing").is_empty());
+
+        let mut dependents = graph.dependents("libc").to_vec();
+        dependents.sort();
+        assert_eq!(dependents, ["libbaz", "libc", "tool"]);
+        assert!(graph.dependents("app").is_empty());
+    }
+
+    #[test]
+    fn graph_cycles() {
+        let packages = cyclic_packages();
+        let graph = PackageGraph::new(&packages);
+
+        // libfoo -> libbar -> libbaz -> libfoo, plus libc depending on itself
+        let mut cycles = graph.cycles("app");
+        cycles.sort();
+        assert_eq!(cycles, [vec!["libbar", "libbaz", "libfoo"], vec!["libc"]]);
+        assert_eq!(graph.cycles("tool"), [vec!["libc"]]);
+        assert!(graph.cycles("missing").is_empty());
+
+        let acyclic = vec![package("a", "1", 1, &["b", "c"]), package("b", "1", 1, &["c"]), package("c", "1", 1, &[])];
+        assert!(PackageGraph::new(&acyclic).cycles("a").is_empty());
+    }
+
+    fn op(action: &'static str, package: &str) -> QueuedOp {
+        QueuedOp { action, package: package.to_string() }
+    }
+
+    fn index(packages: Vec<Package>) -> HashMap<String, Package> {
+        packages.into_iter().map(|package| (package.name.clone(), package)).collect()
+    }
+
+    fn plan_names(packages: &[Package]) -> Vec<&str> {
+        packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn installed() -> Vec<Package> {
+        vec![
+            package("libc", "2.36", 100, &[]),
+            package("libssl", "3.0.1", 40, &["libc"]),
+            package("curl", "7.88", 20, &["libssl", "libc"]),
+            package("git", "2.39", 200, &["curl"]),
+            package("vim", "9.0", 30, &["libc"]),
+        ]
+    }
+
+    #[test]
+    fn plan_install_pulls_dependencies() {
+        let available = index(vec![
+            package("htop", "3.2", 15, &["libncurses", "libc", "libcap"]),
+            package("libncurses", "6.4", 25, &["libtinfo"]),
+            package("libtinfo", "6.4", 5, &[]),
+        ]);
+        let plan = plan(&installed(), &available, &[op("install", "htop"), op("install", "nonexistent")]);
+
+        assert_eq!(plan_names(&plan.install), ["htop", "libncurses", "libtinfo"]);
+        assert_eq!(plan.missing, ["libcap", "nonexistent"]);
+        assert_eq!(plan.size_delta, (15 + 25 + 5) * 1024);
+        assert!(plan.remove.is_empty() && plan.upgrade.is_empty() && plan.conflicts.is_empty());
+        assert!(!plan.clean_cache && !plan.is_empty());
+    }
+
+    #[test]
+    fn plan_remove_takes_dependents() {
+        let plan = plan(&installed(), &HashMap::new(), &[op("remove", "libssl"), op("remove", "not-installed")]);
+        let mut removed = plan_names(&plan.remove);
+        removed.sort();
+        assert_eq!(removed, ["curl", "git", "libssl"]);
+        assert_eq!(plan.size_delta, -(40 + 20 + 200) * 1024);
+    }
+
+    #[test]
+    fn plan_upgrade_only_newer() {
+        let available = index(vec![
+            package("curl", "8.5", 22, &["libssl", "libnghttp2"]),
+            package("libnghttp2", "1.52", 8, &[]),
+            package("vim", "9.0", 30, &[]),
+            package("git", "2.30", 190, &[]),
+        ]);
+        let plan = plan(
+            &installed(),
+            &available,
+            &[op("upgrade", "curl"), op("upgrade", "vim"), op("upgrade", "git"), op("upgrade", "unknown")],
+        );
+
+        assert_eq!(plan.upgrade.len(), 1);
+        let upgrade = &plan.upgrade[0];
+        assert_eq!((upgrade.name.as_str(), upgrade.from.as_str(), upgrade.to.as_str()), ("curl", "7.88", "8.5"));
+        // New dependencies of the upgraded version are installed too
+        assert_eq!(plan_names(&plan.install), ["libnghttp2"]);
+        assert_eq!(plan.size_delta, (2 + 8) * 1024);
+    }
+
+    #[test]
+    fn plan_conflicts_with_removals() {
+        let available = index(vec![
+            package("curl", "8.5", 22, &["libssl"]),
+            package("wget", "1.21", 10, &["libssl"]),
+        ]);
+        let plan = plan(
+            &installed(),
+            &available,
+            &[op("remove", "libssl"), op("upgrade", "curl"), op("install", "wget"), op("clean", "")],
+        );
+
+        // curl is removed along with libssl, so it cannot be upgraded, and wget still needs libssl
+        assert_eq!(plan.conflicts, ["curl", "libssl"]);
+        assert!(plan.upgrade.is_empty());
+        assert_eq!(plan_names(&plan.install), ["wget"]);
+        assert!(plan.clean_cache);
+    }
+
+    #[test]
+    fn plan_empty_queue() {
+        let empty = plan(&installed(), &HashMap::new(), &[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.size_delta, 0);
+
+        let clean_only = plan(&installed(), &HashMap::new(), &[op("clean", "")]);
+        assert!(!clean_only.is_empty());
+    }
+}
@@ -0,0 +1,467 @@
+// Collection pipeline: collectors sample /proc and /sys on tokio tasks and publish over a channel,
+// the UI thread only applies the newest sample of each collector before drawing
+use crate::probes::parse_duration;
+use crate::procfs::{self, CpuTimes, DiskStats, LinkInfo, NetDevCounters};
+use crate::system::{self, Connection, FileSystem, SystemState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, Runtime};
+use tokio::sync::Notify;
+use tokio::time::MissedTickBehavior;
+
+// A collector still running after this is flagged SLOW; its next run waits until it returns
+const SLOW_COLLECTOR: Duration = Duration::from_secs(2);
+// Longest wait between retries of a failing backend, unless its own interval is longer
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshConfig {
+    // Most frames drawn per second; a frame is only drawn after input or new data
+    pub fps: u32,
+    // Alerts, plugins, history and the results of background jobs
+    pub tick: String,
+    pub cpu: String,
+    pub disks: String,
+    pub network: String,
+    pub filesystems: String,
+    pub connections: String,
+    pub services: String,
+    pub packages: String,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            tick: "1s".to_string(),
+            cpu: "1s".to_string(),
+            disks: "1s".to_string(),
+            network: "1s".to_string(),
+            filesystems: "10s".to_string(),
+            connections: "2s".to_string(),
+            services: "5s".to_string(),
+            packages: "5m".to_string(),
+        }
+    }
+}
+
+impl RefreshConfig {
+    pub fn frame_rate(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps.clamp(1, 120) as f64)
+    }
+
+    pub fn tick_rate(&self, system: &mut SystemState) -> Duration {
+        resolve("tick", &self.tick, &Self::default().tick, system)
+    }
+
+    pub fn services_rate(&self, system: &mut SystemState) -> Duration {
+        resolve("services", &self.services, &Self::default().services, system)
+    }
+
+    pub fn packages_rate(&self, system: &mut SystemState) -> Duration {
+        resolve("packages", &self.packages, &Self::default().packages, system)
+    }
+
+    pub fn collector_rate(&self, collector: Collector, system: &mut SystemState) -> Duration {
+        let defaults = Self::default();
+        let (value, default) = match collector {
+            Collector::Cpu => (&self.cpu, defaults.cpu),
+            Collector::Disks => (&self.disks, defaults.disks),
+            Collector::Network => (&self.network, defaults.network),
+            Collector::Filesystems => (&self.filesystems, defaults.filesystems),
+            Collector::Connections => (&self.connections, defaults.connections),
+        };
+        resolve(collector.name(), value, &default, system)
+    }
+}
+
+// An interval that does not parse is logged and replaced by the default
+fn resolve(name: &str, value: &str, default: &str, system: &mut SystemState) -> Duration {
+    parse_duration(value).unwrap_or_else(|| {
+        system.log("WARN", "Config", &format!("Invalid refresh.{} '{}', using {}", name, value, default));
+        parse_duration(default).unwrap_or(Duration::from_secs(1))
+    })
+}
+
+// Doubles the interval for every consecutive failure of a service or package backend
+pub fn backoff(interval: Duration, failures: u32) -> Duration {
+    interval.saturating_mul(1 << failures.min(16)).min(MAX_BACKOFF.max(interval))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collector {
+    Cpu,
+    Disks,
+    Network,
+    Filesystems,
+    Connections,
+}
+
+pub const COLLECTORS: [Collector; 5] =
+    [Collector::Cpu, Collector::Disks, Collector::Network, Collector::Filesystems, Collector::Connections];
+
+impl Collector {
+    pub fn name(self) -> &'static str {
+        match self {
+            Collector::Cpu => "cpu",
+            Collector::Disks => "disks",
+            Collector::Network => "network",
+            Collector::Filesystems => "filesystems",
+            Collector::Connections => "connections",
+        }
+    }
+
+    // Blocking; runs on the runtime's blocking pool
+    fn collect(self) -> Result<Sample, String> {
+        match self {
+            Collector::Cpu => match procfs::read_cpu_times() {
+                Ok(times) if !times.is_empty() => Ok(Sample::Cpu(times)),
+                Ok(_) => Err("no cpu lines in /proc/stat".to_string()),
+                Err(err) => Err(err.to_string()),
+            },
+            Collector::Disks => {
+                let at = Instant::now();
+                system::read_disks().map(|stats| Sample::Disks { stats, at }).map_err(|e| e.to_string())
+            }
+            Collector::Network => {
+                let at = Instant::now();
+                let counters = procfs::read_net_dev().map_err(|e| e.to_string())?;
+                let links = counters
+                    .iter()
+                    .filter_map(|dev| procfs::read_link_info(&dev.name).ok().map(|link| (dev.name.clone(), link)))
+                    .collect();
+                let addresses = procfs::read_interface_addresses().ok();
+                Ok(Sample::Network(NetworkSample { at, counters, links, addresses }))
+            }
+            Collector::Filesystems => system::read_filesystems().map(Sample::Filesystems).map_err(|e| e.to_string()),
+            Collector::Connections => system::read_connections().map(Sample::Connections).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+pub enum Sample {
+    Cpu(Vec<(String, CpuTimes)>),
+    Disks { stats: Vec<DiskStats>, at: Instant },
+    Network(NetworkSample),
+    Filesystems(Vec<FileSystem>),
+    Connections(Vec<Connection>),
+}
+
+pub struct NetworkSample {
+    pub at: Instant,
+    pub counters: Vec<NetDevCounters>,
+    pub links: Vec<(String, LinkInfo)>,
+    pub addresses: Option<Vec<(String, String)>>,
+}
+
+enum Report {
+    Slow(usize),
+    Done { index: usize, elapsed: Duration, finished: Instant, result: Result<Sample, String> },
+}
+
+pub struct CollectorStatus {
+    pub collector: Collector,
+    pub interval: Duration,
+    pub enabled: bool,
+    pub slow: bool,
+    pub runs: u64,
+    pub errors: u64,
+    // Samples replaced by a newer one before the UI got to apply them
+    pub superseded: u64,
+    pub last_duration: Duration,
+    pub max_duration: Duration,
+    total_duration: Duration,
+    pub last_sample: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl CollectorStatus {
+    pub fn average(&self) -> Duration {
+        self.total_duration.checked_div(self.runs as u32).unwrap_or_default()
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.runs > self.errors
+    }
+
+    pub fn state(&self) -> &'static str {
+        if !self.enabled {
+            "PAUSED"
+        } else if self.slow {
+            "SLOW"
+        } else if self.last_error.is_some() {
+            "FAILING"
+        } else if self.runs == 0 {
+            "WAITING"
+        } else {
+            "OK"
+        }
+    }
+}
+
+pub struct Pipeline {
+    runtime: Option<Runtime>,
+    pub status: Vec<CollectorStatus>,
+    // Time the UI thread spent applying the last batch of samples
+    pub apply_time: Duration,
+    // How long the oldest sample of the last batch waited in the channel
+    pub queue_latency: Duration,
+    enabled: Vec<Arc<AtomicBool>>,
+    refresh: Arc<Notify>,
+    reports_rx: Receiver<Report>,
+}
+
+impl Pipeline {
+    pub fn new(config: &RefreshConfig, system: &mut SystemState) -> Self {
+        let (reports_tx, reports_rx) = mpsc::channel();
+        let refresh = Arc::new(Notify::new());
+        // Socket tables are only read while the connections view is open
+        let enabled: Vec<Arc<AtomicBool>> = COLLECTORS
+            .iter()
+            .map(|collector| Arc::new(AtomicBool::new(*collector != Collector::Connections)))
+            .collect();
+        let status: Vec<CollectorStatus> = COLLECTORS
+            .iter()
+            .zip(&enabled)
+            .map(|(collector, enabled)| CollectorStatus {
+                collector: *collector,
+                interval: config.collector_rate(*collector, system),
+                enabled: enabled.load(Ordering::Relaxed),
+                slow: false,
+                runs: 0,
+                errors: 0,
+                superseded: 0,
+                last_duration: Duration::ZERO,
+                max_duration: Duration::ZERO,
+                total_duration: Duration::ZERO,
+                last_sample: None,
+                last_error: None,
+            })
+            .collect();
+
+        let runtime = match tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("collector")
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => Some(runtime),
+            Err(err) => {
+                system.log("ERROR", "Collector", &format!("Cannot start collection runtime, using simulated data: {}", err));
+                None
+            }
+        };
+        if let Some(runtime) = &runtime {
+            for (index, status) in status.iter().enumerate() {
+                let (collector, interval) = (status.collector, status.interval);
+                runtime.spawn(collect_loop(index, collector, interval, enabled[index].clone(), refresh.clone(), reports_tx.clone()));
+            }
+        }
+
+        Self { runtime, status, apply_time: Duration::ZERO, queue_latency: Duration::ZERO, enabled, refresh, reports_rx }
+    }
+
+    // Shared with the command probes
+    pub fn handle(&self) -> Option<Handle> {
+        self.runtime.as_ref().map(|runtime| runtime.handle().clone())
+    }
+
+    pub fn set_enabled(&mut self, collector: Collector, enabled: bool) {
+        if let Some(index) = COLLECTORS.iter().position(|c| *c == collector) {
+            self.enabled[index].store(enabled, Ordering::Relaxed);
+            self.status[index].enabled = enabled;
+        }
+    }
+
+    // Collectors that are idle sample right away instead of waiting for their next tick
+    pub fn refresh_now(&self) {
+        self.refresh.notify_waiters();
+    }
+
+    // How long until the next sample is expected; a sample that is already late is looked for
+    // again after `recheck`
+    pub fn next_sample(&self, recheck: Duration) -> Duration {
+        self.status
+            .iter()
+            .filter(|status| status.enabled)
+            .map(|status| match status.last_sample {
+                Some(at) => status.interval.saturating_sub(at.elapsed()),
+                None => Duration::ZERO,
+            })
+            .min()
+            .filter(|wait| !wait.is_zero())
+            .unwrap_or(recheck)
+    }
+
+    // Returns whether anything changed, so the caller knows to redraw
+    pub fn collect_results(&mut self, system: &mut SystemState) -> bool {
+        let started = Instant::now();
+        if self.runtime.is_none() {
+            let mut changed = false;
+            for status in &mut self.status {
+                let due = status.last_sample.is_none_or(|at| at.elapsed() >= status.interval);
+                if due && matches!(status.collector, Collector::Cpu | Collector::Disks | Collector::Network) {
+                    system.simulate(status.collector);
+                    status.last_sample = Some(Instant::now());
+                    changed = true;
+                }
+            }
+            return changed;
+        }
+
+        let mut changed = false;
+        let mut queue_latency = Duration::ZERO;
+        let mut latest: Vec<Option<Result<Sample, String>>> = COLLECTORS.iter().map(|_| None).collect();
+        while let Ok(report) = self.reports_rx.try_recv() {
+            changed = true;
+            let (index, elapsed, result) = match report {
+                Report::Slow(index) => {
+                    self.status[index].slow = true;
+                    continue;
+                }
+                Report::Done { index, elapsed, finished, result } => {
+                    queue_latency = queue_latency.max(finished.elapsed());
+                    (index, elapsed, result)
+                }
+            };
+            let status = &mut self.status[index];
+            status.slow = false;
+            status.runs += 1;
+            status.last_duration = elapsed;
+            status.max_duration = status.max_duration.max(elapsed);
+            status.total_duration += elapsed;
+            status.last_sample = Some(Instant::now());
+            match &result {
+                Ok(_) => {
+                    if status.last_error.take().is_some() {
+                        system.log("INFO", "Collector", &format!("{} collector recovered", status.collector.name()));
+                    }
+                }
+                Err(err) => {
+                    status.errors += 1;
+                    // Only log when the failure changes; off Linux most collectors fail on every run
+                    if status.last_error.as_ref() != Some(err) {
+                        system.log("WARN", "Collector", &format!("{} collector failed: {}", status.collector.name(), err));
+                    }
+                    status.last_error = Some(err.clone());
+                }
+            }
+            if latest[index].replace(result).is_some() {
+                status.superseded += 1;
+            }
+        }
+
+        for (status, result) in self.status.iter().zip(latest) {
+            match result {
+                Some(Ok(sample)) => system.apply_sample(sample),
+                // A failed run keeps the last real values; a collector that never worked, e.g.
+                // off Linux, falls back to simulated data
+                Some(Err(_)) if !status.succeeded() => system.simulate(status.collector),
+                Some(Err(_)) | None => {}
+            }
+        }
+        if changed {
+            self.apply_time = started.elapsed();
+            self.queue_latency = queue_latency;
+        }
+        changed
+    }
+}
+
+impl Drop for Pipeline {
+    // A collector stuck in a blocking read must not keep the console from exiting
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+async fn collect_loop(
+    index: usize,
+    collector: Collector,
+    interval: Duration,
+    enabled: Arc<AtomicBool>,
+    refresh: Arc<Notify>,
+    tx: Sender<Report>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = refresh.notified() => {}
+        }
+        if !enabled.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        let started = Instant::now();
+        let mut task = tokio::task::spawn_blocking(move || collector.collect());
+        let joined = match tokio::time::timeout(SLOW_COLLECTOR, &mut task).await {
+            Ok(joined) => joined,
+            Err(_) => {
+                if tx.send(Report::Slow(index)).is_err() {
+                    return;
+                }
+                task.await
+            }
+        };
+        let result = joined.unwrap_or_else(|err| Err(format!("collector panicked: {}", err)));
+        let report = Report::Done { index, elapsed: started.elapsed(), finished: Instant::now(), result };
+        if tx.send(report).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        let second = Duration::from_secs(1);
+        assert_eq!(backoff(second * 5, 0), second * 5);
+        assert_eq!(backoff(second * 5, 1), second * 10);
+        assert_eq!(backoff(second * 5, 3), second * 40);
+        assert_eq!(backoff(second * 5, 30), MAX_BACKOFF);
+        // An interval above the cap is never shortened
+        assert_eq!(backoff(second * 3600, 4), second * 3600);
+    }
+
+    fn cpu_report(result: Result<Sample, String>) -> Report {
+        Report::Done { index: 0, elapsed: Duration::ZERO, finished: Instant::now(), result }
+    }
+
+    fn cpu_times(busy: u64, idle: u64) -> Sample {
+        let times = CpuTimes { user: busy, idle, ..CpuTimes::default() };
+        Sample::Cpu(vec![("cpu".to_string(), times), ("cpu0".to_string(), times)])
+    }
+
+    #[test]
+    fn failed_run_keeps_real_values() {
+        let mut system = SystemState::new();
+        let mut pipeline = Pipeline::new(&RefreshConfig::default(), &mut system);
+        let (tx, rx) = mpsc::channel();
+        pipeline.reports_rx = rx;
+        assert_eq!(COLLECTORS[0], Collector::Cpu);
+
+        tx.send(cpu_report(Ok(cpu_times(100, 100)))).unwrap();
+        pipeline.collect_results(&mut system);
+        tx.send(cpu_report(Ok(cpu_times(130, 110)))).unwrap();
+        pipeline.collect_results(&mut system);
+        assert_eq!(system.cpu_usage, 75.0);
+
+        for _ in 0..5 {
+            tx.send(cpu_report(Err("read failed".to_string()))).unwrap();
+            assert!(pipeline.collect_results(&mut system));
+            assert_eq!(system.cpu_usage, 75.0);
+        }
+        assert_eq!(pipeline.status[0].state(), "FAILING");
+    }
+}
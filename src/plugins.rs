@@ -0,0 +1,775 @@
+// Plugin host: the Plugin trait, the registry that loads and enables plugins, and the built-in plugins
+use crate::alerts::{AlertManager, AlertRule};
+use crate::config;
+use crate::dashboards::{self, Dashboard};
+use crate::history;
+use crate::system::SystemState;
+use crate::wasm::WasmPlugin;
+use anyhow::{bail, Context};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Gauge, Paragraph, Widget},
+};
+
+// Consecutive collector errors before a plugin is marked FAILED and disabled
+const MAX_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    // Plugins that are loaded but left disabled at startup
+    pub disabled: Vec<String>,
+    // Directory scanned for *.wasm plugins, config_dir/plugins by default
+    pub dir: Option<PathBuf>,
+    // Directory of *.toml and *.json dashboard tabs, config_dir/dashboards by default
+    pub dashboards: Option<PathBuf>,
+    // Instructions a WASM plugin may execute per call
+    pub fuel: u64,
+    // Linear memory a WASM plugin may grow to
+    pub memory_limit_mb: usize,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            disabled: Vec::new(),
+            dir: None,
+            dashboards: None,
+            fuel: 1_000_000,
+            memory_limit_mb: 16,
+        }
+    }
+}
+
+pub struct PluginCommand {
+    pub name: String,
+    pub usage: String,
+    pub description: String,
+}
+
+pub trait Plugin: Send {
+    fn load(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+        Ok(())
+    }
+    fn unload(&mut self) {}
+    // Data collector, run on every tick while enabled
+    fn collect(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+        Ok(())
+    }
+    // Titles of the tabs added to the tab bar while enabled
+    fn tabs(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn render(&mut self, _tab: &str, _system: &SystemState, _area: Rect, _buf: &mut Buffer) {}
+    fn alert_rules(&self) -> Vec<AlertRule> {
+        Vec::new()
+    }
+    fn commands(&self) -> Vec<PluginCommand> {
+        Vec::new()
+    }
+    fn run_command(&mut self, name: &str, _args: &[&str], _system: &mut SystemState) -> anyhow::Result<String> {
+        bail!("unknown command {}", name)
+    }
+    // Memory held by the plugin when it can tell, e.g. a WASM linear memory
+    fn memory_bytes(&self) -> Option<u64> {
+        None
+    }
+}
+
+pub type PluginFactory = Box<dyn Fn() -> anyhow::Result<Box<dyn Plugin>> + Send>;
+
+#[derive(Debug, Clone, Default)]
+pub struct PluginStats {
+    pub collect_calls: u64,
+    pub renders: u64,
+    pub commands: u64,
+    pub errors: u64,
+    pub busy: Duration,
+    pub last_collect: Duration,
+    pub enabled_at: Option<Instant>,
+}
+
+impl PluginStats {
+    // Share of wall time spent inside the plugin since it was enabled
+    pub fn cpu_percent(&self) -> f64 {
+        match self.enabled_at {
+            Some(at) if at.elapsed() > Duration::ZERO => {
+                self.busy.as_secs_f64() / at.elapsed().as_secs_f64() * 100.0
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+pub struct PluginSlot {
+    pub name: String,
+    pub version: String,
+    // "Native", "WASM" or "Dashboard"
+    pub kind: &'static str,
+    pub description: String,
+    factory: PluginFactory,
+    instance: Option<Box<dyn Plugin>>,
+    // "UNLOADED", "ENABLED", "DISABLED" or "FAILED"
+    pub state: String,
+    pub stats: PluginStats,
+    pub last_error: Option<String>,
+    failures: u32,
+    rules: Vec<String>,
+}
+
+impl PluginSlot {
+    pub fn is_enabled(&self) -> bool {
+        self.state == "ENABLED"
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.instance.is_some()
+    }
+
+    pub fn tabs(&self) -> Vec<String> {
+        match &self.instance {
+            Some(plugin) if self.is_enabled() => plugin.tabs(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn commands(&self) -> Vec<PluginCommand> {
+        match &self.instance {
+            Some(plugin) if self.is_enabled() => plugin.commands(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+
+    pub fn memory_bytes(&self) -> Option<u64> {
+        self.instance.as_ref().and_then(|plugin| plugin.memory_bytes())
+    }
+}
+
+pub struct PluginRegistry {
+    pub plugins: Vec<PluginSlot>,
+    disabled: Vec<String>,
+}
+
+impl PluginRegistry {
+    pub fn new(config: &PluginsConfig) -> Self {
+        let mut registry = Self { plugins: Vec::new(), disabled: config.disabled.clone() };
+        registry.register("pressure", env!("CARGO_PKG_VERSION"), "Native", "CPU, memory and IO stall pressure from /proc/pressure", Box::new(|| {
+            Ok(Box::new(PressurePlugin::default()) as Box<dyn Plugin>)
+        }));
+        registry.register("log-export", env!("CARGO_PKG_VERSION"), "Native", "Counts log errors and exports the system log to a file", Box::new(|| {
+            Ok(Box::new(LogExportPlugin) as Box<dyn Plugin>)
+        }));
+        registry.discover(config);
+        registry
+    }
+
+    // Registers every *.wasm module and dashboard file; they are read again on each load
+    fn discover(&mut self, config: &PluginsConfig) {
+        let dashboards = config.dashboards.clone().or_else(|| config::config_dir().map(|dir| dir.join("dashboards")));
+        for path in dashboards.as_deref().map(dashboards::discover).unwrap_or_default() {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let description = format!("Dashboard {}", path.display());
+            self.register(&name, "-", "Dashboard", &description, Box::new(move || {
+                Ok(Box::new(Dashboard::open(&path)?) as Box<dyn Plugin>)
+            }));
+        }
+
+        let dir = match config.dir.clone().or_else(|| config::config_dir().map(|dir| dir.join("plugins"))) {
+            Some(dir) => dir,
+            None => return,
+        };
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+                .collect(),
+            Err(_) => return,
+        };
+        paths.sort();
+
+        let (fuel, memory_limit) = (config.fuel, config.memory_limit_mb * 1024 * 1024);
+        for path in paths {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let description = format!("Sandboxed module {}", path.display());
+            self.register(&name, "-", "WASM", &description, Box::new(move || {
+                Ok(Box::new(WasmPlugin::open(&path, fuel, memory_limit)?) as Box<dyn Plugin>)
+            }));
+        }
+    }
+
+    pub fn register(&mut self, name: &str, version: &str, kind: &'static str, description: &str, factory: PluginFactory) {
+        self.plugins.retain(|slot| slot.name != name);
+        self.plugins.push(PluginSlot {
+            name: name.to_string(),
+            version: version.to_string(),
+            kind,
+            description: description.to_string(),
+            factory,
+            instance: None,
+            state: "UNLOADED".to_string(),
+            stats: PluginStats::default(),
+            last_error: None,
+            failures: 0,
+            rules: Vec::new(),
+        });
+    }
+
+    // Loads every registered plugin, enabling those not listed as disabled
+    pub fn start(&mut self, system: &mut SystemState, alerts: &mut AlertManager) {
+        for index in 0..self.plugins.len() {
+            if self.load(index, system) && !self.disabled.contains(&self.plugins[index].name) {
+                self.enable(index, system, alerts);
+            }
+        }
+    }
+
+    pub fn load(&mut self, index: usize, system: &mut SystemState) -> bool {
+        let slot = match self.plugins.get_mut(index) {
+            Some(slot) if slot.instance.is_none() => slot,
+            _ => return false,
+        };
+        let loaded = (slot.factory)().and_then(|mut plugin| plugin.load(system).map(|_| plugin));
+        match loaded {
+            Ok(plugin) => {
+                slot.instance = Some(plugin);
+                slot.state = "DISABLED".to_string();
+                slot.last_error = None;
+                system.log("INFO", "Plugins", &format!("Loaded {} ({})", slot.name, slot.kind));
+                true
+            }
+            Err(err) => {
+                slot.state = "FAILED".to_string();
+                slot.stats.errors += 1;
+                slot.last_error = Some(format!("{:#}", err));
+                system.log("ERROR", "Plugins", &format!("Loading {} failed: {:#}", slot.name, err));
+                false
+            }
+        }
+    }
+
+    pub fn enable(&mut self, index: usize, system: &mut SystemState, alerts: &mut AlertManager) {
+        let slot = match self.plugins.get_mut(index) {
+            Some(slot) if !slot.is_enabled() => slot,
+            _ => return,
+        };
+        let plugin = match &slot.instance {
+            Some(plugin) => plugin,
+            None => return,
+        };
+        // Rule names are prefixed with the plugin's, so plugins cannot replace each other's rules
+        slot.rules.clear();
+        for mut rule in plugin.alert_rules() {
+            rule.name = format!("{}:{}", slot.name, rule.name);
+            slot.rules.push(rule.name.clone());
+            alerts.add_rule(rule);
+        }
+        slot.state = "ENABLED".to_string();
+        slot.failures = 0;
+        slot.stats.enabled_at = Some(Instant::now());
+        slot.stats.busy = Duration::ZERO;
+        system.log("INFO", "Plugins", &format!("Enabled {}", slot.name));
+    }
+
+    pub fn disable(&mut self, index: usize, system: &mut SystemState, alerts: &mut AlertManager) {
+        if let Some(slot) = self.plugins.get_mut(index).filter(|slot| slot.is_enabled()) {
+            Self::deactivate(slot, "DISABLED", alerts);
+            system.log("INFO", "Plugins", &format!("Disabled {}", slot.name));
+        }
+    }
+
+    pub fn unload(&mut self, index: usize, system: &mut SystemState, alerts: &mut AlertManager) {
+        let slot = match self.plugins.get_mut(index) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if let Some(mut plugin) = slot.instance.take() {
+            Self::deactivate(slot, "UNLOADED", alerts);
+            plugin.unload();
+            slot.stats = PluginStats::default();
+            system.log("INFO", "Plugins", &format!("Unloaded {}", slot.name));
+        }
+    }
+
+    fn deactivate(slot: &mut PluginSlot, state: &str, alerts: &mut AlertManager) {
+        for rule in slot.rules.drain(..) {
+            alerts.remove_rule(&rule);
+        }
+        slot.state = state.to_string();
+        slot.stats.enabled_at = None;
+    }
+
+    pub fn collect(&mut self, system: &mut SystemState, alerts: &mut AlertManager) {
+        for slot in self.plugins.iter_mut().filter(|slot| slot.is_enabled()) {
+            let plugin = match slot.instance.as_mut() {
+                Some(plugin) => plugin,
+                None => continue,
+            };
+            let started = Instant::now();
+            let result = plugin.collect(system);
+            slot.stats.last_collect = started.elapsed();
+            slot.stats.busy += slot.stats.last_collect;
+            slot.stats.collect_calls += 1;
+
+            match result {
+                Ok(()) => slot.failures = 0,
+                Err(err) => {
+                    slot.failures += 1;
+                    slot.stats.errors += 1;
+                    slot.last_error = Some(format!("{:#}", err));
+                    system.log("WARN", "Plugins", &format!("{} collector failed: {:#}", slot.name, err));
+                    if slot.failures >= MAX_FAILURES {
+                        Self::deactivate(slot, "FAILED", alerts);
+                        system.log("ERROR", "Plugins", &format!("{} disabled after {} failures", slot.name, MAX_FAILURES));
+                    }
+                }
+            }
+        }
+    }
+
+    // Tabs of every enabled plugin as (plugin index, title), in registration order
+    pub fn tabs(&self) -> Vec<(usize, String)> {
+        self.plugins
+            .iter()
+            .enumerate()
+            .flat_map(|(index, slot)| slot.tabs().into_iter().map(move |tab| (index, tab)))
+            .collect()
+    }
+
+    pub fn render(&mut self, index: usize, tab: &str, system: &SystemState, area: Rect, buf: &mut Buffer) {
+        let slot = match self.plugins.get_mut(index) {
+            Some(slot) if slot.is_enabled() => slot,
+            _ => return,
+        };
+        if let Some(plugin) = slot.instance.as_mut() {
+            let started = Instant::now();
+            plugin.render(tab, system, area, buf);
+            slot.stats.busy += started.elapsed();
+            slot.stats.renders += 1;
+        }
+    }
+
+    // Runs "<command> [args...]" on the enabled plugin that provides it
+    pub fn run_command(&mut self, line: &str, system: &mut SystemState) {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return,
+        };
+        let args: Vec<&str> = words.collect();
+        let slot = match self
+            .plugins
+            .iter_mut()
+            .find(|slot| slot.commands().iter().any(|command| command.name == name))
+        {
+            Some(slot) => slot,
+            None => {
+                system.log("WARN", "Plugins", &format!("No enabled plugin provides '{}'", name));
+                return;
+            }
+        };
+        let plugin = match slot.instance.as_mut() {
+            Some(plugin) => plugin,
+            None => return,
+        };
+
+        let started = Instant::now();
+        let result = plugin.run_command(name, &args, system);
+        slot.stats.busy += started.elapsed();
+        slot.stats.commands += 1;
+        match result {
+            Ok(output) => system.log("INFO", "Plugins", &format!("{} {}: {}", slot.name, name, output)),
+            Err(err) => {
+                slot.stats.errors += 1;
+                slot.last_error = Some(format!("{:#}", err));
+                system.log("ERROR", "Plugins", &format!("{} {} failed: {:#}", slot.name, name, err));
+            }
+        }
+    }
+}
+
+// Renders one plugin tab through the registry so the time it takes is accounted to the plugin
+pub struct PluginTab<'a> {
+    pub registry: &'a mut PluginRegistry,
+    pub index: usize,
+    pub tab: &'a str,
+    pub system: &'a SystemState,
+}
+
+impl Widget for PluginTab<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.registry.render(self.index, self.tab, self.system, area, buf);
+    }
+}
+
+// Pressure stall information: share of time tasks waited on CPU, memory or IO
+#[derive(Default)]
+struct PressurePlugin {
+    // resource -> (some avg10, avg60, avg300, full avg10)
+    readings: HashMap<&'static str, [f64; 4]>,
+    peaks: HashMap<&'static str, f64>,
+    read_at: Option<Instant>,
+}
+
+const PRESSURE_RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+fn read_pressure(resource: &str) -> anyhow::Result<[f64; 4]> {
+    let path = format!("/proc/pressure/{}", resource);
+    let text = fs::read_to_string(&path).with_context(|| format!("reading {}", path))?;
+    let mut values = [0.0; 4];
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let averages: HashMap<&str, f64> = fields
+            .filter_map(|field| field.split_once('='))
+            .filter_map(|(key, value)| value.parse().ok().map(|value| (key, value)))
+            .collect();
+        let get = |key| averages.get(key).copied().unwrap_or_default();
+        match kind {
+            Some("some") => {
+                values[0] = get("avg10");
+                values[1] = get("avg60");
+                values[2] = get("avg300");
+            }
+            Some("full") => values[3] = get("avg10"),
+            _ => {}
+        }
+    }
+    Ok(values)
+}
+
+impl Plugin for PressurePlugin {
+    fn load(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+        read_pressure("cpu").context("pressure stall information is not available")?;
+        Ok(())
+    }
+
+    // The kernel only updates avg10 every two seconds
+    fn collect(&mut self, system: &mut SystemState) -> anyhow::Result<()> {
+        if self.read_at.is_some_and(|at| at.elapsed() < Duration::from_secs(2)) {
+            return Ok(());
+        }
+        self.read_at = Some(Instant::now());
+        let now = Local::now().timestamp();
+        for resource in PRESSURE_RESOURCES {
+            let values = read_pressure(resource)?;
+            system.metrics.record(&format!("psi.{}.some", resource), now, values[0]);
+            system.metrics.record(&format!("psi.{}.full", resource), now, values[3]);
+            let peak = self.peaks.entry(resource).or_default();
+            *peak = peak.max(values[0]);
+            self.readings.insert(resource, values);
+        }
+        Ok(())
+    }
+
+    fn tabs(&self) -> Vec<String> {
+        vec!["Pressure".to_string()]
+    }
+
+    fn render(&mut self, _tab: &str, _system: &SystemState, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        for (i, resource) in PRESSURE_RESOURCES.iter().enumerate() {
+            let values = self.readings.get(resource).copied().unwrap_or_default();
+            let color = match values[0] {
+                v if v >= 20.0 => Color::Red,
+                v if v >= 5.0 => Color::Yellow,
+                _ => Color::Green,
+            };
+            let title = format!("{} pressure (some avg10, full {:.2}%)", resource, values[3]);
+            Gauge::default()
+                .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+                .gauge_style(Style::default().fg(color))
+                .percent(values[0].clamp(0.0, 100.0) as u16)
+                .label(format!("{:.2}%", values[0]))
+                .render(chunks[i], buf);
+        }
+
+        let mut lines = vec![Spans::from(Span::styled(
+            format!("{:<8} {:>8} {:>8} {:>8} {:>8}", "", "avg10", "avg60", "avg300", "peak"),
+            Style::default().fg(Color::Yellow),
+        ))];
+        for resource in PRESSURE_RESOURCES {
+            let values = self.readings.get(resource).copied().unwrap_or_default();
+            lines.push(Spans::from(format!(
+                "{:<8} {:>7.2}% {:>7.2}% {:>7.2}% {:>7.2}%",
+                resource,
+                values[0],
+                values[1],
+                values[2],
+                self.peaks.get(resource).copied().unwrap_or_default()
+            )));
+        }
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Stall averages [: reset-peaks]").style(Style::default().fg(Color::Green)))
+            .style(Style::default().fg(Color::Green))
+            .render(chunks[3], buf);
+    }
+
+    fn alert_rules(&self) -> Vec<AlertRule> {
+        vec![AlertRule {
+            name: "memory-stall".to_string(),
+            metric: "psi.memory.some".to_string(),
+            above: Some(20.0),
+            below: None,
+            severity: "WARN".to_string(),
+            message: None,
+        }]
+    }
+
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![PluginCommand {
+            name: "reset-peaks".to_string(),
+            usage: "reset-peaks".to_string(),
+            description: "Forget the highest pressure seen so far".to_string(),
+        }]
+    }
+
+    fn run_command(&mut self, name: &str, _args: &[&str], _system: &mut SystemState) -> anyhow::Result<String> {
+        match name {
+            "reset-peaks" => {
+                self.peaks.clear();
+                Ok("peaks cleared".to_string())
+            }
+            _ => bail!("unknown command {}", name),
+        }
+    }
+}
+
+// Error counting and log export; a collector and commands but no tab
+struct LogExportPlugin;
+
+impl Plugin for LogExportPlugin {
+    fn collect(&mut self, system: &mut SystemState) -> anyhow::Result<()> {
+        let errors = system.logs.iter().filter(|entry| entry.level == "ERROR").count();
+        system.metrics.record("log.errors", Local::now().timestamp(), errors as f64);
+        Ok(())
+    }
+
+    fn alert_rules(&self) -> Vec<AlertRule> {
+        vec![AlertRule {
+            name: "log-errors".to_string(),
+            metric: "log.errors".to_string(),
+            above: Some(20.0),
+            below: None,
+            severity: "WARN".to_string(),
+            message: Some("More than 20 errors in the system log".to_string()),
+        }]
+    }
+
+    fn commands(&self) -> Vec<PluginCommand> {
+        vec![PluginCommand {
+            name: "export-log".to_string(),
+            usage: "export-log [path]".to_string(),
+            description: "Write the system log to a file, by default in the data directory".to_string(),
+        }]
+    }
+
+    fn run_command(&mut self, name: &str, args: &[&str], system: &mut SystemState) -> anyhow::Result<String> {
+        if name != "export-log" {
+            bail!("unknown command {}", name);
+        }
+        let path = match args.first() {
+            Some(path) => PathBuf::from(path),
+            None => history::data_dir()
+                .context("no data directory")?
+                .join(format!("console-{}.log", Local::now().format("%Y%m%d-%H%M%S"))),
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let mut file = fs::File::create(&path).with_context(|| format!("creating {}", path.display()))?;
+        for entry in &system.logs {
+            writeln!(
+                file,
+                "{} [{}] {}: {}",
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                entry.level,
+                entry.source,
+                entry.message
+            )?;
+        }
+        Ok(format!("wrote {} entries to {}", system.logs.len(), path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertConfig;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default, Clone)]
+    struct Calls {
+        loads: Arc<AtomicUsize>,
+        unloaded: Arc<AtomicBool>,
+        failing: Arc<AtomicBool>,
+    }
+
+    struct Stub {
+        calls: Calls,
+    }
+
+    impl Plugin for Stub {
+        fn load(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+            self.calls.loads.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn unload(&mut self) {
+            self.calls.unloaded.store(true, Ordering::SeqCst);
+        }
+
+        fn collect(&mut self, _system: &mut SystemState) -> anyhow::Result<()> {
+            if self.calls.failing.load(Ordering::SeqCst) {
+                bail!("no data");
+            }
+            Ok(())
+        }
+
+        fn tabs(&self) -> Vec<String> {
+            vec!["Stub".to_string()]
+        }
+
+        fn alert_rules(&self) -> Vec<AlertRule> {
+            vec![AlertRule {
+                name: "hot".to_string(),
+                metric: "stub.value".to_string(),
+                above: Some(50.0),
+                below: None,
+                severity: "WARN".to_string(),
+                message: None,
+            }]
+        }
+    }
+
+    fn registry() -> PluginRegistry {
+        PluginRegistry { plugins: Vec::new(), disabled: Vec::new() }
+    }
+
+    fn register(registry: &mut PluginRegistry, name: &str, calls: &Calls) {
+        let calls = calls.clone();
+        registry.register(name, "1.0", "Native", "stub", Box::new(move || {
+            Ok(Box::new(Stub { calls: calls.clone() }) as Box<dyn Plugin>)
+        }));
+    }
+
+    fn setup() -> (SystemState, AlertManager) {
+        let mut system = SystemState::new();
+        system.metrics.record("stub.value", Local::now().timestamp(), 90.0);
+        (system, AlertManager::new(&AlertConfig { rules: Vec::new(), sinks: Vec::new() }))
+    }
+
+    fn firing(alerts: &AlertManager) -> Vec<String> {
+        let mut rules: Vec<String> = alerts.active().map(|alert| alert.rule.clone()).collect();
+        rules.sort();
+        rules
+    }
+
+    #[test]
+    fn lifecycle() {
+        let (mut system, mut alerts) = setup();
+        let calls = Calls::default();
+        let mut registry = registry();
+        register(&mut registry, "stub", &calls);
+
+        assert!(registry.load(0, &mut system));
+        assert!(!registry.load(0, &mut system), "already loaded");
+        assert_eq!(calls.loads.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.plugins[0].state, "DISABLED");
+        assert!(registry.tabs().is_empty());
+
+        registry.enable(0, &mut system, &mut alerts);
+        assert_eq!(registry.plugins[0].state, "ENABLED");
+        assert_eq!(registry.tabs(), [(0, "Stub".to_string())]);
+        assert_eq!(registry.plugins[0].rules(), ["stub:hot"]);
+
+        registry.disable(0, &mut system, &mut alerts);
+        assert_eq!(registry.plugins[0].state, "DISABLED");
+        assert!(registry.tabs().is_empty());
+        assert!(registry.plugins[0].rules().is_empty());
+
+        registry.unload(0, &mut system, &mut alerts);
+        assert_eq!(registry.plugins[0].state, "UNLOADED");
+        assert!(!registry.plugins[0].is_loaded());
+        assert!(calls.unloaded.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn failing_factory_marks_failed() {
+        let (mut system, _) = setup();
+        let mut registry = registry();
+        registry.register("broken", "1.0", "Native", "stub", Box::new(|| bail!("missing library")));
+        assert!(!registry.load(0, &mut system));
+        assert_eq!(registry.plugins[0].state, "FAILED");
+        assert_eq!(registry.plugins[0].last_error.as_deref(), Some("missing library"));
+    }
+
+    #[test]
+    fn repeated_collector_failures_disable_plugin() {
+        let (mut system, mut alerts) = setup();
+        let calls = Calls::default();
+        let mut registry = registry();
+        register(&mut registry, "stub", &calls);
+        registry.start(&mut system, &mut alerts);
+        alerts.evaluate(&mut system);
+        assert_eq!(firing(&alerts), ["stub:hot"]);
+
+        calls.failing.store(true, Ordering::SeqCst);
+        for _ in 1..MAX_FAILURES {
+            registry.collect(&mut system, &mut alerts);
+            assert_eq!(registry.plugins[0].state, "ENABLED");
+        }
+        // A success in between starts the count again
+        calls.failing.store(false, Ordering::SeqCst);
+        registry.collect(&mut system, &mut alerts);
+        calls.failing.store(true, Ordering::SeqCst);
+        for _ in 0..MAX_FAILURES {
+            registry.collect(&mut system, &mut alerts);
+        }
+        let slot = &registry.plugins[0];
+        assert_eq!(slot.state, "FAILED");
+        assert_eq!(slot.stats.errors, 2 * MAX_FAILURES as u64 - 1);
+        assert!(slot.rules().is_empty());
+        assert!(firing(&alerts).is_empty());
+
+        // A failed plugin is no longer collected, and can be enabled again
+        registry.collect(&mut system, &mut alerts);
+        assert_eq!(registry.plugins[0].stats.errors, 2 * MAX_FAILURES as u64 - 1);
+        registry.enable(0, &mut system, &mut alerts);
+        assert_eq!(registry.plugins[0].state, "ENABLED");
+    }
+
+    #[test]
+    fn plugins_sharing_a_rule_name_keep_their_own_rules() {
+        let (mut system, mut alerts) = setup();
+        let mut registry = registry();
+        register(&mut registry, "first", &Calls::default());
+        register(&mut registry, "second", &Calls::default());
+        registry.start(&mut system, &mut alerts);
+        alerts.evaluate(&mut system);
+        assert_eq!(firing(&alerts), ["first:hot", "second:hot"]);
+
+        registry.disable(0, &mut system, &mut alerts);
+        alerts.evaluate(&mut system);
+        assert_eq!(firing(&alerts), ["second:hot"]);
+    }
+}
@@ -0,0 +1,353 @@
+// External command probes: shell commands run on the tokio runtime whose output becomes a metric
+use crate::shell;
+use crate::system::SystemState;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::time::MissedTickBehavior;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    // Recorded as the metric probe.<name>
+    pub name: String,
+    pub cmd: String,
+    #[serde(default = "default_interval")]
+    pub interval: String,
+    // Defaults to the interval, so a probe never overlaps itself
+    #[serde(default)]
+    pub timeout: Option<String>,
+    // "number", "lines", "exit_code", "duration" or "json:<dotted.path>"
+    #[serde(default = "default_parse")]
+    pub parse: String,
+}
+
+fn default_interval() -> String {
+    "10s".to_string()
+}
+
+fn default_parse() -> String {
+    "number".to_string()
+}
+
+// "500ms", "5s", "2m", "1h"; a bare number is seconds
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return None,
+    };
+    // Out of range values are refused rather than panicking, and zero would spin the interval timers
+    Duration::try_from_secs_f64(seconds).ok().filter(|duration| !duration.is_zero())
+}
+
+pub fn first_number(output: &str) -> Option<f64> {
+    output
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .find_map(|token| token.parse().ok())
+}
+
+fn json_number(output: &str, path: &str) -> Result<f64, String> {
+    let mut value: serde_json::Value = serde_json::from_str(output).map_err(|e| format!("invalid JSON: {}", e))?;
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        value = match value {
+            serde_json::Value::Array(mut items) => match key.parse::<usize>() {
+                Ok(index) if index < items.len() => items.swap_remove(index),
+                _ => return Err(format!("no element {} in {}", key, path)),
+            },
+            serde_json::Value::Object(mut map) => map.remove(key).ok_or_else(|| format!("no key {} in {}", key, path))?,
+            _ => return Err(format!("{} is not an object", path)),
+        };
+    }
+    match value {
+        serde_json::Value::Number(number) => number.as_f64().ok_or_else(|| format!("{} is not a number", path)),
+        serde_json::Value::String(text) => text.trim().parse().map_err(|_| format!("{} is not a number", path)),
+        serde_json::Value::Bool(flag) => Ok(if flag { 1.0 } else { 0.0 }),
+        _ => Err(format!("{} is not a number", path)),
+    }
+}
+
+// How one run went, whatever the parse mode
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    elapsed: Duration,
+}
+
+fn parse_output(parse: &str, run: &Run) -> Result<f64, String> {
+    if parse == "exit_code" {
+        return run.code.map(f64::from).ok_or_else(|| "killed by a signal".to_string());
+    }
+    if run.code != Some(0) {
+        let code = run.code.map_or("signal".to_string(), |code| code.to_string());
+        return match run.stderr.lines().next().map(str::trim).filter(|line| !line.is_empty()) {
+            Some(stderr) => Err(format!("exit {}: {}", code, stderr)),
+            None => Err(format!("exit {}", code)),
+        };
+    }
+    match parse {
+        "number" => first_number(&run.stdout).ok_or_else(|| "no number in output".to_string()),
+        "lines" => Ok(run.stdout.lines().filter(|line| !line.trim().is_empty()).count() as f64),
+        "duration" => Ok(run.elapsed.as_secs_f64() * 1000.0),
+        _ => match parse.strip_prefix("json:") {
+            Some(path) => json_number(&run.stdout, path),
+            None => Err(format!("unknown parse mode {}", parse)),
+        },
+    }
+}
+
+async fn run_probe(cmd: &str, parse: &str, timeout: Duration) -> Result<f64, String> {
+    let started = Instant::now();
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Own process group, so a timeout also kills the rest of a pipeline
+    #[cfg(unix)]
+    command.process_group(0);
+    let child = command.spawn().map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    let group = child.id();
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| e.to_string())?,
+        Err(_) => {
+            #[cfg(unix)]
+            if let Some(pid) = group {
+                shell::kill_group(pid);
+            }
+            return Err(shell::timed_out(timeout));
+        }
+    };
+    let run = Run {
+        code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        elapsed: started.elapsed(),
+    };
+    parse_output(parse, &run)
+}
+
+pub struct ProbeStatus {
+    pub name: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub runs: u64,
+    pub failures: u64,
+    pub last_value: Option<f64>,
+    pub last_error: Option<String>,
+    pub last_run: Option<Instant>,
+    pub last_duration: Duration,
+}
+
+struct ProbeResult {
+    index: usize,
+    elapsed: Duration,
+    value: Result<f64, String>,
+}
+
+pub struct ProbeRunner {
+    pub status: Vec<ProbeStatus>,
+    results_rx: Receiver<ProbeResult>,
+}
+
+impl ProbeRunner {
+    // Probes run on the collection pipeline's runtime and stop with it
+    pub fn new(probes: &[ProbeConfig], runtime: Option<Handle>, system: &mut SystemState) -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        let mut runner = Self { status: Vec::new(), results_rx };
+        if probes.is_empty() {
+            return runner;
+        }
+        let runtime = match runtime {
+            Some(runtime) => runtime,
+            None => {
+                system.log("ERROR", "Probes", "No collection runtime, probes are not run");
+                return runner;
+            }
+        };
+
+        for probe in probes {
+            let interval = match parse_duration(&probe.interval) {
+                Some(interval) => interval,
+                None => {
+                    system.log("ERROR", "Probes", &format!("{}: invalid interval '{}'", probe.name, probe.interval));
+                    continue;
+                }
+            };
+            let timeout = match probe.timeout.as_deref().map(parse_duration) {
+                Some(Some(timeout)) => timeout,
+                Some(None) => {
+                    system.log("ERROR", "Probes", &format!("{}: invalid timeout", probe.name));
+                    continue;
+                }
+                None => interval,
+            };
+
+            let index = runner.status.len();
+            runner.status.push(ProbeStatus {
+                name: probe.name.clone(),
+                interval,
+                timeout,
+                runs: 0,
+                failures: 0,
+                last_value: None,
+                last_error: None,
+                last_run: None,
+                last_duration: Duration::ZERO,
+            });
+            runtime.spawn(probe_loop(index, probe.cmd.clone(), probe.parse.clone(), interval, timeout, results_tx.clone()));
+        }
+        system.log("INFO", "Probes", &format!("Started {} probes", runner.status.len()));
+        runner
+    }
+
+    pub fn collect_results(&mut self, system: &mut SystemState) {
+        while let Ok(result) = self.results_rx.try_recv() {
+            let status = match self.status.get_mut(result.index) {
+                Some(status) => status,
+                None => continue,
+            };
+            status.runs += 1;
+            status.last_run = Some(Instant::now());
+            status.last_duration = result.elapsed;
+            match result.value {
+                Ok(value) => {
+                    system.metrics.record(&format!("probe.{}", status.name), Local::now().timestamp(), value);
+                    status.last_value = Some(value);
+                    if status.last_error.take().is_some() {
+                        system.log("INFO", "Probes", &format!("{} recovered: {}", status.name, value));
+                    }
+                }
+                Err(err) => {
+                    status.failures += 1;
+                    // Only log when the failure changes, so a probe down for hours does not flood the log
+                    if status.last_error.as_ref() != Some(&err) {
+                        system.log("WARN", "Probes", &format!("{} failed: {}", status.name, err));
+                    }
+                    status.last_error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+async fn probe_loop(
+    index: usize,
+    cmd: String,
+    parse: String,
+    interval: Duration,
+    timeout: Duration,
+    tx: Sender<ProbeResult>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let started = Instant::now();
+        let value = run_probe(&cmd, &parse, timeout).await;
+        if tx.send(ProbeResult { index, elapsed: started.elapsed(), value }).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration(" 5s "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        for invalid in ["", "s", "0", "0s", "-1s", "5d", "1e3s", "99999999999999999999s", "0.0000000001s"] {
+            assert_eq!(parse_duration(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn first_numbers() {
+        assert_eq!(first_number("42"), Some(42.0));
+        assert_eq!(first_number("/dev/sda1  73% /"), Some(1.0));
+        assert_eq!(first_number("load: -0.5 avg"), Some(-0.5));
+        assert_eq!(first_number("temp=41.5'C"), Some(41.5));
+        assert_eq!(first_number("- . none"), None);
+    }
+
+    #[test]
+    fn json_paths() {
+        let output = r#"{"disk": {"used": 73, "free": "27.5"}, "ok": true, "items": [{"n": 1}, {"n": 2}], "name": "x"}"#;
+        assert_eq!(json_number(output, "disk.used"), Ok(73.0));
+        assert_eq!(json_number(output, "disk.free"), Ok(27.5));
+        assert_eq!(json_number(output, "ok"), Ok(1.0));
+        assert_eq!(json_number(output, "items.1.n"), Ok(2.0));
+        assert_eq!(json_number("12", ""), Ok(12.0));
+        assert_eq!(json_number(output, "items.5.n"), Err("no element 5 in items.5.n".to_string()));
+        assert_eq!(json_number(output, "disk.total"), Err("no key total in disk.total".to_string()));
+        assert_eq!(json_number(output, "name.first"), Err("name.first is not an object".to_string()));
+        assert_eq!(json_number(output, "name"), Err("name is not a number".to_string()));
+        assert!(json_number("not json", "a").unwrap_err().starts_with("invalid JSON"));
+    }
+
+    fn run(code: Option<i32>, stdout: &str, stderr: &str) -> Run {
+        Run { code, stdout: stdout.to_string(), stderr: stderr.to_string(), elapsed: Duration::from_millis(250) }
+    }
+
+    #[test]
+    fn parse_modes() {
+        let ok = run(Some(0), "a 12\n\nb 3\n", "");
+        assert_eq!(parse_output("number", &ok), Ok(12.0));
+        assert_eq!(parse_output("lines", &ok), Ok(2.0));
+        assert_eq!(parse_output("duration", &ok), Ok(250.0));
+        assert_eq!(parse_output("json:a", &run(Some(0), r#"{"a": 4}"#, "")), Ok(4.0));
+        assert_eq!(parse_output("number", &run(Some(0), "none", "")), Err("no number in output".to_string()));
+        assert_eq!(parse_output("csv", &ok), Err("unknown parse mode csv".to_string()));
+
+        // exit_code reports any exit; other modes fail on a non-zero one
+        assert_eq!(parse_output("exit_code", &run(Some(3), "", "")), Ok(3.0));
+        assert_eq!(parse_output("exit_code", &run(None, "", "")), Err("killed by a signal".to_string()));
+        assert_eq!(parse_output("number", &run(Some(2), "7", "  no such file \nmore")), Err("exit 2: no such file".to_string()));
+        assert_eq!(parse_output("lines", &run(None, "", "")), Err("exit signal".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_the_whole_pipeline() {
+        let pid_file = std::env::temp_dir().join(format!("redox-console-probes-{}-pipeline", process::id()));
+        let cmd = format!("sleep 30 & echo $! > {}; wait | cat", pid_file.display());
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let result = runtime.block_on(run_probe(&cmd, "number", Duration::from_millis(300)));
+        assert_eq!(result, Err("timed out after 0.3s".to_string()));
+
+        let pid = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        fs::remove_file(&pid_file).unwrap();
+        // Gone, or a zombie waiting to be reaped by init
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+            let state = stat.rsplit_once(") ").and_then(|(_, rest)| rest.chars().next());
+            if matches!(state, None | Some('Z')) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "sleep {} still running", pid);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
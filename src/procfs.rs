@@ -0,0 +1,823 @@
+// Readers for Linux procfs/sysfs files and libc interface queries; callers fall back to simulated data when absent
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+
+impl CpuTimes {
+    pub fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+}
+
+// Returns the aggregate "cpu" line first, followed by one entry per logical CPU
+pub fn read_cpu_times() -> io::Result<Vec<(String, CpuTimes)>> {
+    Ok(parse_cpu_times(&fs::read_to_string("/proc/stat")?))
+}
+
+pub fn parse_cpu_times(text: &str) -> Vec<(String, CpuTimes)> {
+    text.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let values: Vec<u64> = fields.map(|v| v.parse().unwrap_or(0)).collect();
+            if values.len() < 4 {
+                return None;
+            }
+            let field = |i: usize| values.get(i).copied().unwrap_or(0);
+            Some((
+                name,
+                CpuTimes {
+                    user: field(0),
+                    nice: field(1),
+                    system: field(2),
+                    idle: field(3),
+                    iowait: field(4),
+                    irq: field(5),
+                    softirq: field(6),
+                    steal: field(7),
+                },
+            ))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetDevCounters {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+}
+
+pub fn read_net_dev() -> io::Result<Vec<NetDevCounters>> {
+    Ok(parse_net_dev(&fs::read_to_string("/proc/net/dev")?))
+}
+
+pub fn parse_net_dev(text: &str) -> Vec<NetDevCounters> {
+    text.lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            let values: Vec<u64> = rest.split_whitespace().map(|v| v.parse().unwrap_or(0)).collect();
+            if values.len() < 12 {
+                return None;
+            }
+            Some(NetDevCounters {
+                name: name.trim().to_string(),
+                rx_bytes: values[0],
+                rx_packets: values[1],
+                rx_errors: values[2],
+                rx_dropped: values[3],
+                tx_bytes: values[8],
+                tx_packets: values[9],
+                tx_errors: values[10],
+                tx_dropped: values[11],
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkInfo {
+    pub up: bool,
+    pub operstate: String,
+    pub mac: String,
+    pub mtu: u32,
+    pub speed_mbps: Option<u32>,
+    pub duplex: Option<String>,
+    pub driver: Option<String>,
+    pub kind: String,
+}
+
+const IFF_UP: u32 = 0x1;
+const ARPHRD_LOOPBACK: u32 = 772;
+const IFF_TAP: u32 = 0x2;
+
+pub fn read_link_info(name: &str) -> io::Result<LinkInfo> {
+    let dir = Path::new("/sys/class/net").join(name);
+    let read = |file: &str| fs::read_to_string(dir.join(file)).map(|s| s.trim().to_string());
+    let read_num = |file: &str| read(file).ok().and_then(|s| parse_number(&s));
+
+    let flags = parse_number(&read("flags")?).unwrap_or(0);
+    // speed and duplex fail with EINVAL or report -1/"unknown" when there is no carrier
+    let speed_mbps = read("speed").ok().and_then(|s| s.parse::<i64>().ok()).filter(|s| *s > 0).map(|s| s as u32);
+    let duplex = read("duplex").ok().filter(|d| d != "unknown");
+    let driver = fs::read_link(dir.join("device/driver"))
+        .ok()
+        .and_then(|link| link.file_name().map(|n| n.to_string_lossy().into_owned()));
+
+    let kind = if read_num("type") == Some(ARPHRD_LOOPBACK) {
+        "loopback".to_string()
+    } else if let Some(devtype) = read("uevent")
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVTYPE="))
+    {
+        // bridge, vlan, bond, wlan, wireguard, ...
+        devtype.to_string()
+    } else if let Some(tun_flags) = read_num("tun_flags") {
+        if tun_flags & IFF_TAP != 0 { "tap" } else { "tun" }.to_string()
+    } else if driver.is_some() {
+        "ethernet".to_string()
+    } else if read_num("iflink") != read_num("ifindex") {
+        // Peer lives in another namespace, as with container veth pairs
+        "veth".to_string()
+    } else {
+        "virtual".to_string()
+    };
+
+    Ok(LinkInfo {
+        up: flags & IFF_UP != 0,
+        operstate: read("operstate").unwrap_or_else(|_| "unknown".to_string()),
+        mac: read("address").unwrap_or_default(),
+        mtu: read_num("mtu").unwrap_or(0),
+        speed_mbps,
+        duplex,
+        driver,
+        kind,
+    })
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// All IPv4/IPv6 addresses as (interface, "address/prefix") via getifaddrs, which glibc answers over netlink
+#[cfg(unix)]
+pub fn read_interface_addresses() -> io::Result<Vec<(String, String)>> {
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addresses = Vec::new();
+    let mut cursor = head;
+    while !cursor.is_null() {
+        // Safety: getifaddrs returned a valid list that stays alive until freeifaddrs below
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+        let address = match unsafe { sockaddr_ip(entry.ifa_addr) } {
+            Some(address) => address,
+            None => continue,
+        };
+        let prefix = match unsafe { sockaddr_ip(entry.ifa_netmask) } {
+            Some(IpAddr::V4(mask)) => u32::from(mask).count_ones(),
+            Some(IpAddr::V6(mask)) => u128::from(mask).count_ones(),
+            None if address.is_ipv4() => 32,
+            None => 128,
+        };
+        let name = unsafe { std::ffi::CStr::from_ptr(entry.ifa_name) }.to_string_lossy().into_owned();
+        addresses.push((name, format!("{}/{}", address, prefix)));
+    }
+
+    unsafe { libc::freeifaddrs(head) };
+    Ok(addresses)
+}
+
+#[cfg(not(unix))]
+pub fn read_interface_addresses() -> io::Result<Vec<(String, String)>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "getifaddrs is not available"))
+}
+
+#[cfg(unix)]
+unsafe fn sockaddr_ip(addr: *const libc::sockaddr) -> Option<IpAddr> {
+    if addr.is_null() {
+        return None;
+    }
+    match i32::from((*addr).sa_family) {
+        libc::AF_INET => {
+            let sin = &*(addr as *const libc::sockaddr_in);
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))))
+        }
+        libc::AF_INET6 => {
+            let sin6 = &*(addr as *const libc::sockaddr_in6);
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SocketEntry {
+    pub proto: String,
+    pub local: String,
+    pub local_port: Option<u16>,
+    pub remote: String,
+    pub remote_port: Option<u16>,
+    pub state: String,
+    pub inode: u64,
+}
+
+const TCP_STATES: [&str; 12] = [
+    "UNKNOWN", "ESTABLISHED", "SYN_SENT", "SYN_RECV", "FIN_WAIT1", "FIN_WAIT2",
+    "TIME_WAIT", "CLOSE", "CLOSE_WAIT", "LAST_ACK", "LISTEN", "CLOSING",
+];
+
+// TCP, UDP and Unix sockets; fails only when none of the tables can be read
+pub fn read_sockets() -> io::Result<Vec<SocketEntry>> {
+    let mut sockets = Vec::new();
+    let mut last_err = None;
+    for proto in ["tcp", "tcp6", "udp", "udp6", "unix"] {
+        match fs::read_to_string(format!("/proc/net/{}", proto)) {
+            Ok(text) if proto == "unix" => sockets.extend(parse_unix_sockets(&text)),
+            Ok(text) => sockets.extend(parse_inet_sockets(proto, &text)),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match last_err {
+        Some(err) if sockets.is_empty() => Err(err),
+        _ => Ok(sockets),
+    }
+}
+
+pub fn parse_inet_sockets(proto: &str, text: &str) -> Vec<SocketEntry> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            let (local, local_port) = parse_socket_address(fields[1])?;
+            let (remote, remote_port) = parse_socket_address(fields[2])?;
+            let state = usize::from_str_radix(fields[3], 16).unwrap_or(0);
+            let state = match (proto.starts_with("udp"), state) {
+                // UDP reuses TCP_CLOSE for unconnected sockets
+                (true, 7) => "UNCONN",
+                (_, state) => TCP_STATES.get(state).copied().unwrap_or("UNKNOWN"),
+            };
+            Some(SocketEntry {
+                proto: proto.to_string(),
+                local: format_socket_address(local, local_port),
+                local_port: Some(local_port),
+                remote: format_socket_address(remote, remote_port),
+                remote_port: Some(remote_port).filter(|port| *port != 0),
+                state: state.to_string(),
+                inode: fields[9].parse().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+// Addresses are the kernel's in-memory words printed as hex, so each 32-bit word is native-endian
+fn parse_socket_address(text: &str) -> Option<(IpAddr, u16)> {
+    let (address, port) = text.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let word = |i: usize| u32::from_str_radix(address.get(i * 8..i * 8 + 8)?, 16).ok().map(u32::to_ne_bytes);
+    let address = match address.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(word(0)?)),
+        32 => {
+            let mut bytes = [0u8; 16];
+            for i in 0..4 {
+                bytes[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        }
+        _ => return None,
+    };
+    Some((address, port))
+}
+
+fn format_socket_address(address: IpAddr, port: u16) -> String {
+    let port = if port == 0 { "*".to_string() } else { port.to_string() };
+    match address {
+        IpAddr::V4(address) => format!("{}:{}", address, port),
+        IpAddr::V6(address) => format!("[{}]:{}", address, port),
+    }
+}
+
+const SO_ACCEPTCON: u32 = 0x10000;
+
+pub fn parse_unix_sockets(text: &str) -> Vec<SocketEntry> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            let flags = u32::from_str_radix(fields[3], 16).unwrap_or(0);
+            let state = match fields[5] {
+                _ if flags & SO_ACCEPTCON != 0 => "LISTEN",
+                "01" => "UNCONNECTED",
+                "02" => "CONNECTING",
+                "03" => "CONNECTED",
+                "04" => "DISCONNECTING",
+                _ => "UNKNOWN",
+            };
+            let kind = match fields[4] {
+                "0001" => "stream",
+                "0002" => "dgram",
+                "0005" => "seqpacket",
+                _ => "unix",
+            };
+            Some(SocketEntry {
+                proto: format!("unix/{}", kind),
+                local: fields.get(7).map(|path| path.to_string()).unwrap_or_else(|| "-".to_string()),
+                local_port: None,
+                remote: "-".to_string(),
+                remote_port: None,
+                state: state.to_string(),
+                inode: fields[6].parse().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+// Maps socket inodes to (pid, command name) by walking /proc/<pid>/fd; other users' processes need root
+pub fn socket_owners() -> HashMap<u64, (u32, String)> {
+    let mut owners = HashMap::new();
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return owners,
+    };
+    for entry in entries.flatten() {
+        let pid: u32 = match entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let fds = match fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        let mut name = None;
+        for fd in fds.flatten() {
+            let inode = fs::read_link(fd.path()).ok().and_then(|target| {
+                target
+                    .to_str()
+                    .and_then(|t| t.strip_prefix("socket:[")?.strip_suffix(']')?.parse::<u64>().ok())
+            });
+            if let Some(inode) = inode {
+                let name = name.get_or_insert_with(|| {
+                    fs::read_to_string(entry.path().join("comm"))
+                        .map(|comm| comm.trim().to_string())
+                        .unwrap_or_default()
+                });
+                owners.insert(inode, (pid, name.clone()));
+            }
+        }
+    }
+    owners
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MountEntry {
+    pub device: String,
+    pub source: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub options: String,
+}
+
+pub fn read_mounts() -> io::Result<Vec<MountEntry>> {
+    Ok(parse_mountinfo(&fs::read_to_string("/proc/self/mountinfo")?))
+}
+
+// id parent major:minor root mount_point options [optional fields...] - fs_type source super_options
+pub fn parse_mountinfo(text: &str) -> Vec<MountEntry> {
+    text.lines()
+        .filter_map(|line| {
+            let (mount, fs) = line.split_once(" - ")?;
+            let mount: Vec<&str> = mount.split_whitespace().collect();
+            let mut fs = fs.split_whitespace();
+            if mount.len() < 6 {
+                return None;
+            }
+            Some(MountEntry {
+                device: mount[2].to_string(),
+                mount_point: unescape_mount_field(mount[4]),
+                options: mount[5].to_string(),
+                fs_type: fs.next()?.to_string(),
+                source: unescape_mount_field(fs.next().unwrap_or("none")),
+            })
+        })
+        .collect()
+}
+
+// Spaces, tabs, newlines and backslashes are written as \ooo octal escapes
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escaped.and_then(|octal| u8::from_str_radix(std::str::from_utf8(octal).ok()?, 8).ok()) {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsUsage {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub available_bytes: u64,
+    pub inodes_total: u64,
+    pub inodes_free: u64,
+}
+
+#[cfg(unix)]
+pub fn statvfs(path: &str) -> io::Result<FsUsage> {
+    let path = std::ffi::CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fragment = stat.f_frsize as u64;
+    Ok(FsUsage {
+        total_bytes: stat.f_blocks as u64 * fragment,
+        free_bytes: stat.f_bfree as u64 * fragment,
+        available_bytes: stat.f_bavail as u64 * fragment,
+        inodes_total: stat.f_files as u64,
+        inodes_free: stat.f_ffree as u64,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn statvfs(_path: &str) -> io::Result<FsUsage> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "statvfs is not available"))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelfUsage {
+    pub cpu_seconds: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+}
+
+// CPU time, resident memory and thread count of the console itself
+#[cfg(unix)]
+pub fn read_self_usage() -> io::Result<SelfUsage> {
+    let stat = fs::read_to_string("/proc/self/stat")?;
+    // The command name can contain spaces, so fields are counted from its closing parenthesis
+    let fields: Vec<&str> = stat.rsplit_once(')').map(|(_, rest)| rest.split_whitespace().collect()).unwrap_or_default();
+    let field = |index: usize| {
+        fields
+            .get(index)
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "short /proc/self/stat"))
+    };
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+    Ok(SelfUsage {
+        cpu_seconds: (field(11)? + field(12)?) as f64 / ticks,
+        rss_bytes: field(21)? * page_size,
+        threads: field(17)?,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn read_self_usage() -> io::Result<SelfUsage> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "process statistics are not available"))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiskStats {
+    pub name: String,
+    pub reads: u64,
+    pub sectors_read: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub sectors_written: u64,
+    pub write_ms: u64,
+    pub in_flight: u64,
+    pub io_ms: u64,
+    pub weighted_ms: u64,
+}
+
+// /proc/diskstats always counts 512-byte sectors, whatever the device's block size
+pub const SECTOR_BYTES: u64 = 512;
+
+pub fn read_diskstats() -> io::Result<Vec<DiskStats>> {
+    Ok(parse_diskstats(&fs::read_to_string("/proc/diskstats")?))
+}
+
+pub fn parse_diskstats(text: &str) -> Vec<DiskStats> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 14 {
+                return None;
+            }
+            let field = |i: usize| fields[i].parse().unwrap_or(0);
+            Some(DiskStats {
+                name: fields[2].to_string(),
+                reads: field(3),
+                sectors_read: field(5),
+                read_ms: field(6),
+                writes: field(7),
+                sectors_written: field(9),
+                write_ms: field(10),
+                in_flight: field(11),
+                io_ms: field(12),
+                weighted_ms: field(13),
+            })
+        })
+        .collect()
+}
+
+// Whole disks appear in /sys/block, partitions only below their parent
+pub fn is_whole_disk(name: &str) -> bool {
+    Path::new("/sys/block").join(name).exists() || !Path::new("/sys/block").exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_STAT: &str = "\
+cpu  97228 120 10413 356740 569 0 18 10509 0 0
+cpu0 48614 60 5206 178370 284 0 9 5254 0 0
+cpu1 48614 60 5207 178370 285 0 9 5255 0 0
+intr 506328 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1 1 2 0 0
+ctxt 1196986
+btime 1760745600
+processes 4127
+procs_running 2
+";
+
+    #[test]
+    fn cpu_times_aggregate_then_per_core() {
+        let cpus = parse_cpu_times(PROC_STAT);
+        let names: Vec<&str> = cpus.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["cpu", "cpu0", "cpu1"]);
+
+        let total = cpus[0].1;
+        assert_eq!((total.user, total.nice, total.system, total.idle), (97228, 120, 10413, 356740));
+        assert_eq!((total.iowait, total.irq, total.softirq, total.steal), (569, 0, 18, 10509));
+        // guest time is already included in user, so it is not counted twice
+        assert_eq!(total.total(), 97228 + 120 + 10413 + 356740 + 569 + 18 + 10509);
+        assert_eq!(cpus[2].1.system, 5207);
+    }
+
+    #[test]
+    fn cpu_times_old_kernels_and_short_lines() {
+        // Pre-2.6 kernels report only user, nice, system and idle
+        let cpus = parse_cpu_times("cpu 10 20 30 40\ncpu0 10 20 30 40\ncpu1 1 2 3\n");
+        assert_eq!(cpus.len(), 2);
+        assert_eq!(cpus[1].1.idle, 40);
+        assert_eq!(cpus[1].1.iowait, 0);
+        assert_eq!(cpus[1].1.total(), 100);
+    }
+
+    // Hex as the kernel prints it: each 32-bit word of the address in host byte order
+    fn kernel_hex(octets: &[u8]) -> String {
+        octets
+            .chunks(4)
+            .map(|word| format!("{:08X}", u32::from_ne_bytes([word[0], word[1], word[2], word[3]])))
+            .collect()
+    }
+
+    const TCP_HEADER: &str =
+        "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n";
+
+    #[test]
+    fn inet_sockets_native_endian_ipv4() {
+        let text = format!(
+            "{}   0: {}:0016 {}:0000 0A 00000000:00000000 00:00000000 00000000     0        0 6148 1 0000000000000000 100 0 0 10 0\n   \
+             1: {}:D2F4 {}:01BB 01 00000000:00000000 02:000A7D3B 00000000  1000        0 91237 2 0000000000000000 20 4 30 10 -1\n",
+            TCP_HEADER,
+            kernel_hex(&[127, 0, 0, 1]),
+            kernel_hex(&[0, 0, 0, 0]),
+            kernel_hex(&[192, 168, 1, 20]),
+            kernel_hex(&[93, 184, 216, 34]),
+        );
+        let sockets = parse_inet_sockets("tcp", &text);
+        assert_eq!(sockets.len(), 2);
+
+        assert_eq!(sockets[0].local, "127.0.0.1:22");
+        assert_eq!(sockets[0].local_port, Some(22));
+        assert_eq!(sockets[0].remote, "0.0.0.0:*");
+        assert_eq!(sockets[0].remote_port, None);
+        assert_eq!(sockets[0].state, "LISTEN");
+        assert_eq!(sockets[0].inode, 6148);
+
+        assert_eq!(sockets[1].local, "192.168.1.20:54004");
+        assert_eq!(sockets[1].remote, "93.184.216.34:443");
+        assert_eq!(sockets[1].remote_port, Some(443));
+        assert_eq!(sockets[1].state, "ESTABLISHED");
+    }
+
+    #[test]
+    fn inet_sockets_native_endian_ipv6() {
+        let loopback = Ipv6Addr::LOCALHOST.octets();
+        let link_local: Ipv6Addr = "fe80::1c2b:3ff:fe4d:5e6f".parse().unwrap();
+        let text = format!(
+            "{}   0: {}:0277 {}:0000 07 00000000:00000000 00:00000000 00000000     0        0 3021 2 0000000000000000 0\n   \
+             1: {}:0035 {}:C350 07 00000000:00000000 00:00000000 00000000   101        0 4410 2 0000000000000000 0\n",
+            TCP_HEADER,
+            kernel_hex(&loopback),
+            kernel_hex(&[0; 16]),
+            kernel_hex(&link_local.octets()),
+            kernel_hex(&[0; 16]),
+        );
+        let sockets = parse_inet_sockets("udp6", &text);
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].local, "[::1]:631");
+        assert_eq!(sockets[0].remote, "[::]:*");
+        // UDP reports TCP_CLOSE for sockets without a peer
+        assert_eq!(sockets[0].state, "UNCONN");
+        assert_eq!(sockets[1].local, "[fe80::1c2b:3ff:fe4d:5e6f]:53");
+        assert_eq!(sockets[1].remote, "[::]:50000");
+        assert_eq!(sockets[1].proto, "udp6");
+    }
+
+    // Captured from an x86_64 host, so the words are little-endian
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn inet_sockets_captured_sample() {
+        let text = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:26B7 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 6148 1 000000000a4804d6 100 0 0 10 0
+   1: 00000000:07E8 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 662 1 00000000643efb66 100 0 0 10 0
+   2: 0F02000A:9C40 2BFCA8C0:0016 06 00000000:00000000 03:00000C4A 00000000     0        0 0 3 00000000b3c92a1e
+";
+        let sockets = parse_inet_sockets("tcp", text);
+        assert_eq!(sockets.len(), 3);
+        assert_eq!(sockets[0].local, "127.0.0.1:9911");
+        assert_eq!(sockets[1].local, "0.0.0.0:2024");
+        assert_eq!(sockets[2].local, "10.0.2.15:40000");
+        assert_eq!(sockets[2].remote, "192.168.252.43:22");
+        assert_eq!(sockets[2].state, "TIME_WAIT");
+
+        let tcp6 = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 3021 1 0000000000000000 100 0 0 10 0
+";
+        let sockets = parse_inet_sockets("tcp6", tcp6);
+        assert_eq!(sockets[0].local, "[::1]:631");
+        assert_eq!(sockets[0].state, "LISTEN");
+    }
+
+    #[test]
+    fn inet_sockets_skip_malformed_rows() {
+        let text = format!("{}   0: 0100007F 00000000:0000 0A\n   1: 0100:0016 00000000:0000 0A 0 0 0 0 0 1\n", TCP_HEADER);
+        assert!(parse_inet_sockets("tcp", &text).is_empty());
+    }
+
+    #[test]
+    fn unix_sockets() {
+        let text = "\
+Num       RefCount Protocol Flags    Type St Inode Path
+00000000e95ce34a: 00000003 00000000 00000000 0001 03 41061
+00000000f059a179: 00000002 00000000 00010000 0001 01 39365 /run/dbus/system_bus_socket
+00000000999f1a7a: 00000002 00000000 00000000 0002 01 17120 @/org/kernel/udev/udevd
+0000000012ab34cd: 00000002 00000000 00000000 0005 02 20001
+";
+        let sockets = parse_unix_sockets(text);
+        assert_eq!(sockets.len(), 4);
+
+        assert_eq!(sockets[0].proto, "unix/stream");
+        assert_eq!(sockets[0].state, "CONNECTED");
+        assert_eq!(sockets[0].local, "-");
+        assert_eq!(sockets[0].inode, 41061);
+
+        // SO_ACCEPTCON marks listening sockets whatever their state column says
+        assert_eq!(sockets[1].state, "LISTEN");
+        assert_eq!(sockets[1].local, "/run/dbus/system_bus_socket");
+
+        assert_eq!(sockets[2].proto, "unix/dgram");
+        assert_eq!(sockets[2].state, "UNCONNECTED");
+        assert_eq!(sockets[2].local, "@/org/kernel/udev/udevd");
+
+        assert_eq!(sockets[3].proto, "unix/seqpacket");
+        assert_eq!(sockets[3].state, "CONNECTING");
+        assert!(sockets.iter().all(|s| s.local_port.is_none() && s.remote_port.is_none()));
+    }
+
+    #[test]
+    fn mountinfo_entries() {
+        let text = "\
+23 28 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+28 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw,errors=remount-ro
+41 28 259:1 / /boot/efi rw,relatime shared:30 master:2 - vfat /dev/nvme0n1p1 rw,fmask=0077
+57 28 0:45 /home/user/My\\040Files /mnt/My\\040Files rw,relatime - ext4 /dev/nvme0n1p2 rw
+62 28 0:48 / /media/usb\\011stick rw,nosuid - fuseblk /dev/sdb1\\134x rw
+truncated line without separator
+";
+        let mounts = parse_mountinfo(text);
+        assert_eq!(mounts.len(), 5);
+
+        assert_eq!(mounts[0].mount_point, "/proc");
+        assert_eq!(mounts[0].fs_type, "proc");
+        assert_eq!(mounts[0].device, "0:22");
+
+        assert_eq!(mounts[1].mount_point, "/");
+        assert_eq!(mounts[1].source, "/dev/nvme0n1p2");
+        assert_eq!(mounts[1].device, "259:2");
+        assert_eq!(mounts[1].options, "rw,relatime");
+
+        // Any number of optional fields may precede the separator
+        assert_eq!(mounts[2].mount_point, "/boot/efi");
+        assert_eq!(mounts[2].fs_type, "vfat");
+
+        assert_eq!(mounts[3].mount_point, "/mnt/My Files");
+        assert_eq!(mounts[4].mount_point, "/media/usb\tstick");
+        assert_eq!(mounts[4].source, "/dev/sdb1\\x");
+    }
+
+    #[test]
+    fn mount_field_escapes() {
+        assert_eq!(unescape_mount_field("/mnt/My\\040Files"), "/mnt/My Files");
+        assert_eq!(unescape_mount_field("a\\011b\\012c"), "a\tb\nc");
+        assert_eq!(unescape_mount_field("back\\134slash"), "back\\slash");
+        assert_eq!(unescape_mount_field("/mnt/caf\\303\\251"), "/mnt/café");
+        // Incomplete or non-octal escapes are kept as written
+        assert_eq!(unescape_mount_field("tail\\04"), "tail\\04");
+        assert_eq!(unescape_mount_field("not\\089octal"), "not\\089octal");
+        assert_eq!(unescape_mount_field(""), "");
+    }
+
+    #[test]
+    fn diskstats_field_counts() {
+        // 14 fields before 4.18, 18 with discard counters, 20 once flush counters were added in 5.5
+        let text = "\
+   8       0 sda 4210 120 338164 1903 9021 5533 412360 20110 0 11472 22013
+   8       1 sda1 3980 120 330020 1850 8800 5533 410000 20000 0 11300 21850
+ 259       0 nvme0n1 81234 1520 6512344 20411 153022 98211 9822100 301234 2 190220 321645 0 0 0 0
+ 259       1 nvme0n1p1 412 0 18110 88 2 0 2 0 0 120 88 11 0 4096 3
+   7       0 loop0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+ 253       0 dm-0 70011 0 6102232 18833 251000 0 9822100 512044 5 201100 530877 0 0 0 0 1021 2034
+   1       0 ram0 1 2
+";
+        let disks = parse_diskstats(text);
+        let names: Vec<&str> = disks.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["sda", "sda1", "nvme0n1", "nvme0n1p1", "loop0", "dm-0"]);
+
+        let sda = &disks[0];
+        assert_eq!((sda.reads, sda.sectors_read, sda.writes, sda.weighted_ms), (4210, 338164, 9021, 22013));
+
+        let nvme = &disks[2];
+        assert_eq!((nvme.reads, nvme.sectors_read, nvme.read_ms), (81234, 6512344, 20411));
+        assert_eq!((nvme.writes, nvme.sectors_written, nvme.write_ms), (153022, 9822100, 301234));
+        assert_eq!((nvme.in_flight, nvme.io_ms, nvme.weighted_ms), (2, 190220, 321645));
+        assert_eq!(nvme.sectors_read * SECTOR_BYTES, 3_334_320_128);
+
+        // Discard and flush columns follow the classic eleven and are ignored
+        assert_eq!(disks[3].weighted_ms, 88);
+        assert_eq!(disks[5].in_flight, 5);
+        assert_eq!(disks[5].weighted_ms, 530877);
+    }
+
+    #[test]
+    fn diskstats_short_rows() {
+        // Partitions on 2.6.25 and older kernels only had four counters
+        let disks = parse_diskstats("   8       1 sda1 3980 330020 8800 410000\n   8       0 sda 4210 120 338164 1903 9021 5533 412360 20110 0 11472 22013\n");
+        assert_eq!(disks.len(), 1);
+        assert_eq!(disks[0].name, "sda");
+        assert_eq!((disks[0].reads, disks[0].writes, disks[0].io_ms), (4210, 9021, 11472));
+        assert!(parse_diskstats("").is_empty());
+    }
+
+    #[test]
+    fn net_dev_counters() {
+        let text = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 114473035   12350    0    0    0     0          0         0 114473035   12350    0    0    0     0       0          0
+  eth0:9876543210 7654321   12   34    0     0          0       120 123456789  654321    5    6    0     0       0          0
+wlp0s20f3: 1000 10 0 0 0 0 0 0 2000 20 0 0 0 0 0 0
+  tun0: 1 2 3
+";
+        let devices = parse_net_dev(text);
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["lo", "eth0", "wlp0s20f3"]);
+
+        // Large counters run straight into the colon
+        let eth0 = &devices[1];
+        assert_eq!((eth0.rx_bytes, eth0.rx_packets, eth0.rx_errors, eth0.rx_dropped), (9876543210, 7654321, 12, 34));
+        assert_eq!((eth0.tx_bytes, eth0.tx_packets, eth0.tx_errors, eth0.tx_dropped), (123456789, 654321, 5, 6));
+        assert_eq!(devices[2].tx_bytes, 2000);
+    }
+}
@@ -0,0 +1,464 @@
+// Package repository mirror health: reachability, index freshness, signature presence and cache size
+use crate::alerts::{Alert, AlertManager};
+use crate::http;
+use crate::packages::PackagesConfig;
+use crate::system::SystemState;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const REPO_CHECK: Duration = Duration::from_secs(300);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryConfig {
+    pub name: String,
+    // file:///path/to/mirror or http://host:port/path
+    pub url: String,
+    // Index file relative to the url
+    #[serde(default = "default_index")]
+    pub index: String,
+    // Detached signature relative to the url, "<index>.sig" when unset
+    #[serde(default)]
+    pub signature: Option<String>,
+    // Alert when no signature file at least as new as the index is present; its contents are not verified
+    #[serde(default = "default_true")]
+    pub require_signature: bool,
+    // Local directory holding downloaded packages from this repository
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    // Index older than this is reported as stale
+    #[serde(default = "default_max_age")]
+    pub max_age_hours: u64,
+}
+
+fn default_index() -> String {
+    "repo.toml".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_age() -> u64 {
+    48
+}
+
+impl RepositoryConfig {
+    fn signature_path(&self) -> String {
+        self.signature.clone().unwrap_or_else(|| format!("{}.sig", self.index))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RepoHealth {
+    pub name: String,
+    // "ONLINE", "STALE", "NO SIGNATURE", "NO INDEX" or "UNREACHABLE"
+    pub status: String,
+    pub error: Option<String>,
+    pub age_hours: Option<f64>,
+    pub max_age_hours: u64,
+    // Whether a signature file at least as new as the index exists; None when it could not be looked up
+    pub signature_present: Option<bool>,
+    pub require_signature: bool,
+    pub cache_bytes: Option<u64>,
+}
+
+impl RepoHealth {
+    pub fn stale(&self) -> bool {
+        self.age_hours.is_some_and(|age| age > self.max_age_hours as f64)
+    }
+
+    pub fn missing_signature(&self) -> bool {
+        self.require_signature && self.signature_present == Some(false)
+    }
+}
+
+// What a single probe of the index or signature found
+struct Remote {
+    exists: bool,
+    modified: Option<SystemTime>,
+}
+
+fn probe(base: &str, file: &str) -> Result<Remote, String> {
+    if let Some(dir) = base.strip_prefix("file://") {
+        let path = Path::new(dir).join(file);
+        if !Path::new(dir).is_dir() {
+            return Err(format!("{} is not a directory", dir));
+        }
+        return Ok(match fs::metadata(&path) {
+            Ok(metadata) => Remote { exists: true, modified: metadata.modified().ok() },
+            Err(_) => Remote { exists: false, modified: None },
+        });
+    }
+
+    let url = format!("{}/{}", base.trim_end_matches('/'), file);
+    let response = http::request("HEAD", &url, None, HTTP_TIMEOUT).map_err(|e| e.to_string())?;
+    match response.status {
+        200..=299 => Ok(Remote {
+            exists: true,
+            modified: response
+                .header("Last-Modified")
+                .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+                .map(SystemTime::from),
+        }),
+        404 | 410 => Ok(Remote { exists: false, modified: None }),
+        status => Err(format!("HTTP {} for {}", status, url)),
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+pub fn check(repo: &RepositoryConfig) -> RepoHealth {
+    let mut health = RepoHealth {
+        name: repo.name.clone(),
+        status: "ONLINE".to_string(),
+        error: None,
+        age_hours: None,
+        max_age_hours: repo.max_age_hours,
+        signature_present: None,
+        require_signature: repo.require_signature,
+        cache_bytes: repo.cache_dir.as_deref().filter(|dir| dir.is_dir()).map(dir_size),
+    };
+
+    let index = match probe(&repo.url, &repo.index) {
+        Ok(index) => index,
+        Err(err) => {
+            health.status = "UNREACHABLE".to_string();
+            health.error = Some(err);
+            return health;
+        }
+    };
+    if !index.exists {
+        health.status = "NO INDEX".to_string();
+        health.error = Some(format!("{} not found", repo.index));
+        return health;
+    }
+
+    if let Some(modified) = index.modified {
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        health.age_hours = Some(age.as_secs_f64() / 3600.0);
+    }
+    // A signature older than the index was made for a previous index
+    health.signature_present = match probe(&repo.url, &repo.signature_path()) {
+        Ok(signature) => Some(signature.exists && signature.modified.zip(index.modified).is_none_or(|(s, i)| s >= i)),
+        Err(err) => {
+            health.error = Some(err);
+            None
+        }
+    };
+
+    if health.stale() {
+        health.status = "STALE".to_string();
+    } else if health.missing_signature() {
+        health.status = "NO SIGNATURE".to_string();
+    }
+    health
+}
+
+pub struct RepoMonitor {
+    repos: Vec<RepositoryConfig>,
+    pub health: Vec<RepoHealth>,
+    pub checked_at: Option<Instant>,
+    checking: bool,
+    results_tx: Sender<Vec<RepoHealth>>,
+    results_rx: Receiver<Vec<RepoHealth>>,
+}
+
+impl RepoMonitor {
+    pub fn new(config: &PackagesConfig) -> Self {
+        let mut repos = config.repositories.clone();
+        // The local Redox repository is checked even when no mirrors are listed
+        if repos.is_empty() {
+            if let Some(dir) = &config.repo {
+                repos.push(RepositoryConfig {
+                    name: "local".to_string(),
+                    url: format!("file://{}", dir.display()),
+                    index: default_index(),
+                    signature: None,
+                    require_signature: true,
+                    cache_dir: None,
+                    max_age_hours: default_max_age(),
+                });
+            }
+        }
+
+        let (results_tx, results_rx) = mpsc::channel();
+        Self {
+            repos,
+            health: Vec::new(),
+            checked_at: None,
+            checking: false,
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    pub fn is_checking(&self) -> bool {
+        self.checking
+    }
+
+    // Probes every repository on a background thread; http mirrors can take the full timeout
+    pub fn refresh(&mut self) {
+        if self.repos.is_empty() || self.checking {
+            return;
+        }
+        if self.checked_at.is_some_and(|at| at.elapsed() < REPO_CHECK) {
+            return;
+        }
+        self.checking = true;
+        let repos = self.repos.clone();
+        let tx = self.results_tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(repos.iter().map(check).collect());
+        });
+    }
+
+    pub fn check_now(&mut self) {
+        self.checked_at = None;
+        self.refresh();
+    }
+
+    pub fn collect_results(&mut self, system: &mut SystemState, alerts: &mut AlertManager) {
+        while let Ok(health) = self.results_rx.try_recv() {
+            self.checking = false;
+            self.checked_at = Some(Instant::now());
+            let now = Local::now();
+            for repo in &health {
+                if let Some(age) = repo.age_hours {
+                    system.metrics.record(&format!("repo.{}.age_hours", repo.name), now.timestamp(), age);
+                }
+                if let Some(bytes) = repo.cache_bytes {
+                    system.metrics.record(&format!("repo.{}.cache_bytes", repo.name), now.timestamp(), bytes as f64);
+                }
+                if let Some(err) = &repo.error {
+                    system.log("WARN", "Packages", &format!("Repository {}: {}", repo.name, err));
+                }
+
+                let stale_rule = format!("repo-stale:{}", repo.name);
+                if repo.stale() {
+                    let age = repo.age_hours.unwrap_or_default();
+                    alerts.raise(system, Alert {
+                        rule: stale_rule,
+                        state: "FIRING".to_string(),
+                        severity: "WARN".to_string(),
+                        metric: format!("repo.{}.age_hours", repo.name),
+                        value: age,
+                        threshold: repo.max_age_hours as f64,
+                        message: format!("{} index is {:.0}h old (limit {}h)", repo.name, age, repo.max_age_hours),
+                        timestamp: now,
+                    });
+                } else if repo.age_hours.is_some() {
+                    alerts.resolve(system, &stale_rule, format!("{} index refreshed", repo.name));
+                }
+
+                let signature_rule = format!("repo-signature:{}", repo.name);
+                if repo.missing_signature() {
+                    alerts.raise(system, Alert {
+                        rule: signature_rule,
+                        state: "FIRING".to_string(),
+                        severity: "ERROR".to_string(),
+                        metric: format!("repo.{}.signature_present", repo.name),
+                        value: 0.0,
+                        threshold: 1.0,
+                        message: format!("{} index has no signature as new as the index", repo.name),
+                        timestamp: now,
+                    });
+                } else if repo.signature_present.is_some() {
+                    alerts.resolve(system, &signature_rule, format!("{} index signature present", repo.name));
+                }
+            }
+
+            let healthy = health.iter().all(|repo| repo.status == "ONLINE");
+            let status = if healthy { "ONLINE" } else { "DEGRADED" };
+            system.subsystem_status.insert("Package Repositories".to_string(), status.to_string());
+            self.health = health;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("redox-console-repos-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn repo(url: String) -> RepositoryConfig {
+        RepositoryConfig {
+            name: "mirror".to_string(),
+            url,
+            index: default_index(),
+            signature: None,
+            require_signature: true,
+            cache_dir: None,
+            max_age_hours: 48,
+        }
+    }
+
+    fn write_aged(path: &Path, hours: u64) {
+        let file = File::create(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(hours * 3600)).unwrap();
+    }
+
+    fn http_date(hours_ago: i64) -> String {
+        (chrono::Utc::now() - chrono::Duration::hours(hours_ago)).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+
+    // Answers `requests` HEAD requests, looking each path up in `routes` and returning 404 otherwise
+    fn serve(routes: Vec<(&'static str, String)>, requests: usize) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://127.0.0.1:{}/redox", listener.local_addr().unwrap().port());
+        let handle = thread::spawn(move || {
+            let mut seen = Vec::new();
+            for _ in 0..requests {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or("").to_string();
+                let response = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map_or("HTTP/1.1 404 Not Found\r\n\r\n".to_string(), |(_, response)| response.clone());
+                reader.into_inner().write_all(response.as_bytes()).unwrap();
+                seen.push(request_line.trim_end().to_string());
+            }
+            seen
+        });
+        (base, handle)
+    }
+
+    fn ok(last_modified: String) -> String {
+        format!("HTTP/1.1 200 OK\r\nLast-Modified: {}\r\nContent-Length: 0\r\n\r\n", last_modified)
+    }
+
+    #[test]
+    fn file_repository_states() {
+        let dir = temp_dir("file");
+        let url = format!("file://{}", dir.display());
+        let index = dir.join("repo.toml");
+        let signature = dir.join("repo.toml.sig");
+
+        assert_eq!(check(&repo(url.clone())).status, "NO INDEX");
+
+        write_aged(&index, 2);
+        let health = check(&repo(url.clone()));
+        assert_eq!(health.status, "NO SIGNATURE");
+        assert_eq!(health.signature_present, Some(false));
+        assert!((health.age_hours.unwrap() - 2.0).abs() < 0.1);
+        let mut optional = repo(url.clone());
+        optional.require_signature = false;
+        assert_eq!(check(&optional).status, "ONLINE");
+
+        // A signature made for an earlier index does not count
+        write_aged(&signature, 3);
+        assert_eq!(check(&repo(url.clone())).status, "NO SIGNATURE");
+
+        write_aged(&signature, 1);
+        let health = check(&repo(url.clone()));
+        assert_eq!(health.status, "ONLINE");
+        assert_eq!(health.signature_present, Some(true));
+        assert!(health.error.is_none());
+
+        write_aged(&index, 72);
+        let health = check(&repo(url.clone()));
+        assert_eq!(health.status, "STALE");
+        assert!(health.stale());
+
+        let cache = dir.join("cache");
+        fs::create_dir_all(cache.join("sub")).unwrap();
+        fs::write(cache.join("a.pkg"), [0u8; 100]).unwrap();
+        fs::write(cache.join("sub/b.pkg"), [0u8; 28]).unwrap();
+        let mut cached = repo(url);
+        cached.cache_dir = Some(cache);
+        assert_eq!(check(&cached).cache_bytes, Some(128));
+
+        let health = check(&repo(format!("file://{}", dir.join("missing").display())));
+        assert_eq!(health.status, "UNREACHABLE");
+        assert!(health.error.unwrap().contains("is not a directory"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn http_repository_online() {
+        let (base, server) = serve(
+            vec![("/redox/repo.toml", ok(http_date(1))), ("/redox/repo.toml.sig", ok(http_date(0)))],
+            2,
+        );
+        let health = check(&repo(base));
+        assert_eq!(health.status, "ONLINE");
+        assert_eq!(health.signature_present, Some(true));
+        assert!((health.age_hours.unwrap() - 1.0).abs() < 0.1);
+        assert_eq!(server.join().unwrap(), ["HEAD /redox/repo.toml HTTP/1.1", "HEAD /redox/repo.toml.sig HTTP/1.1"]);
+    }
+
+    #[test]
+    fn http_repository_stale_and_unsigned() {
+        let (base, server) = serve(vec![("/redox/repo.toml", ok(http_date(100)))], 2);
+        let health = check(&repo(base));
+        assert_eq!(health.status, "STALE");
+        assert_eq!(health.signature_present, Some(false));
+        server.join().unwrap();
+
+        let (base, server) = serve(vec![("/redox/repo.toml", ok(http_date(5)))], 2);
+        assert_eq!(check(&repo(base)).status, "NO SIGNATURE");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn http_repository_missing_or_unreachable() {
+        let (base, server) = serve(Vec::new(), 1);
+        let health = check(&repo(base));
+        assert_eq!(health.status, "NO INDEX");
+        assert_eq!(health.signature_present, None);
+        server.join().unwrap();
+
+        let (base, server) = serve(vec![("/redox/repo.toml", "HTTP/1.1 503 Service Unavailable\r\n\r\n".to_string())], 1);
+        let health = check(&repo(base));
+        assert_eq!(health.status, "UNREACHABLE");
+        assert!(health.error.unwrap().starts_with("HTTP 503"));
+        server.join().unwrap();
+
+        // Nothing listens on a port that was just released
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let health = check(&repo(format!("http://127.0.0.1:{}/redox", port)));
+        assert_eq!(health.status, "UNREACHABLE");
+        assert!(health.error.is_some());
+    }
+}
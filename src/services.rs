@@ -0,0 +1,789 @@
+// Service backends: systemd through systemctl, or a small file-based supervisor
+use crate::config;
+use crate::pipeline::backoff;
+use crate::system::{ServiceStatus, SystemState};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServicesConfig {
+    // "auto", "systemd" or "supervisor"
+    pub backend: String,
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ServicesConfig {
+    fn default() -> Self {
+        Self {
+            backend: "auto".to_string(),
+            dir: None,
+        }
+    }
+}
+
+pub trait ServiceBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn list(&self) -> anyhow::Result<Vec<ServiceStatus>>;
+    // action is "start", "stop" or "restart"
+    fn control(&self, service: &str, action: &str) -> anyhow::Result<String>;
+}
+
+pub fn open_backend(config: &ServicesConfig) -> Option<Arc<dyn ServiceBackend>> {
+    let supervisor_dir = config
+        .dir
+        .clone()
+        .or_else(|| config::config_dir().map(|dir| dir.join("services")));
+    match config.backend.as_str() {
+        "systemd" => Some(Arc::new(SystemdBackend)),
+        "supervisor" => supervisor_dir.map(|dir| Arc::new(SupervisorBackend { dir }) as Arc<dyn ServiceBackend>),
+        _ if Path::new("/run/systemd/system").exists() => Some(Arc::new(SystemdBackend)),
+        _ => supervisor_dir
+            .filter(|dir| dir.is_dir())
+            .map(|dir| Arc::new(SupervisorBackend { dir }) as Arc<dyn ServiceBackend>),
+    }
+}
+
+fn status_label(active_state: &str) -> &'static str {
+    match active_state {
+        "active" | "reloading" => "RUNNING",
+        "activating" | "deactivating" => "STARTING",
+        "failed" => "FAILED",
+        _ => "STOPPED",
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    if secs >= 86400 {
+        format!("{}d {}h", secs / 86400, secs % 86400 / 3600)
+    } else if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else {
+        format!("{}m", secs / 60)
+    }
+}
+
+// Systemd
+
+pub struct SystemdBackend;
+
+impl SystemdBackend {
+    fn systemctl(args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new("systemctl")
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .context("running systemctl")?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl ServiceBackend for SystemdBackend {
+    fn name(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<ServiceStatus>> {
+        let units = Self::systemctl(&["list-units", "--type=service", "--all", "--plain", "--no-legend", "--no-pager"])?;
+        let names: Vec<&str> = units
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|unit| unit.ends_with(".service"))
+            .collect();
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut args = vec![
+            "show",
+            "--no-pager",
+            "-p",
+            "Id,Description,ActiveState,SubState,MainPID,MemoryCurrent,NRestarts,ActiveEnterTimestampMonotonic,Requires,BindsTo,Requisite",
+        ];
+        args.extend(&names);
+        let shown = Self::systemctl(&args)?;
+        let uptime = fs::read_to_string("/proc/uptime")
+            .ok()
+            .and_then(|text| text.split_whitespace().next()?.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        Ok(parse_systemctl_show(&shown, uptime))
+    }
+
+    fn control(&self, service: &str, action: &str) -> anyhow::Result<String> {
+        Self::systemctl(&["--no-ask-password", action, service])?;
+        Ok(format!("{} {}", action, service))
+    }
+}
+
+// `systemctl show` prints one Key=Value block per unit, separated by blank lines
+pub fn parse_systemctl_show(text: &str, uptime_secs: f64) -> Vec<ServiceStatus> {
+    text.split("\n\n")
+        .filter_map(|block| {
+            let props: HashMap<&str, &str> = block.lines().filter_map(|line| line.split_once('=')).collect();
+            let name = props.get("Id")?.to_string();
+            let active_state = props.get("ActiveState").copied().unwrap_or("unknown");
+            let since_boot = props
+                .get("ActiveEnterTimestampMonotonic")
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|us| *us > 0 && active_state == "active");
+            Some(ServiceStatus {
+                status: status_label(active_state).to_string(),
+                uptime: since_boot
+                    .map(|us| format_uptime((uptime_secs - us as f64 / 1_000_000.0).max(0.0) as u64))
+                    .unwrap_or_else(|| "-".to_string()),
+                description: props.get("Description").unwrap_or(&"").to_string(),
+                active_state: active_state.to_string(),
+                sub_state: props.get("SubState").unwrap_or(&"-").to_string(),
+                main_pid: props.get("MainPID").and_then(|v| v.parse().ok()).filter(|pid| *pid > 0),
+                // "[not set]" when memory accounting is off
+                memory_bytes: props.get("MemoryCurrent").and_then(|v| v.parse().ok()),
+                restarts: props.get("NRestarts").and_then(|v| v.parse().ok()).unwrap_or(0),
+                // Hard dependencies on other services; targets, slices and mounts are left out
+                requires: ["Requires", "BindsTo", "Requisite"]
+                    .iter()
+                    .filter_map(|key| props.get(key))
+                    .flat_map(|units| units.split_whitespace())
+                    .filter(|unit| unit.ends_with(".service"))
+                    .map(|unit| unit.to_string())
+                    .collect(),
+                name,
+            })
+        })
+        .collect()
+}
+
+// File-based supervisor: <dir>/<name>.toml definitions, pid and log files under <dir>/run
+
+#[derive(Debug, Deserialize)]
+struct ServiceDefinition {
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    requires: Vec<String>,
+}
+
+pub struct SupervisorBackend {
+    dir: PathBuf,
+}
+
+impl SupervisorBackend {
+    fn definitions(&self) -> anyhow::Result<Vec<(String, ServiceDefinition)>> {
+        let mut definitions = Vec::new();
+        for entry in fs::read_dir(&self.dir).with_context(|| format!("reading {}", self.dir.display()))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                let text = fs::read_to_string(&path)?;
+                let definition = toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+                definitions.push((name, definition));
+            }
+        }
+        definitions.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(definitions)
+    }
+
+    fn run_file(&self, name: &str, extension: &str) -> PathBuf {
+        self.dir.join("run").join(format!("{}.{}", name, extension))
+    }
+
+    fn pid(&self, name: &str) -> Option<u32> {
+        fs::read_to_string(self.run_file(name, "pid")).ok()?.trim().parse().ok()
+    }
+
+    fn restarts(&self, name: &str) -> u32 {
+        fs::read_to_string(self.run_file(name, "restarts"))
+            .ok()
+            .and_then(|text| text.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn start(&self, name: &str) -> anyhow::Result<String> {
+        if self.pid(name).is_some_and(process_alive) {
+            return Ok(format!("{} already running", name));
+        }
+        let definition = self
+            .definitions()?
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, definition)| definition)
+            .with_context(|| format!("no definition for {}", name))?;
+
+        fs::create_dir_all(self.dir.join("run"))?;
+        let log = OpenOptions::new().create(true).append(true).open(self.run_file(name, "log"))?;
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&definition.command)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        // Keep terminal signals aimed at the console away from supervised services
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command.spawn().with_context(|| format!("starting {}", name))?;
+        fs::write(self.run_file(name, "pid"), child.id().to_string())?;
+        Ok(format!("started {} (pid {})", name, child.id()))
+    }
+
+    fn stop(&self, name: &str) -> anyhow::Result<String> {
+        let pid = match self.pid(name) {
+            Some(pid) if process_alive(pid) => pid,
+            _ => {
+                let _ = fs::remove_file(self.run_file(name, "pid"));
+                return Ok(format!("{} not running", name));
+            }
+        };
+        signal(pid, libc::SIGTERM);
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while process_alive(pid) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(100));
+        }
+        let forced = process_alive(pid);
+        if forced {
+            signal(pid, libc::SIGKILL);
+        }
+        fs::remove_file(self.run_file(name, "pid"))?;
+        Ok(format!("stopped {}{}", name, if forced { " (killed)" } else { "" }))
+    }
+}
+
+impl ServiceBackend for SupervisorBackend {
+    fn name(&self) -> &'static str {
+        "supervisor"
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<ServiceStatus>> {
+        Ok(self
+            .definitions()?
+            .into_iter()
+            .map(|(name, definition)| {
+                let pid = self.pid(&name);
+                let running = pid.filter(|pid| process_alive(*pid));
+                // A pid file without a live process means it exited without being stopped
+                let (active_state, sub_state) = match (pid, running) {
+                    (_, Some(_)) => ("active", "running"),
+                    (Some(_), None) => ("failed", "exited"),
+                    (None, _) => ("inactive", "dead"),
+                };
+                let uptime = running
+                    .and_then(|_| fs::metadata(self.run_file(&name, "pid")).ok()?.modified().ok())
+                    .and_then(|started| SystemTime::now().duration_since(started).ok())
+                    .map(|age| format_uptime(age.as_secs()))
+                    .unwrap_or_else(|| "-".to_string());
+                ServiceStatus {
+                    status: status_label(active_state).to_string(),
+                    uptime,
+                    description: definition.description,
+                    active_state: active_state.to_string(),
+                    sub_state: sub_state.to_string(),
+                    main_pid: running,
+                    memory_bytes: running.and_then(resident_bytes),
+                    restarts: self.restarts(&name),
+                    requires: definition.requires,
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    fn control(&self, service: &str, action: &str) -> anyhow::Result<String> {
+        match action {
+            "start" => self.start(service),
+            "stop" => self.stop(service),
+            "restart" => {
+                self.stop(service)?;
+                fs::write(self.run_file(service, "restarts"), (self.restarts(service) + 1).to_string())?;
+                self.start(service)?;
+                Ok(format!("restarted {}", service))
+            }
+            _ => bail!("unknown action {}", action),
+        }
+    }
+}
+
+// Also reaps the process if it is an exited child of the console
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let pid = pid as libc::pid_t;
+    unsafe {
+        libc::waitpid(pid, std::ptr::null_mut(), libc::WNOHANG);
+        libc::kill(pid, 0) == 0
+    }
+}
+
+// Services run in their own process group, so children of the shell are signalled too
+#[cfg(unix)]
+fn signal(pid: u32, signal: libc::c_int) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn signal(_pid: u32, _signal: i32) {}
+
+fn resident_bytes(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+// Dependency graph
+
+pub fn dependents<'a>(services: &'a [ServiceStatus], name: &str) -> Vec<&'a str> {
+    services
+        .iter()
+        .filter(|service| service.requires.iter().any(|required| required == name))
+        .map(|service| service.name.as_str())
+        .collect()
+}
+
+fn is_failed(service: &ServiceStatus) -> bool {
+    service.status == "FAILED" || service.active_state == "failed"
+}
+
+// For every service that should be up, the failed services it transitively requires; stopped
+// dependencies are walked through but not blamed, since stopping a service is deliberate
+pub fn failed_requirements(services: &[ServiceStatus]) -> HashMap<String, Vec<String>> {
+    let by_name: HashMap<&str, &ServiceStatus> = services.iter().map(|s| (s.name.as_str(), s)).collect();
+    let mut affected = HashMap::new();
+    for service in services.iter().filter(|service| service.status != "STOPPED") {
+        let mut failed = Vec::new();
+        let mut seen = vec![service.name.as_str()];
+        let mut pending: Vec<&str> = service.requires.iter().map(|name| name.as_str()).collect();
+        while let Some(name) = pending.pop() {
+            if seen.contains(&name) {
+                continue;
+            }
+            seen.push(name);
+            if let Some(required) = by_name.get(name) {
+                if is_failed(required) {
+                    failed.push(name.to_string());
+                }
+                pending.extend(required.requires.iter().map(|name| name.as_str()));
+            }
+        }
+        if !failed.is_empty() {
+            failed.sort();
+            affected.insert(service.name.clone(), failed);
+        }
+    }
+    affected
+}
+
+// Manager
+
+pub struct ServiceManager {
+    backend: Option<Arc<dyn ServiceBackend>>,
+    interval: Duration,
+    refreshed: Option<Instant>,
+    listing: bool,
+    failures: u32,
+    listing_tx: Sender<anyhow::Result<Vec<ServiceStatus>>>,
+    listing_rx: Receiver<anyhow::Result<Vec<ServiceStatus>>>,
+    results_tx: Sender<(String, anyhow::Result<String>)>,
+    results_rx: Receiver<(String, anyhow::Result<String>)>,
+}
+
+impl ServiceManager {
+    pub fn new(config: &ServicesConfig, interval: Duration) -> Self {
+        let (results_tx, results_rx) = mpsc::channel();
+        let (listing_tx, listing_rx) = mpsc::channel();
+        Self {
+            backend: open_backend(config),
+            interval,
+            refreshed: None,
+            listing: false,
+            failures: 0,
+            listing_tx,
+            listing_rx,
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.as_ref().map_or("simulated", |backend| backend.name())
+    }
+
+    // Lists services on a background thread, since systemctl can stall; without a backend the
+    // built-in service list is left as is
+    pub fn refresh(&mut self) {
+        let backend = match &self.backend {
+            Some(backend) if !self.listing => backend.clone(),
+            _ => return,
+        };
+        if !self.due(Instant::now()) {
+            return;
+        }
+        self.listing = true;
+        let tx = self.listing_tx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(backend.list());
+        });
+    }
+
+    // The interval doubles with each failed listing in a row
+    fn due(&self, now: Instant) -> bool {
+        self.refreshed.is_none_or(|at| now.duration_since(at) >= backoff(self.interval, self.failures))
+    }
+
+    // Runs the action off the UI thread; the outcome is logged by collect_results
+    pub fn control(&mut self, system: &mut SystemState, service: &str, action: &str) {
+        let backend = match &self.backend {
+            Some(backend) => backend.clone(),
+            None => {
+                system.log("WARN", "Services", &format!("Cannot {} {}: no service backend available", action, service));
+                return;
+            }
+        };
+        system.log("INFO", "Services", &format!("{} {} via {}", action, service, backend.name()));
+        let tx = self.results_tx.clone();
+        let (service, action) = (service.to_string(), action.to_string());
+        thread::spawn(move || {
+            let result = backend.control(&service, &action);
+            let _ = tx.send((format!("{} {}", action, service), result));
+        });
+    }
+
+    pub fn collect_results(&mut self, system: &mut SystemState) {
+        while let Ok(result) = self.listing_rx.try_recv() {
+            self.listing = false;
+            self.refreshed = Some(Instant::now());
+            let name = self.backend_name();
+            match result {
+                Ok(services) => {
+                    if self.failures > 0 {
+                        system.log("INFO", "Services", &format!("{} backend recovered", name));
+                    }
+                    self.failures = 0;
+                    system.services = services;
+                    system.update_service_subsystems();
+                }
+                Err(err) => {
+                    // Keep the backend and retry less often, so a transient failure is not permanent
+                    self.failures += 1;
+                    let retry = backoff(self.interval, self.failures);
+                    let message = format!("{} backend failed: {:#}; retrying in {}s", name, err, retry.as_secs());
+                    system.log("ERROR", "Services", &message);
+                }
+            }
+        }
+
+        while let Ok((label, result)) = self.results_rx.try_recv() {
+            match result {
+                Ok(status) => system.log("INFO", "Services", &status),
+                Err(err) => system.log("ERROR", "Services", &format!("{} failed: {:#}", label, err)),
+            }
+            self.refreshed = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("redox-console-services-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn status<'a>(services: &'a [ServiceStatus], name: &str) -> &'a ServiceStatus {
+        services.iter().find(|service| service.name == name).unwrap()
+    }
+
+    // Start returns once the process is spawned and stop once it is gone, so no polling is needed
+    fn listed(backend: &SupervisorBackend, name: &str) -> ServiceStatus {
+        status(&backend.list().unwrap(), name).clone()
+    }
+
+    #[test]
+    fn supervisor_lifecycle() {
+        let dir = temp_dir("lifecycle");
+        fs::write(dir.join("sleeper.toml"), "description = \"Sleeps\"\ncommand = \"sleep 30\"\n").unwrap();
+        fs::write(dir.join("web.toml"), "command = \"sleep 30\"\nrequires = [\"sleeper\"]\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not a definition").unwrap();
+        let backend = SupervisorBackend { dir: dir.clone() };
+
+        let services = backend.list().unwrap();
+        assert_eq!(services.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["sleeper", "web"]);
+        let sleeper = status(&services, "sleeper");
+        assert_eq!((sleeper.status.as_str(), sleeper.active_state.as_str(), sleeper.sub_state.as_str()), ("STOPPED", "inactive", "dead"));
+        assert_eq!(sleeper.description, "Sleeps");
+        assert_eq!(sleeper.main_pid, None);
+        assert_eq!(status(&services, "web").requires, ["sleeper"]);
+
+        assert!(backend.control("sleeper", "start").unwrap().starts_with("started sleeper (pid "));
+        let running = listed(&backend, "sleeper");
+        assert_eq!((running.status.as_str(), running.sub_state.as_str()), ("RUNNING", "running"));
+        let first_pid = running.main_pid.unwrap();
+        assert_eq!(backend.control("sleeper", "start").unwrap(), "sleeper already running");
+
+        assert_eq!(backend.control("sleeper", "restart").unwrap(), "restarted sleeper");
+        let restarted = listed(&backend, "sleeper");
+        assert_eq!(restarted.status, "RUNNING");
+        assert_eq!(restarted.restarts, 1);
+        assert_ne!(restarted.main_pid, Some(first_pid));
+        assert!(!process_alive(first_pid));
+
+        assert_eq!(backend.control("sleeper", "stop").unwrap(), "stopped sleeper");
+        let stopped = listed(&backend, "sleeper");
+        assert_eq!(stopped.status, "STOPPED");
+        assert!(!dir.join("run/sleeper.pid").exists());
+        assert_eq!(backend.control("sleeper", "stop").unwrap(), "sleeper not running");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn supervisor_reports_exited_service_as_failed() {
+        let dir = temp_dir("failed");
+        fs::write(dir.join("crasher.toml"), "command = \"exit 3\"\n").unwrap();
+        let backend = SupervisorBackend { dir: dir.clone() };
+
+        backend.control("crasher", "start").unwrap();
+        // Block until the shell exits instead of polling for it
+        let pid = backend.pid("crasher").unwrap();
+        unsafe {
+            libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), 0);
+        }
+        let crashed = listed(&backend, "crasher");
+        assert_eq!((crashed.status.as_str(), crashed.active_state.as_str(), crashed.sub_state.as_str()), ("FAILED", "failed", "exited"));
+        assert_eq!(crashed.main_pid, None);
+        assert_eq!(crashed.uptime, "-");
+
+        assert!(backend.control("missing", "start").is_err());
+        assert!(backend.control("crasher", "reload").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn supervisor_rejects_invalid_definition() {
+        let dir = temp_dir("invalid");
+        fs::write(dir.join("broken.toml"), "description = \"no command\"\n").unwrap();
+        let backend = SupervisorBackend { dir: dir.clone() };
+        let err = backend.list().unwrap_err();
+        assert!(format!("{:#}", err).contains("broken.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn systemctl_show_blocks() {
+        let text = "\
+Id=nginx.service
+Description=A high performance web server
+ActiveState=active
+SubState=running
+MainPID=812
+MemoryCurrent=10485760
+NRestarts=2
+ActiveEnterTimestampMonotonic=400000000
+Requires=system.slice sysinit.target php-fpm.service
+BindsTo=
+Requisite=
+
+Id=php-fpm.service
+Description=The PHP FastCGI Process Manager
+ActiveState=failed
+SubState=failed
+MainPID=0
+MemoryCurrent=[not set]
+NRestarts=0
+ActiveEnterTimestampMonotonic=0
+Requires=
+BindsTo=redis.service
+Requisite=network-online.target
+
+Id=cron.service
+ActiveState=activating
+SubState=start-pre
+";
+        let services = parse_systemctl_show(text, 1000.0);
+        assert_eq!(services.len(), 3);
+
+        let nginx = &services[0];
+        assert_eq!(nginx.name, "nginx.service");
+        assert_eq!(nginx.description, "A high performance web server");
+        assert_eq!((nginx.status.as_str(), nginx.sub_state.as_str()), ("RUNNING", "running"));
+        assert_eq!(nginx.main_pid, Some(812));
+        assert_eq!(nginx.memory_bytes, Some(10485760));
+        assert_eq!(nginx.restarts, 2);
+        // Active since 400s after boot on a machine up for 1000s
+        assert_eq!(nginx.uptime, "10m");
+        assert_eq!(nginx.requires, ["php-fpm.service"]);
+
+        let php = &services[1];
+        assert_eq!((php.status.as_str(), php.active_state.as_str()), ("FAILED", "failed"));
+        assert_eq!(php.main_pid, None);
+        assert_eq!(php.memory_bytes, None);
+        assert_eq!(php.uptime, "-");
+        assert_eq!(php.requires, ["redis.service"]);
+
+        let cron = &services[2];
+        assert_eq!(cron.status, "STARTING");
+        assert_eq!(cron.description, "");
+        assert!(cron.requires.is_empty());
+    }
+
+    #[test]
+    fn systemctl_show_skips_blocks_without_id() {
+        assert!(parse_systemctl_show("ActiveState=active\n", 0.0).is_empty());
+        assert!(parse_systemctl_show("", 0.0).is_empty());
+    }
+
+    struct FlakyBackend {
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    impl ServiceBackend for FlakyBackend {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        // Fails the first call only
+        fn list(&self) -> anyhow::Result<Vec<ServiceStatus>> {
+            if self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                bail!("bus timeout");
+            }
+            Ok(Vec::new())
+        }
+
+        fn control(&self, _service: &str, _action: &str) -> anyhow::Result<String> {
+            Ok(String::new())
+        }
+    }
+
+    // Waits for the listing thread, then hands its result to collect_results
+    fn wait_listing(manager: &mut ServiceManager, system: &mut SystemState) {
+        let result = manager.listing_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        manager.listing_tx.send(result).unwrap();
+        manager.collect_results(system);
+    }
+
+    #[test]
+    fn failed_listing_keeps_backend_and_backs_off() {
+        let mut system = SystemState::new();
+        let mut manager = ServiceManager::new(&ServicesConfig::default(), Duration::from_secs(10));
+        manager.backend = Some(Arc::new(FlakyBackend { calls: Default::default() }));
+
+        manager.refresh();
+        wait_listing(&mut manager, &mut system);
+        assert_eq!(manager.backend_name(), "flaky");
+        assert_eq!(manager.failures, 1);
+        assert!(system.logs.iter().any(|log| log.message.contains("flaky backend failed: bus timeout")));
+
+        // Retried after the doubled interval, not the configured one
+        let failed_at = manager.refreshed.unwrap();
+        assert!(!manager.due(failed_at + Duration::from_secs(10)));
+        assert!(manager.due(failed_at + Duration::from_secs(20)));
+        manager.refresh();
+        assert!(!manager.listing);
+
+        // As after a service action, which forces the next listing
+        manager.refreshed = None;
+        manager.refresh();
+        assert!(manager.listing);
+        wait_listing(&mut manager, &mut system);
+        assert_eq!(manager.failures, 0);
+        assert!(system.services.is_empty());
+        assert!(system.logs.iter().any(|log| log.message == "flaky backend recovered"));
+    }
+
+    fn service(name: &str, status: &str, requires: &[&str]) -> ServiceStatus {
+        ServiceStatus {
+            name: name.to_string(),
+            status: status.to_string(),
+            uptime: "-".to_string(),
+            description: String::new(),
+            active_state: match status {
+                "RUNNING" => "active",
+                "FAILED" => "failed",
+                _ => "inactive",
+            }
+            .to_string(),
+            sub_state: "-".to_string(),
+            main_pid: None,
+            memory_bytes: None,
+            restarts: 0,
+            requires: requires.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn failed_requirements_only_blames_failed_services() {
+        let services = vec![
+            service("web", "RUNNING", &["app"]),
+            service("app", "RUNNING", &["db", "cache"]),
+            service("db", "FAILED", &["storage"]),
+            service("cache", "STOPPED", &["storage"]),
+            service("storage", "FAILED", &[]),
+            service("batch", "STOPPED", &["db"]),
+            service("worker", "STARTING", &["cache"]),
+            service("report", "RUNNING", &["missing"]),
+        ];
+        let affected = failed_requirements(&services);
+
+        // Failures are found through running and stopped services alike
+        assert_eq!(affected["web"], ["db", "storage"]);
+        assert_eq!(affected["app"], ["db", "storage"]);
+        assert_eq!(affected["db"], ["storage"]);
+        assert_eq!(affected["worker"], ["storage"]);
+        // A stopped service is not reported, nor is a stopped or unknown dependency
+        assert!(!affected.contains_key("batch"));
+        assert!(!affected.contains_key("cache"));
+        assert!(!affected.contains_key("report"));
+        assert_eq!(affected.len(), 4);
+    }
+
+    #[test]
+    fn failed_requirements_terminates_on_cycles() {
+        let services = vec![
+            service("a", "RUNNING", &["b"]),
+            service("b", "RUNNING", &["c"]),
+            service("c", "FAILED", &["a"]),
+            service("self", "RUNNING", &["self"]),
+        ];
+        let affected = failed_requirements(&services);
+
+        assert_eq!(affected["a"], ["c"]);
+        assert_eq!(affected["b"], ["c"]);
+        // c reaches itself only through the cycle and is not its own failed requirement
+        assert!(!affected.contains_key("c"));
+        assert!(!affected.contains_key("self"));
+    }
+
+    #[test]
+    fn dependents_are_direct_only() {
+        let services = vec![service("web", "RUNNING", &["app"]), service("app", "RUNNING", &["db"]), service("db", "RUNNING", &[])];
+        assert_eq!(dependents(&services, "db"), ["app"]);
+        assert!(dependents(&services, "web").is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+// Shell commands with a deadline, for dashboards, alert hooks and probes
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub fn command(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script).stdin(Stdio::null());
+    cmd
+}
+
+// Runs the command in its own process group; past the timeout the whole group is killed, so
+// nothing the shell started is left running
+pub fn run(mut cmd: Command, timeout: Duration) -> Result<Output, String> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(timed_out(timeout));
+            }
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+pub fn timed_out(timeout: Duration) -> String {
+    format!("timed out after {:.1}s", timeout.as_secs_f64())
+}
+
+// Kills every process in the group led by `pid`
+#[cfg(unix)]
+pub fn kill_group(pid: u32) {
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    kill_group(child.id());
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_output_and_status() {
+        let output = run(command("echo 42; echo oops >&2; exit 3"), Duration::from_secs(5)).unwrap();
+        assert_eq!(output.stdout, "42\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[test]
+    fn hung_command_is_killed() {
+        let started = Instant::now();
+        // The background sleep keeps the pipes open unless the whole group is killed
+        let err = run(command("sleep 30 & sleep 30"), Duration::from_millis(200)).err().unwrap();
+        assert_eq!(err, "timed out after 0.2s");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,7 +1,44 @@
+use crate::metrics::MetricStore;
+use crate::pipeline::{Collector, NetworkSample, Sample};
+use crate::procfs::{self, CpuTimes, DiskStats, FsUsage, LinkInfo, MountEntry, NetDevCounters};
 use chrono::{DateTime, Local};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+const MAX_LOG_ENTRIES: usize = 500;
+const STATVFS_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Kernel and virtual filesystems hidden from the mount list unless pseudo filesystems are shown
+const PSEUDO_FS_TYPES: [&str; 21] = [
+    "proc", "sysfs", "devtmpfs", "devpts", "cgroup", "cgroup2", "securityfs", "debugfs",
+    "tracefs", "pstore", "bpf", "mqueue", "hugetlbfs", "configfs", "fusectl", "autofs",
+    "binfmt_misc", "nsfs", "rpc_pipefs", "efivarfs", "selinuxfs",
+];
+
+// statvfs on these can block indefinitely when the server is gone
+const NETWORK_FS_TYPES: [&str; 7] = ["nfs", "nfs4", "cifs", "smb3", "9p", "ceph", "glusterfs"];
+
+const HISTORY_METRICS: [&str; 16] = [
+    "cpu.usage",
+    "cpu.user",
+    "cpu.system",
+    "cpu.iowait",
+    "cpu.irq",
+    "cpu.steal",
+    "memory.used_gb",
+    "memory.used_percent",
+    "swap.used_gb",
+    "ipc.messages",
+    "fs.reads",
+    "fs.writes",
+    "net.rx_rate",
+    "net.tx_rate",
+    "net.error_rate",
+    "net.drop_rate",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Process {
@@ -17,11 +54,68 @@ pub struct Process {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileSystem {
     pub mount: String,
+    pub source: String,
+    pub device: String,
     pub fs_type: String,
+    pub options: String,
     pub status: String,
-    pub used: String,
-    pub free: String,
+    pub pseudo: bool,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
     pub usage_percent: u16,
+    pub inodes_total: u64,
+    pub inodes_used: u64,
+    pub inode_percent: u16,
+}
+
+impl FileSystem {
+    fn simulated(mount: &str, fs_type: &str, total_gb: f64, used_gb: f64, inodes_total: u64, inodes_used: u64) -> Self {
+        let gb = 1024.0 * 1024.0 * 1024.0;
+        let mut fs = Self {
+            mount: mount.to_string(),
+            source: "-".to_string(),
+            device: "0:0".to_string(),
+            fs_type: fs_type.to_string(),
+            options: "rw".to_string(),
+            status: "ONLINE".to_string(),
+            pseudo: false,
+            total_bytes: 0,
+            used_bytes: 0,
+            free_bytes: 0,
+            usage_percent: 0,
+            inodes_total: 0,
+            inodes_used: 0,
+            inode_percent: 0,
+        };
+        fs.apply_usage(&FsUsage {
+            total_bytes: (total_gb * gb) as u64,
+            free_bytes: ((total_gb - used_gb) * gb) as u64,
+            available_bytes: ((total_gb - used_gb) * gb) as u64,
+            inodes_total,
+            inodes_free: inodes_total - inodes_used,
+        });
+        fs
+    }
+
+    // Like df, usage is relative to what unprivileged users can still allocate
+    fn apply_usage(&mut self, usage: &FsUsage) {
+        self.total_bytes = usage.total_bytes;
+        self.used_bytes = usage.total_bytes.saturating_sub(usage.free_bytes);
+        self.free_bytes = usage.available_bytes;
+        self.usage_percent = percent(self.used_bytes, self.used_bytes + self.free_bytes);
+        self.inodes_total = usage.inodes_total;
+        self.inodes_used = usage.inodes_total.saturating_sub(usage.inodes_free);
+        self.inode_percent = percent(self.inodes_used, self.inodes_total);
+    }
+}
+
+fn percent(part: u64, total: u64) -> u16 {
+    if total == 0 {
+        0
+    } else {
+        (part as f64 * 100.0 / total as f64).ceil().min(100.0) as u16
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,23 +126,217 @@ pub struct LogEntry {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub size_bytes: Option<u64>,
+    // Package names only; version constraints and alternatives are dropped
+    pub depends: Vec<String>,
+    pub description: String,
+    // Newer version in the local repository index
+    pub update: Option<String>,
+}
+
+impl Package {
+    fn simulated(name: &str, version: &str, size_mb: f64, depends: &[&str], update: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            size_bytes: Some((size_mb * 1024.0 * 1024.0) as u64),
+            depends: depends.iter().map(|name| name.to_string()).collect(),
+            description: String::new(),
+            update: update.map(|version| version.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceStatus {
     pub name: String,
     pub status: String,
     pub uptime: String,
     pub description: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub main_pid: Option<u32>,
+    pub memory_bytes: Option<u64>,
+    pub restarts: u32,
+    // Services this one needs to be running; dependents are derived from these
+    pub requires: Vec<String>,
+}
+
+impl ServiceStatus {
+    fn simulated(name: &str, running: bool, uptime: &str, description: &str, requires: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            status: if running { "RUNNING" } else { "STOPPED" }.to_string(),
+            uptime: uptime.to_string(),
+            description: description.to_string(),
+            active_state: if running { "active" } else { "inactive" }.to_string(),
+            sub_state: if running { "running" } else { "dead" }.to_string(),
+            main_pid: None,
+            memory_bytes: None,
+            restarts: 0,
+            requires: requires.iter().map(|name| name.to_string()).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkInterface {
     pub name: String,
     pub status: String,
-    pub ip: String,
+    pub operstate: String,
+    pub kind: String,
+    pub driver: String,
+    pub mac: String,
+    pub mtu: u32,
+    pub speed_mbps: Option<u32>,
+    pub duplex: String,
+    pub addresses: Vec<String>,
     pub rx_bytes: u64,
     pub tx_bytes: u64,
     pub rx_packets: u64,
     pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+    pub rx_rate: f64,
+    pub tx_rate: f64,
+    pub rx_error_rate: f64,
+    pub tx_error_rate: f64,
+    pub rx_drop_rate: f64,
+    pub tx_drop_rate: f64,
+}
+
+impl NetworkInterface {
+    fn new(name: &str, kind: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: "UNKNOWN".to_string(),
+            operstate: "unknown".to_string(),
+            kind: kind.to_string(),
+            driver: "-".to_string(),
+            mac: "-".to_string(),
+            mtu: 0,
+            speed_mbps: None,
+            duplex: "-".to_string(),
+            addresses: Vec::new(),
+            rx_bytes: 0,
+            tx_bytes: 0,
+            rx_packets: 0,
+            tx_packets: 0,
+            rx_errors: 0,
+            tx_errors: 0,
+            rx_dropped: 0,
+            tx_dropped: 0,
+            rx_rate: 0.0,
+            tx_rate: 0.0,
+            rx_error_rate: 0.0,
+            tx_error_rate: 0.0,
+            rx_drop_rate: 0.0,
+            tx_drop_rate: 0.0,
+        }
+    }
+
+    fn apply_link(&mut self, link: LinkInfo) {
+        self.status = if link.up { "UP" } else { "DOWN" }.to_string();
+        self.operstate = link.operstate;
+        self.kind = link.kind;
+        self.driver = link.driver.unwrap_or_else(|| "-".to_string());
+        self.mac = link.mac;
+        self.mtu = link.mtu;
+        self.speed_mbps = link.speed_mbps;
+        self.duplex = link.duplex.unwrap_or_else(|| "-".to_string());
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiskDevice {
+    pub name: String,
+    pub read_iops: f64,
+    pub write_iops: f64,
+    pub read_rate: f64,
+    pub write_rate: f64,
+    pub read_await_ms: f64,
+    pub write_await_ms: f64,
+    pub queue_depth: f64,
+    pub util_percent: f64,
+    pub in_flight: u64,
+}
+
+impl DiskDevice {
+    // Same derivations as iostat -x: await from time spent per completed I/O, aqu-sz from weighted time
+    fn from_delta(prev: &DiskStats, cur: &DiskStats, secs: f64) -> Self {
+        let delta = |cur: u64, prev: u64| cur.saturating_sub(prev) as f64;
+        let reads = delta(cur.reads, prev.reads);
+        let writes = delta(cur.writes, prev.writes);
+        let per_io = |ms: f64, ios: f64| if ios > 0.0 { ms / ios } else { 0.0 };
+        let elapsed_ms = secs * 1000.0;
+
+        Self {
+            name: cur.name.clone(),
+            read_iops: reads / secs,
+            write_iops: writes / secs,
+            read_rate: delta(cur.sectors_read, prev.sectors_read) * procfs::SECTOR_BYTES as f64 / secs,
+            write_rate: delta(cur.sectors_written, prev.sectors_written) * procfs::SECTOR_BYTES as f64 / secs,
+            read_await_ms: per_io(delta(cur.read_ms, prev.read_ms), reads),
+            write_await_ms: per_io(delta(cur.write_ms, prev.write_ms), writes),
+            queue_depth: delta(cur.weighted_ms, prev.weighted_ms) / elapsed_ms,
+            util_percent: (delta(cur.io_ms, prev.io_ms) * 100.0 / elapsed_ms).min(100.0),
+            in_flight: cur.in_flight,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    pub proto: String,
+    pub local: String,
+    pub local_port: Option<u16>,
+    pub remote: String,
+    pub remote_port: Option<u16>,
+    pub state: String,
+    pub pid: Option<u32>,
+    pub process: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CpuCore {
+    pub name: String,
+    pub user: f32,
+    pub system: f32,
+    pub iowait: f32,
+    pub irq: f32,
+    pub steal: f32,
+}
+
+impl CpuCore {
+    fn from_delta(name: &str, prev: &CpuTimes, cur: &CpuTimes) -> Self {
+        let total = cur.total().saturating_sub(prev.total());
+        let pct = |cur: u64, prev: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                cur.saturating_sub(prev) as f32 * 100.0 / total as f32
+            }
+        };
+
+        Self {
+            name: name.to_string(),
+            user: pct(cur.user + cur.nice, prev.user + prev.nice),
+            system: pct(cur.system, prev.system),
+            iowait: pct(cur.iowait, prev.iowait),
+            irq: pct(cur.irq + cur.softirq, prev.irq + prev.softirq),
+            steal: pct(cur.steal, prev.steal),
+        }
+    }
+
+    pub fn busy(&self) -> f32 {
+        (self.user + self.system + self.iowait + self.irq + self.steal).min(100.0)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,6 +357,14 @@ pub struct SystemState {
     pub fs_writes: u32,
     pub network_rx: u64,
     pub network_tx: u64,
+    pub network_rx_rate: f64,
+    pub network_tx_rate: f64,
+    pub network_error_rate: f64,
+    pub network_drop_rate: f64,
+    last_network_sample: Option<Instant>,
+    pub cpu_breakdown: CpuCore,
+    pub cpu_cores: Vec<CpuCore>,
+    prev_cpu_times: Vec<(String, CpuTimes)>,
     
     // System status
     pub kernel_status: HashMap<String, String>,
@@ -79,12 +375,99 @@ pub struct SystemState {
     pub filesystems: Vec<FileSystem>,
     pub logs: Vec<LogEntry>,
     pub services: Vec<ServiceStatus>,
+    pub packages: Vec<Package>,
     pub network_interfaces: Vec<NetworkInterface>,
+    pub connections: Vec<Connection>,
+    pub disks: Vec<DiskDevice>,
+    prev_disk_stats: Vec<DiskStats>,
+    last_disk_sample: Option<Instant>,
     
     // Performance history
-    pub cpu_history: Vec<f32>,
-    pub memory_history: Vec<f32>,
-    pub network_history: Vec<(u64, u64)>,
+    pub metrics: MetricStore,
+}
+
+fn statvfs_with_timeout(path: &str) -> Result<FsUsage, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let path = path.to_string();
+    // A hung call keeps its thread, but the collector moves on
+    std::thread::spawn(move || {
+        let _ = tx.send(procfs::statvfs(&path).map_err(|e| e.to_string()));
+    });
+    rx.recv_timeout(STATVFS_TIMEOUT).unwrap_or_else(|_| Err("timeout".to_string()))
+}
+
+// The read_* functions do the blocking /proc and /sys work and run on the collection pipeline
+pub fn read_disks() -> io::Result<Vec<DiskStats>> {
+    Ok(procfs::read_diskstats()?
+        .into_iter()
+        .filter(|disk| procfs::is_whole_disk(&disk.name))
+        // Unused loop and ram devices are just noise
+        .filter(|disk| !(disk.name.starts_with("loop") || disk.name.starts_with("ram")) || disk.reads + disk.writes > 0)
+        .collect())
+}
+
+pub fn read_filesystems() -> io::Result<Vec<FileSystem>> {
+    let mounts = procfs::read_mounts()?;
+    if mounts.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no mounts listed"));
+    }
+    Ok(mounts
+        .into_iter()
+        .map(|mount| {
+            let usage = if NETWORK_FS_TYPES.contains(&mount.fs_type.as_str()) || mount.fs_type.starts_with("fuse.") {
+                statvfs_with_timeout(&mount.mount_point)
+            } else {
+                procfs::statvfs(&mount.mount_point).map_err(|e| e.to_string())
+            };
+            let MountEntry { device, source, mount_point, fs_type, options } = mount;
+            let mut fs = FileSystem {
+                pseudo: PSEUDO_FS_TYPES.contains(&fs_type.as_str()),
+                mount: mount_point,
+                source,
+                device,
+                fs_type,
+                options,
+                status: "ONLINE".to_string(),
+                total_bytes: 0,
+                used_bytes: 0,
+                free_bytes: 0,
+                usage_percent: 0,
+                inodes_total: 0,
+                inodes_used: 0,
+                inode_percent: 0,
+            };
+            match usage {
+                Ok(usage) => {
+                    fs.apply_usage(&usage);
+                    // Zero-sized mounts are kernel interfaces even when their type is not listed
+                    fs.pseudo |= usage.total_bytes == 0;
+                }
+                Err(err) => fs.status = if err == "timeout" { "STALE" } else { "ERROR" }.to_string(),
+            }
+            fs
+        })
+        .collect())
+}
+
+pub fn read_connections() -> io::Result<Vec<Connection>> {
+    let sockets = procfs::read_sockets()?;
+    let owners = procfs::socket_owners();
+    Ok(sockets
+        .into_iter()
+        .map(|socket| {
+            let owner = owners.get(&socket.inode);
+            Connection {
+                proto: socket.proto,
+                local: socket.local,
+                local_port: socket.local_port,
+                remote: socket.remote,
+                remote_port: socket.remote_port,
+                state: socket.state,
+                pid: owner.map(|(pid, _)| *pid),
+                process: owner.map(|(_, name)| name.clone()).unwrap_or_else(|| "-".to_string()),
+            }
+        })
+        .collect())
 }
 
 impl SystemState {
@@ -158,38 +541,9 @@ impl SystemState {
         ];
 
         let filesystems = vec![
-            FileSystem {
-                mount: "/".to_string(),
-                fs_type: "RedoxFS".to_string(),
-                status: "ONLINE".to_string(),
-                used: "1.3 GB".to_string(),
-                free: "3.7 GB".to_string(),
-                usage_percent: 26,
-            },
-            FileSystem {
-                mount: "/usr".to_string(),
-                fs_type: "RedoxFS".to_string(),
-                status: "ONLINE".to_string(),
-                used: "2.1 GB".to_string(),
-                free: "5.0 GB".to_string(),
-                usage_percent: 30,
-            },
-            FileSystem {
-                mount: "/tmp".to_string(),
-                fs_type: "RamFS".to_string(),
-                status: "ONLINE".to_string(),
-                used: "45 MB".to_string(),
-                free: "955 MB".to_string(),
-                usage_percent: 4,
-            },
-            FileSystem {
-                mount: "/mnt/net".to_string(),
-                fs_type: "NetFS".to_string(),
-                status: "OFFLINE".to_string(),
-                used: "-".to_string(),
-                free: "-".to_string(),
-                usage_percent: 0,
-            },
+            FileSystem::simulated("/", "RedoxFS", 5.0, 1.3, 327_680, 41_212),
+            FileSystem::simulated("/usr", "RedoxFS", 7.1, 2.1, 458_752, 96_530),
+            FileSystem::simulated("/tmp", "RamFS", 1.0, 0.045, 65_536, 212),
         ];
 
         let logs = vec![
@@ -219,51 +573,65 @@ impl SystemState {
             },
         ];
 
+        let packages = vec![
+            Package::simulated("redox-kernel", "0.8.5", 12.3, &["rust-std"], Some("0.8.6")),
+            Package::simulated("ion-shell", "1.0.5", 2.1, &["rust-std", "redox-kernel"], None),
+            Package::simulated("netstack", "0.3.2", 8.7, &["redox-kernel", "rust-std"], Some("0.3.3")),
+            Package::simulated("orbital", "0.5.1", 15.2, &["redox-kernel", "netstack"], None),
+            Package::simulated("pkg-manager", "0.4.8", 1.8, &["netstack"], Some("0.4.9")),
+            Package::simulated("rust-std", "1.75.0", 45.1, &[], Some("1.76.0")),
+        ];
+
         let services = vec![
-            ServiceStatus {
-                name: "redoxd".to_string(),
-                status: "RUNNING".to_string(),
-                uptime: "2h 15m".to_string(),
-                description: "Core system daemon".to_string(),
-            },
-            ServiceStatus {
-                name: "audiod".to_string(),
-                status: "RUNNING".to_string(),
-                uptime: "2h 14m".to_string(),
-                description: "Audio subsystem daemon".to_string(),
-            },
-            ServiceStatus {
-                name: "netstack".to_string(),
-                status: "STOPPED".to_string(),
-                uptime: "-".to_string(),
-                description: "Network stack service".to_string(),
-            },
-            ServiceStatus {
-                name: "orbital".to_string(),
-                status: "STOPPED".to_string(),
-                uptime: "-".to_string(),
-                description: "GUI display server".to_string(),
-            },
+            ServiceStatus::simulated("redoxd", true, "2h 15m", "Core system daemon", &[]),
+            ServiceStatus::simulated("audiod", true, "2h 14m", "Audio subsystem daemon", &["redoxd"]),
+            ServiceStatus::simulated("netstack", false, "-", "Network stack service", &["redoxd"]),
+            ServiceStatus::simulated("orbital", false, "-", "GUI display server", &["redoxd", "audiod"]),
         ];
 
         let network_interfaces = vec![
             NetworkInterface {
-                name: "eth0".to_string(),
                 status: "DOWN".to_string(),
-                ip: "0.0.0.0".to_string(),
-                rx_bytes: 0,
-                tx_bytes: 0,
-                rx_packets: 0,
-                tx_packets: 0,
+                operstate: "down".to_string(),
+                driver: "e1000d".to_string(),
+                mac: "52:54:00:12:34:56".to_string(),
+                mtu: 1500,
+                ..NetworkInterface::new("eth0", "ethernet")
             },
             NetworkInterface {
-                name: "lo".to_string(),
                 status: "UP".to_string(),
-                ip: "127.0.0.1".to_string(),
+                operstate: "unknown".to_string(),
+                mac: "00:00:00:00:00:00".to_string(),
+                mtu: 65536,
+                addresses: vec!["127.0.0.1/8".to_string(), "::1/128".to_string()],
                 rx_bytes: 1024,
                 tx_bytes: 1024,
                 rx_packets: 12,
                 tx_packets: 12,
+                ..NetworkInterface::new("lo", "loopback")
+            },
+        ];
+
+        let connections = vec![
+            Connection {
+                proto: "tcp".to_string(),
+                local: "0.0.0.0:80".to_string(),
+                local_port: Some(80),
+                remote: "0.0.0.0:*".to_string(),
+                remote_port: None,
+                state: "LISTEN".to_string(),
+                pid: Some(44),
+                process: "httpd".to_string(),
+            },
+            Connection {
+                proto: "udp".to_string(),
+                local: "0.0.0.0:68".to_string(),
+                local_port: Some(68),
+                remote: "0.0.0.0:*".to_string(),
+                remote_port: None,
+                state: "UNCONN".to_string(),
+                pid: Some(32),
+                process: "dhcpd".to_string(),
             },
         ];
 
@@ -284,26 +652,34 @@ impl SystemState {
             fs_writes: 203,
             network_rx: 1024,
             network_tx: 2048,
+            network_rx_rate: 0.0,
+            network_tx_rate: 0.0,
+            network_error_rate: 0.0,
+            network_drop_rate: 0.0,
+            last_network_sample: None,
+            cpu_breakdown: CpuCore::default(),
+            cpu_cores: Vec::new(),
+            prev_cpu_times: procfs::read_cpu_times().unwrap_or_default(),
             kernel_status,
             subsystem_status,
             processes,
             filesystems,
             logs,
             services,
+            packages,
             network_interfaces,
-            cpu_history: vec![20.0, 22.0, 24.5],
-            memory_history: vec![1.0, 1.1, 1.2],
-            network_history: vec![(800, 1200), (900, 1800), (1024, 2048)],
+            connections,
+            disks: Vec::new(),
+            prev_disk_stats: Vec::new(),
+            last_disk_sample: None,
+            metrics: MetricStore::new(),
         }
     }
 
+    // /proc and /sys are sampled by the collection pipeline; this only moves the simulated values on
     pub fn update(&mut self) {
         let mut rng = rand::thread_rng();
         
-        // Update CPU usage
-        self.cpu_usage += rng.gen_range(-3.0..3.0);
-        self.cpu_usage = self.cpu_usage.clamp(1.0, 95.0);
-        
         // Update memory
         self.memory_used += rng.gen_range(-0.1..0.2);
         self.memory_used = self.memory_used.clamp(0.8, 3.8);
@@ -311,12 +687,6 @@ impl SystemState {
         
         // Update I/O
         self.ipc_messages = (self.ipc_messages as i32 + rng.gen_range(-50..100)).max(500) as u32;
-        self.fs_reads = (self.fs_reads as i32 + rng.gen_range(-30..50)).max(200) as u32;
-        self.fs_writes = (self.fs_writes as i32 + rng.gen_range(-20..30)).max(50) as u32;
-        
-        // Update network
-        self.network_rx += rng.gen_range(0..100);
-        self.network_tx += rng.gen_range(0..200);
         
         // Update process CPU usage
         for process in &mut self.processes {
@@ -328,70 +698,240 @@ impl SystemState {
         self.uptime = Local::now().signed_duration_since(self.boot_time).to_std().unwrap_or_default();
         
         // Update history
-        self.cpu_history.push(self.cpu_usage);
-        if self.cpu_history.len() > 60 {
-            self.cpu_history.remove(0);
-        }
-        
-        self.memory_history.push(self.memory_used);
-        if self.memory_history.len() > 60 {
-            self.memory_history.remove(0);
+        self.record_metrics();
+    }
+
+    pub fn apply_sample(&mut self, sample: Sample) {
+        match sample {
+            Sample::Cpu(times) => self.apply_cpu_times(times),
+            Sample::Disks { stats, at } => self.apply_disk_stats(stats, at),
+            Sample::Network(network) => self.apply_network(network),
+            Sample::Filesystems(filesystems) => self.filesystems = filesystems,
+            Sample::Connections(connections) => self.connections = connections,
         }
-        
-        self.network_history.push((self.network_rx, self.network_tx));
-        if self.network_history.len() > 60 {
-            self.network_history.remove(0);
+    }
+
+    // Keeps the simulated values moving when a collector cannot read its source, e.g. off Linux
+    pub fn simulate(&mut self, collector: Collector) {
+        let mut rng = rand::thread_rng();
+        match collector {
+            Collector::Cpu => {
+                self.cpu_usage += rng.gen_range(-3.0..3.0);
+                self.cpu_usage = self.cpu_usage.clamp(1.0, 95.0);
+            }
+            Collector::Disks => {
+                self.fs_reads = (self.fs_reads as i32 + rng.gen_range(-30..50)).max(200) as u32;
+                self.fs_writes = (self.fs_writes as i32 + rng.gen_range(-20..30)).max(50) as u32;
+            }
+            Collector::Network => {
+                let counters = self.simulate_net_dev();
+                self.apply_network(NetworkSample { at: Instant::now(), counters, links: Vec::new(), addresses: None });
+            }
+            Collector::Filesystems | Collector::Connections => {}
         }
     }
 
-    pub fn refresh(&mut self) {
-        // Force refresh - could reload from actual system
-        self.update();
+    fn record_metrics(&mut self) {
+        let now = Local::now().timestamp();
+        for name in HISTORY_METRICS {
+            if let Some(value) = self.metric_value(name) {
+                self.metrics.record(name, now, value);
+            }
+        }
+        for disk in &self.disks {
+            self.metrics.record(&format!("disk.{}.read_rate", disk.name), now, disk.read_rate);
+            self.metrics.record(&format!("disk.{}.write_rate", disk.name), now, disk.write_rate);
+            self.metrics.record(&format!("disk.{}.util", disk.name), now, disk.util_percent);
+            self.metrics.record(&format!("disk.{}.queue", disk.name), now, disk.queue_depth);
+        }
+        for interface in &self.network_interfaces {
+            self.metrics.record(&format!("net.{}.rx_rate", interface.name), now, interface.rx_rate);
+            self.metrics.record(&format!("net.{}.tx_rate", interface.name), now, interface.tx_rate);
+        }
     }
 
-    pub fn toggle_network(&mut self) {
-        let new_status = if self.kernel_status.get("Network Stack") == Some(&"OFFLINE".to_string()) {
-            "ONLINE"
-        } else {
-            "OFFLINE"
+    fn apply_network(&mut self, sample: NetworkSample) {
+        let NetworkSample { at: now, counters, links, addresses } = sample;
+        let elapsed = self
+            .last_network_sample
+            .map(|last| now.duration_since(last).as_secs_f64())
+            .filter(|secs| *secs > 0.0);
+        self.last_network_sample = Some(now);
+
+        let previous = std::mem::take(&mut self.network_interfaces);
+        let rate = |cur: u64, prev: Option<u64>| match (prev, elapsed) {
+            (Some(prev), Some(secs)) => cur.saturating_sub(prev) as f64 / secs,
+            _ => 0.0,
         };
-        
-        self.kernel_status.insert("Network Stack".to_string(), new_status.to_string());
-        self.subsystem_status.insert("NetStack Daemon".to_string(), new_status.to_string());
-        
-        if new_status == "ONLINE" {
-            for interface in &mut self.network_interfaces {
-                if interface.name == "eth0" {
-                    interface.status = "UP".to_string();
-                    interface.ip = "192.168.1.100".to_string();
+
+        self.network_interfaces = counters
+            .into_iter()
+            .map(|dev| {
+                let prev = previous.iter().find(|i| i.name == dev.name);
+                let mut interface = prev.cloned().unwrap_or_else(|| NetworkInterface::new(&dev.name, "virtual"));
+                if let Some((_, link)) = links.iter().find(|(name, _)| *name == dev.name) {
+                    interface.apply_link(link.clone());
                 }
-            }
-        } else {
-            for interface in &mut self.network_interfaces {
-                if interface.name == "eth0" {
-                    interface.status = "DOWN".to_string();
-                    interface.ip = "0.0.0.0".to_string();
+                if let Some(addresses) = &addresses {
+                    interface.addresses = addresses
+                        .iter()
+                        .filter(|(name, _)| *name == dev.name)
+                        .map(|(_, address)| address.clone())
+                        .collect();
                 }
-            }
+                interface.rx_rate = rate(dev.rx_bytes, prev.map(|p| p.rx_bytes));
+                interface.tx_rate = rate(dev.tx_bytes, prev.map(|p| p.tx_bytes));
+                interface.rx_error_rate = rate(dev.rx_errors, prev.map(|p| p.rx_errors));
+                interface.tx_error_rate = rate(dev.tx_errors, prev.map(|p| p.tx_errors));
+                interface.rx_drop_rate = rate(dev.rx_dropped, prev.map(|p| p.rx_dropped));
+                interface.tx_drop_rate = rate(dev.tx_dropped, prev.map(|p| p.tx_dropped));
+                interface.rx_bytes = dev.rx_bytes;
+                interface.tx_bytes = dev.tx_bytes;
+                interface.rx_packets = dev.rx_packets;
+                interface.tx_packets = dev.tx_packets;
+                interface.rx_errors = dev.rx_errors;
+                interface.tx_errors = dev.tx_errors;
+                interface.rx_dropped = dev.rx_dropped;
+                interface.tx_dropped = dev.tx_dropped;
+                interface
+            })
+            .collect();
+
+        self.network_rx = self.network_interfaces.iter().map(|i| i.rx_bytes).sum();
+        self.network_tx = self.network_interfaces.iter().map(|i| i.tx_bytes).sum();
+        self.network_rx_rate = self.network_interfaces.iter().map(|i| i.rx_rate).sum();
+        self.network_tx_rate = self.network_interfaces.iter().map(|i| i.tx_rate).sum();
+        self.network_error_rate = self.network_interfaces.iter().map(|i| i.rx_error_rate + i.tx_error_rate).sum();
+        self.network_drop_rate = self.network_interfaces.iter().map(|i| i.rx_drop_rate + i.tx_drop_rate).sum();
+
+        let online = self.network_interfaces.iter().any(|i| i.kind != "loopback" && i.status == "UP");
+        let status = if online { "ONLINE" } else { "OFFLINE" };
+        self.kernel_status.insert("Network Stack".to_string(), status.to_string());
+        self.subsystem_status.insert("NetStack Daemon".to_string(), status.to_string());
+    }
+
+    // Random traffic on interfaces that are up, used when /proc/net/dev is unavailable
+    fn simulate_net_dev(&self) -> Vec<NetDevCounters> {
+        let mut rng = rand::thread_rng();
+        self.network_interfaces
+            .iter()
+            .map(|interface| {
+                let up = interface.status == "UP";
+                let rx = if up { rng.gen_range(0..100) } else { 0 };
+                let tx = if up { rng.gen_range(0..200) } else { 0 };
+                NetDevCounters {
+                    name: interface.name.clone(),
+                    rx_bytes: interface.rx_bytes + rx,
+                    rx_packets: interface.rx_packets + u64::from(rx > 0),
+                    rx_errors: interface.rx_errors,
+                    rx_dropped: interface.rx_dropped,
+                    tx_bytes: interface.tx_bytes + tx,
+                    tx_packets: interface.tx_packets + u64::from(tx > 0),
+                    tx_errors: interface.tx_errors,
+                    tx_dropped: interface.tx_dropped,
+                }
+            })
+            .collect()
+    }
+
+    fn apply_disk_stats(&mut self, stats: Vec<DiskStats>, now: Instant) {
+        let elapsed = self.last_disk_sample.map(|last| now.duration_since(last).as_secs_f64());
+        self.last_disk_sample = Some(now);
+
+        if let Some(secs) = elapsed.filter(|secs| *secs > 0.0) {
+            self.disks = stats
+                .iter()
+                .map(|cur| match self.prev_disk_stats.iter().find(|prev| prev.name == cur.name) {
+                    Some(prev) => DiskDevice::from_delta(prev, cur, secs),
+                    None => DiskDevice { name: cur.name.clone(), ..DiskDevice::default() },
+                })
+                .collect();
+            self.fs_reads = self.disks.iter().map(|d| d.read_iops).sum::<f64>().round() as u32;
+            self.fs_writes = self.disks.iter().map(|d| d.write_iops).sum::<f64>().round() as u32;
         }
+        self.prev_disk_stats = stats;
     }
 
-    pub fn toggle_orbital(&mut self) {
-        let new_status = if self.subsystem_status.get("GUI Orbital") == Some(&"OFFLINE".to_string()) {
-            "ONLINE"
-        } else {
-            "OFFLINE"
-        };
-        
-        self.subsystem_status.insert("GUI Orbital".to_string(), new_status.to_string());
-        self.subsystem_status.insert("Display Manager".to_string(), new_status.to_string());
-        
-        for service in &mut self.services {
-            if service.name == "orbital" {
-                service.status = if new_status == "ONLINE" { "RUNNING" } else { "STOPPED" }.to_string();
-                service.uptime = if new_status == "ONLINE" { "0m" } else { "-" }.to_string();
+    // Lines are matched to the previous sample by name, since CPUs can come and go between reads
+    fn apply_cpu_times(&mut self, times: Vec<(String, CpuTimes)>) {
+        if times.is_empty() {
+            return;
+        }
+        let mut cores = Vec::new();
+        for (name, cur) in &times {
+            let prev = match self.prev_cpu_times.iter().find(|(prev_name, _)| prev_name == name) {
+                Some((_, prev)) => prev,
+                None => continue,
+            };
+            let core = CpuCore::from_delta(name, prev, cur);
+            if name == "cpu" {
+                self.cpu_breakdown = core;
+                self.cpu_usage = self.cpu_breakdown.busy();
+            } else {
+                cores.push(core);
             }
         }
+        self.cpu_cores = cores;
+
+        self.prev_cpu_times = times;
+    }
+
+    // GUI Orbital and Display Manager follow the orbital service when the backend has one
+    pub fn update_service_subsystems(&mut self) {
+        let orbital = self
+            .services
+            .iter()
+            .find(|service| service.name == "orbital" || service.name == "orbital.service");
+        if let Some(orbital) = orbital {
+            let status = if orbital.status == "RUNNING" { "ONLINE" } else { "OFFLINE" };
+            self.subsystem_status.insert("GUI Orbital".to_string(), status.to_string());
+            self.subsystem_status.insert("Display Manager".to_string(), status.to_string());
+        }
+    }
+
+    pub fn log(&mut self, level: &str, source: &str, message: &str) {
+        self.logs.push(LogEntry {
+            timestamp: Local::now(),
+            level: level.to_string(),
+            source: source.to_string(),
+            message: message.to_string(),
+        });
+        if self.logs.len() > MAX_LOG_ENTRIES {
+            self.logs.remove(0);
+        }
+    }
+
+    pub fn metric_value(&self, name: &str) -> Option<f64> {
+        if let Some(core) = name.strip_suffix(".usage").and_then(|n| self.cpu_cores.iter().find(|c| c.name == n)) {
+            return Some(core.busy() as f64);
+        }
+
+        let value = match name {
+            "cpu.usage" => self.cpu_usage,
+            "cpu.user" => self.cpu_breakdown.user,
+            "cpu.system" => self.cpu_breakdown.system,
+            "cpu.iowait" => self.cpu_breakdown.iowait,
+            "cpu.irq" => self.cpu_breakdown.irq,
+            "cpu.steal" => self.cpu_breakdown.steal,
+            "memory.used_gb" => self.memory_used,
+            "memory.used_percent" => self.memory_used / self.memory_total * 100.0,
+            "swap.used_gb" => self.swap_used,
+            "swap.used_percent" => self.swap_used / self.swap_total * 100.0,
+            "load.1" => self.load_average[0],
+            "load.5" => self.load_average[1],
+            "load.15" => self.load_average[2],
+            "ipc.messages" => self.ipc_messages as f32,
+            "fs.reads" => self.fs_reads as f32,
+            "fs.writes" => self.fs_writes as f32,
+            "net.rx_bytes" => return Some(self.network_rx as f64),
+            "net.tx_bytes" => return Some(self.network_tx as f64),
+            "net.rx_rate" => return Some(self.network_rx_rate),
+            "net.tx_rate" => return Some(self.network_tx_rate),
+            "net.error_rate" => return Some(self.network_error_rate),
+            "net.drop_rate" => return Some(self.network_drop_rate),
+            _ => return self.metrics.latest(name),
+        };
+        Some(value as f64)
     }
 
     pub fn get_uptime_string(&self) -> String {
@@ -401,4 +941,89 @@ impl SystemState {
         let seconds = total_seconds % 60;
         format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn counters(rx_bytes: u64, rx_errors: u64, rx_dropped: u64, tx_bytes: u64, tx_errors: u64, tx_dropped: u64) -> NetDevCounters {
+        NetDevCounters {
+            name: "eth0".to_string(),
+            rx_bytes,
+            rx_packets: 0,
+            rx_errors,
+            rx_dropped,
+            tx_bytes,
+            tx_packets: 0,
+            tx_errors,
+            tx_dropped,
+        }
+    }
+
+    fn sample(at: Instant, counters: NetDevCounters) -> NetworkSample {
+        NetworkSample { at, counters: vec![counters], links: Vec::new(), addresses: None }
+    }
+
+    #[test]
+    fn network_rates_from_counter_deltas() {
+        let mut system = SystemState::new();
+        let start = Instant::now();
+        system.apply_network(sample(start, counters(1000, 10, 4, 500, 0, 0)));
+        // The first sample only sets the baseline
+        assert_eq!(system.network_rx_rate, 0.0);
+        assert_eq!(system.network_rx, 1000);
+
+        system.apply_network(sample(start + Duration::from_secs(2), counters(5096, 16, 8, 1524, 2, 0)));
+        assert_eq!(system.network_rx_rate, 2048.0);
+        assert_eq!(system.network_tx_rate, 512.0);
+        assert_eq!(system.network_error_rate, 4.0);
+        assert_eq!(system.network_drop_rate, 2.0);
+        assert_eq!(system.metric_value("net.error_rate"), Some(4.0));
+
+        let eth0 = &system.network_interfaces[0];
+        assert_eq!((eth0.rx_error_rate, eth0.tx_error_rate), (3.0, 1.0));
+        assert_eq!((eth0.rx_drop_rate, eth0.tx_drop_rate), (2.0, 0.0));
+        assert_eq!((eth0.rx_errors, eth0.rx_dropped), (16, 8));
+
+        // A counter reset (driver reload) reads as zero rather than wrapping
+        system.apply_network(sample(start + Duration::from_secs(3), counters(10, 0, 0, 10, 0, 0)));
+        assert_eq!(system.network_rx_rate, 0.0);
+        assert_eq!(system.network_error_rate, 0.0);
+    }
+
+    fn cpu(name: &str, user: u64, idle: u64) -> (String, CpuTimes) {
+        (name.to_string(), CpuTimes { user, idle, ..Default::default() })
+    }
+
+    #[test]
+    fn cpu_times_pair_by_name() {
+        let mut system = SystemState::new();
+        system.apply_cpu_times(vec![cpu("cpu", 0, 0), cpu("cpu0", 0, 0), cpu("cpu1", 0, 0)]);
+        system.apply_cpu_times(vec![cpu("cpu", 100, 100), cpu("cpu0", 75, 25), cpu("cpu1", 25, 75)]);
+        assert_eq!(system.cpu_usage, 50.0);
+        let busy: Vec<_> = system.cpu_cores.iter().map(|c| (c.name.as_str(), c.busy())).collect();
+        assert_eq!(busy, [("cpu0", 75.0), ("cpu1", 25.0)]);
+
+        // cpu1 went offline and cpu2 came up: same length, but cpu2 has no baseline yet
+        system.apply_cpu_times(vec![cpu("cpu", 200, 200), cpu("cpu0", 175, 25), cpu("cpu2", 0, 0)]);
+        let busy: Vec<_> = system.cpu_cores.iter().map(|c| (c.name.as_str(), c.busy())).collect();
+        assert_eq!(busy, [("cpu0", 100.0)]);
+
+        system.apply_cpu_times(vec![cpu("cpu", 300, 300), cpu("cpu0", 200, 100), cpu("cpu2", 50, 50)]);
+        let busy: Vec<_> = system.cpu_cores.iter().map(|c| (c.name.as_str(), c.busy())).collect();
+        assert_eq!(busy, [("cpu0", 25.0), ("cpu2", 50.0)]);
+    }
+
+    #[test]
+    fn empty_cpu_times_keep_the_baseline() {
+        let mut system = SystemState::new();
+        system.apply_cpu_times(Vec::new());
+        system.apply_cpu_times(vec![cpu("cpu", 0, 0), cpu("cpu0", 0, 0)]);
+        system.apply_cpu_times(Vec::new());
+        system.apply_cpu_times(vec![cpu("cpu", 30, 70), cpu("cpu0", 30, 70)]);
+        assert_eq!(system.cpu_usage, 30.0);
+        assert_eq!(system.cpu_cores.len(), 1);
+    }
+}
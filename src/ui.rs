@@ -1,17 +1,47 @@
-use crate::system::SystemState;
+use crate::alerts::{Alert, AlertManager};
+use crate::config::Config;
+use crate::diskusage::DiskUsage;
+use crate::history::HistoryWriter;
+use crate::metrics::{self, RESOLUTIONS};
+use crate::monitor::SelfMonitor;
+use crate::netctl::LinkController;
+use crate::packages::PackageManager;
+use crate::pipeline::{Collector, Pipeline};
+use crate::plugins::{PluginRegistry, PluginTab};
+use crate::probes::ProbeRunner;
+use crate::repos::RepoMonitor;
+use crate::services::{self, ServiceManager};
+use crate::system::{Connection, DiskDevice, FileSystem, NetworkInterface, Package, SystemState};
 use crate::{modules, advanced_modules};
+use chrono::Local;
+use crossterm::event::KeyCode;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols,
     text::{Span, Spans},
     widgets::{
-        Block, Borders, Cell, Gauge, List, ListItem, Paragraph, Row, Sparkline, Table,
-        Tabs, Wrap,
+        Axis, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, List, ListItem, Paragraph,
+        Row, Table, TableState, Tabs, Wrap,
     },
     Frame,
 };
 
+pub const HISTORY_WINDOWS: [(&str, i64); 5] = [
+    ("10m", 10 * 60),
+    ("1h", 60 * 60),
+    ("6h", 6 * 60 * 60),
+    ("24h", 24 * 60 * 60),
+    ("7d", 7 * 24 * 60 * 60),
+];
+
+// Tabs past these come from enabled plugins
+const BUILTIN_TABS: usize = 12;
+
 pub struct TabsState {
     pub titles: Vec<String>,
     pub index: usize,
@@ -34,20 +64,91 @@ impl TabsState {
     }
 }
 
-pub struct Tab {
-    pub title: String,
-    pub content: String,
+// Disk usage browser position, rooted at the mount it was opened from
+pub struct Explorer {
+    pub root: PathBuf,
+    pub path: PathBuf,
+    pub selected: usize,
+}
+
+// Text being typed into the prompt line, by what it applies to
+pub enum Prompt {
+    ConnectionFilter(String),
+    PackageSearch(String),
+    PackageInstall(String),
+    PluginCommand(String),
+}
+
+// An action waiting for the user to confirm it
+pub enum Confirm {
+    Service { service: String, action: &'static str },
+    LinkDown { interface: String },
+    PackagePlan,
 }
 
 pub struct App {
     pub tabs: TabsState,
     pub system: SystemState,
-    pub enhanced_view: bool,
+    pub alerts: AlertManager,
+    pub history: Option<HistoryWriter>,
+    pub history_window: usize,
+    pub chart_cursor: Option<i64>,
+    pub links: LinkController,
+    pub selected_interface: Option<String>,
+    pub show_connections: bool,
+    pub show_pseudo_fs: bool,
+    pub show_disk_io: bool,
+    pub selected_mount: usize,
+    pub explorer: Option<Explorer>,
+    pub disk_usage: DiskUsage,
+    pub connection_filter: String,
+    pub prompt: Option<Prompt>,
+    pub services: ServiceManager,
+    pub selected_service: usize,
+    pub confirm: Option<Confirm>,
+    pub packages: PackageManager,
+    pub selected_package: usize,
+    pub package_search: String,
+    pub show_package_detail: bool,
+    pub repos: RepoMonitor,
+    pub plugins: PluginRegistry,
+    pub selected_plugin: usize,
+    pub probes: ProbeRunner,
+    pub pipeline: Pipeline,
+    pub frame_rate: Duration,
+    pub tick_rate: Duration,
+    pub monitor: SelfMonitor,
+    pub show_monitor: bool,
 }
 
 impl App {
-    pub fn new() -> App {
-        App {
+    pub fn new(config: &Config) -> App {
+        let mut system = SystemState::new();
+        let mut restored = 0;
+        let history = if config.history.enabled {
+            match HistoryWriter::open(&config.history, &mut system.metrics) {
+                Ok((writer, count)) => {
+                    restored = count;
+                    Some(writer)
+                }
+                Err(err) => {
+                    system.log("ERROR", "History", &format!("History persistence disabled: {:#}", err));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if restored > 0 {
+            system.log("INFO", "History", &format!("Restored {} samples from disk", restored));
+        }
+        let refresh = &config.refresh;
+        let (tick_rate, services_rate, packages_rate) =
+            (refresh.tick_rate(&mut system), refresh.services_rate(&mut system), refresh.packages_rate(&mut system));
+        let pipeline = Pipeline::new(refresh, &mut system);
+        let probes = ProbeRunner::new(&config.probes, pipeline.handle(), &mut system);
+
+        let mut app = App {
             tabs: TabsState::new(vec![
                 "Overview".to_string(),
                 "Kernel".to_string(),
@@ -59,10 +160,87 @@ impl App {
                 "DevTools".to_string(),
                 "Plugins".to_string(),
                 "Config".to_string(),
+                "Performance".to_string(),
+                "Services".to_string(),
             ]),
-            system: SystemState::new(),
-            enhanced_view: true,
+            system,
+            alerts: AlertManager::new(&config.alerts),
+            history,
+            // Open on the last day when there is restored history to look at
+            history_window: if restored > 0 { 3 } else { 0 },
+            chart_cursor: None,
+            links: LinkController::new(&config.network),
+            selected_interface: None,
+            show_connections: false,
+            show_pseudo_fs: false,
+            show_disk_io: false,
+            selected_mount: 0,
+            explorer: None,
+            disk_usage: DiskUsage::new(),
+            connection_filter: String::new(),
+            prompt: None,
+            services: ServiceManager::new(&config.services, services_rate),
+            selected_service: 0,
+            confirm: None,
+            packages: PackageManager::new(&config.packages, packages_rate),
+            selected_package: 0,
+            package_search: String::new(),
+            show_package_detail: false,
+            repos: RepoMonitor::new(&config.packages),
+            plugins: PluginRegistry::new(&config.plugins),
+            selected_plugin: 0,
+            probes,
+            pipeline,
+            frame_rate: refresh.frame_rate(),
+            tick_rate,
+            monitor: SelfMonitor::new(),
+            show_monitor: false,
+        };
+        app.plugins.start(&mut app.system, &mut app.alerts);
+        app.sync_plugin_tabs();
+        app
+    }
+
+    // Rebuilds the tab bar from the built-in tabs and those of enabled plugins
+    pub fn sync_plugin_tabs(&mut self) {
+        self.tabs.titles.truncate(BUILTIN_TABS);
+        self.tabs.titles.extend(self.plugins.tabs().into_iter().map(|(_, title)| title));
+        self.tabs.index = self.tabs.index.min(self.tabs.titles.len() - 1);
+    }
+
+    pub fn select_plugin(&mut self, delta: isize) {
+        let count = self.plugins.plugins.len();
+        if count > 0 {
+            self.selected_plugin = self.selected_plugin.saturating_add_signed(delta).min(count - 1);
+        }
+    }
+
+    pub fn toggle_plugin_enabled(&mut self) {
+        let index = self.selected_plugin;
+        match self.plugins.plugins.get(index) {
+            Some(slot) if slot.is_enabled() => self.plugins.disable(index, &mut self.system, &mut self.alerts),
+            Some(slot) if slot.is_loaded() => self.plugins.enable(index, &mut self.system, &mut self.alerts),
+            Some(_) => {
+                if self.plugins.load(index, &mut self.system) {
+                    self.plugins.enable(index, &mut self.system, &mut self.alerts);
+                }
+            }
+            None => return,
         }
+        self.sync_plugin_tabs();
+    }
+
+    pub fn load_plugin(&mut self) {
+        self.plugins.load(self.selected_plugin, &mut self.system);
+    }
+
+    pub fn unload_plugin(&mut self) {
+        self.plugins.unload(self.selected_plugin, &mut self.system, &mut self.alerts);
+        self.sync_plugin_tabs();
+    }
+
+    pub fn open_plugin_command(&mut self) {
+        self.prompt = Some(Prompt::PluginCommand(String::new()));
     }
 
     pub fn next_tab(&mut self) {
@@ -73,8 +251,354 @@ impl App {
         self.tabs.previous();
     }
 
+    pub fn zoom_in(&mut self) {
+        self.history_window = self.history_window.saturating_sub(1);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.history_window = (self.history_window + 1).min(HISTORY_WINDOWS.len() - 1);
+    }
+
+    // Moves the chart crosshair by 1/100th of the visible window
+    pub fn move_cursor(&mut self, steps: i64) {
+        let now = Local::now().timestamp();
+        let window = HISTORY_WINDOWS[self.history_window].1;
+        let step = (window / 100).max(1);
+        let cursor = self.chart_cursor.unwrap_or(now) + steps * step;
+        self.chart_cursor = Some(cursor.clamp(now - window, now));
+    }
+
+    pub fn clear_cursor(&mut self) {
+        self.chart_cursor = None;
+    }
+
+    // Interfaces come and go between samples, so the selection follows the name rather than the row
+    pub fn selected_interface_index(&self) -> usize {
+        let interfaces = &self.system.network_interfaces;
+        self.selected_interface
+            .as_ref()
+            .and_then(|name| interfaces.iter().position(|interface| &interface.name == name))
+            .unwrap_or(0)
+    }
+
+    pub fn select_interface(&mut self, delta: isize) {
+        let interfaces = &self.system.network_interfaces;
+        if !interfaces.is_empty() {
+            let index = self.selected_interface_index().saturating_add_signed(delta).min(interfaces.len() - 1);
+            self.selected_interface = Some(interfaces[index].name.clone());
+        }
+    }
+
+    // Bringing a link up is harmless; taking one down can cut the session, so it asks first
+    pub fn toggle_selected_interface(&mut self) {
+        let interface = match self.system.network_interfaces.get(self.selected_interface_index()) {
+            Some(interface) => interface,
+            None => return,
+        };
+        if interface.status == "UP" {
+            self.confirm = Some(Confirm::LinkDown { interface: interface.name.clone() });
+        } else {
+            let name = interface.name.clone();
+            self.links.set_link(&mut self.system, &name, true);
+        }
+    }
+
+    pub fn visible_mounts(&self) -> Vec<&FileSystem> {
+        self.system
+            .filesystems
+            .iter()
+            .filter(|fs| self.show_pseudo_fs || !fs.pseudo)
+            .collect()
+    }
+
+    pub fn select_mount(&mut self, delta: isize) {
+        let count = self.visible_mounts().len();
+        if count > 0 {
+            self.selected_mount = self.selected_mount.saturating_add_signed(delta).min(count - 1);
+        }
+    }
+
+    pub fn open_explorer(&mut self) {
+        let root = match self.visible_mounts().get(self.selected_mount) {
+            Some(fs) => PathBuf::from(&fs.mount),
+            None => return,
+        };
+        self.disk_usage.request(&root);
+        self.explorer = Some(Explorer { path: root.clone(), root, selected: 0 });
+    }
+
+    pub fn close_explorer(&mut self) {
+        self.explorer = None;
+    }
+
+    pub fn explorer_move(&mut self, delta: isize) {
+        if let Some(explorer) = &mut self.explorer {
+            let count = self.disk_usage.listing(&explorer.path).map_or(0, |listing| listing.entries.len());
+            if count > 0 {
+                explorer.selected = explorer.selected.saturating_add_signed(delta).min(count - 1);
+            }
+        }
+    }
+
+    pub fn explorer_enter(&mut self) {
+        let explorer = match &mut self.explorer {
+            Some(explorer) => explorer,
+            None => return,
+        };
+        let entry = self
+            .disk_usage
+            .listing(&explorer.path)
+            .and_then(|listing| listing.entries.get(explorer.selected));
+        if let Some(entry) = entry.filter(|entry| entry.is_dir && !entry.mount_point) {
+            explorer.path.push(&entry.name);
+            explorer.selected = 0;
+            self.disk_usage.request(&explorer.path);
+        }
+    }
+
+    // Goes to the parent directory and reselects the one we came from
+    pub fn explorer_up(&mut self) {
+        let explorer = match &mut self.explorer {
+            Some(explorer) if explorer.path != explorer.root => explorer,
+            _ => return,
+        };
+        let child = explorer.path.file_name().map(|name| name.to_os_string());
+        explorer.path.pop();
+        explorer.selected = self
+            .disk_usage
+            .listing(&explorer.path)
+            .and_then(|listing| listing.entries.iter().position(|entry| Some(&entry.name) == child.as_ref()))
+            .unwrap_or(0);
+    }
+
+    pub fn explorer_rescan(&mut self) {
+        if let Some(explorer) = &mut self.explorer {
+            explorer.selected = 0;
+            self.disk_usage.rescan(&explorer.path);
+        }
+    }
+
+    pub fn toggle_connections(&mut self) {
+        self.show_connections = !self.show_connections;
+        self.pipeline.set_enabled(Collector::Connections, self.show_connections);
+        if self.show_connections {
+            self.pipeline.refresh_now();
+        }
+    }
+
+    pub fn open_prompt(&mut self) {
+        self.prompt = Some(Prompt::ConnectionFilter(self.connection_filter.clone()));
+    }
+
+    pub fn open_package_search(&mut self) {
+        self.show_package_detail = false;
+        self.prompt = Some(Prompt::PackageSearch(self.package_search.clone()));
+    }
+
+    // Line editing while the prompt is open; Enter applies, Esc keeps the previous connection filter.
+    // Package search applies as you type and Esc clears it
+    pub fn handle_prompt_key(&mut self, code: KeyCode) {
+        let text = match self.prompt.as_mut() {
+            Some(Prompt::ConnectionFilter(text))
+            | Some(Prompt::PackageSearch(text))
+            | Some(Prompt::PackageInstall(text))
+            | Some(Prompt::PluginCommand(text)) => text,
+            None => return,
+        };
+        match code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Enter => {
+                match self.prompt.take() {
+                    Some(Prompt::ConnectionFilter(text)) => self.connection_filter = text.trim().to_string(),
+                    Some(Prompt::PackageInstall(text)) => self.packages.queue_install(&text, &self.system.packages),
+                    Some(Prompt::PluginCommand(text)) => self.plugins.run_command(&text, &mut self.system),
+                    _ => {}
+                }
+                return;
+            }
+            KeyCode::Esc => {
+                if let Some(Prompt::PackageSearch(_)) = self.prompt.take() {
+                    self.package_search.clear();
+                }
+                return;
+            }
+            _ => {}
+        }
+        if let Some(Prompt::PackageSearch(text)) = &self.prompt {
+            self.package_search = text.trim().to_string();
+            self.selected_package = 0;
+        }
+    }
+
+    // Installed packages whose name or description contains the search text
+    pub fn visible_packages(&self) -> Vec<&Package> {
+        let search = self.package_search.to_lowercase();
+        self.system
+            .packages
+            .iter()
+            .filter(|package| {
+                search.is_empty()
+                    || package.name.to_lowercase().contains(&search)
+                    || package.description.to_lowercase().contains(&search)
+            })
+            .collect()
+    }
+
+    pub fn open_package_install(&mut self) {
+        self.prompt = Some(Prompt::PackageInstall(String::new()));
+    }
+
+    // Queues the action for the selected package; upgrades need a newer version in the index
+    pub fn toggle_package_op(&mut self, action: &'static str) {
+        let package = match self.visible_packages().get(self.selected_package) {
+            Some(package) if action != "upgrade" || package.update.is_some() => package.name.clone(),
+            Some(package) => {
+                let message = format!("{} is already the newest version in the index", package.name);
+                self.system.log("WARN", "Packages", &message);
+                return;
+            }
+            None => return,
+        };
+        self.packages.toggle(action, &package, &self.system.packages);
+    }
+
+    pub fn toggle_clean_cache(&mut self) {
+        self.packages.toggle("clean", "", &self.system.packages);
+    }
+
+    pub fn clear_package_queue(&mut self) {
+        self.packages.clear_queue(&self.system.packages);
+    }
+
+    pub fn confirm_package_plan(&mut self) {
+        let plan = &self.packages.plan;
+        if !plan.conflicts.is_empty() {
+            let message = format!("Plan not applied: {} would be removed but is still needed", plan.conflicts.join(", "));
+            self.system.log("WARN", "Packages", &message);
+        } else if self.packages.run.is_none() && !plan.is_empty() {
+            self.confirm = Some(Confirm::PackagePlan);
+        }
+    }
+
+    pub fn toggle_package_detail(&mut self) {
+        self.show_package_detail = !self.show_package_detail && !self.visible_packages().is_empty();
+    }
+
+    pub fn clear_package_search(&mut self) {
+        self.package_search.clear();
+        self.selected_package = 0;
+    }
+
+    pub fn select_service(&mut self, delta: isize) {
+        let count = self.system.services.len();
+        if count > 0 {
+            self.selected_service = self.selected_service.saturating_add_signed(delta).min(count - 1);
+        }
+    }
+
+    pub fn select_package(&mut self, delta: isize) {
+        let count = self.visible_packages().len();
+        if count > 0 {
+            self.selected_package = self.selected_package.saturating_add_signed(delta).min(count - 1);
+        }
+    }
+
+    pub fn request_service_action(&mut self, action: &'static str) {
+        if let Some(service) = self.system.services.get(self.selected_service) {
+            self.confirm = Some(Confirm::Service { service: service.name.clone(), action });
+        }
+    }
+
+    // Starts orbital when it is down and stops it otherwise, after confirmation
+    pub fn toggle_orbital(&mut self) {
+        let orbital = self
+            .system
+            .services
+            .iter()
+            .find(|service| service.name == "orbital" || service.name == "orbital.service");
+        match orbital {
+            Some(service) => {
+                let action = if service.status == "RUNNING" { "stop" } else { "start" };
+                self.confirm = Some(Confirm::Service { service: service.name.clone(), action });
+            }
+            None => self.system.log("WARN", "Services", "No orbital service is known to the service backend"),
+        }
+    }
+
+    pub fn handle_confirm_key(&mut self, code: KeyCode) {
+        let pending = match self.confirm.take() {
+            Some(pending) if matches!(code, KeyCode::Char('y') | KeyCode::Char('Y')) => pending,
+            _ => return,
+        };
+        match pending {
+            Confirm::Service { service, action } => self.services.control(&mut self.system, &service, action),
+            Confirm::LinkDown { interface } => self.links.set_link(&mut self.system, &interface, false),
+            Confirm::PackagePlan => self.packages.apply(&mut self.system),
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.pipeline.refresh_now();
+        self.system.update();
+    }
+
+    // Cheap enough to run between frames; returns whether new samples arrived
+    pub fn collect_samples(&mut self) -> bool {
+        self.pipeline.set_enabled(Collector::Connections, self.tabs.index == 4 && self.show_connections);
+        self.pipeline.collect_results(&mut self.system)
+    }
+
     pub fn on_tick(&mut self) {
         self.system.update();
+        self.monitor.sample(&mut self.system);
+        self.links.collect_results(&mut self.system);
+        self.services.collect_results(&mut self.system);
+        self.services.refresh();
+        self.packages.collect_results(&mut self.system);
+        self.packages.refresh();
+        self.repos.collect_results(&mut self.system, &mut self.alerts);
+        self.repos.refresh();
+        self.disk_usage.collect_results();
+        self.probes.collect_results(&mut self.system);
+        self.plugins.collect(&mut self.system, &mut self.alerts);
+        if self.tabs.titles.len() != BUILTIN_TABS + self.plugins.tabs().len() {
+            self.sync_plugin_tabs();
+        }
+        self.alerts.evaluate(&mut self.system);
+
+        if let Some(history) = &mut self.history {
+            if let Some(err) = history.compaction_error() {
+                self.system.log("WARN", "History", &format!("History compaction failed: {:#}", err));
+            }
+            if let Err(err) = history.persist(&mut self.system.metrics) {
+                self.system.log("ERROR", "History", &format!("History persistence disabled: {:#}", err));
+                self.history = None;
+            }
+        }
+    }
+
+    pub fn notify_administrator(&mut self) {
+        let active: Vec<String> = self.alerts.active().map(|a| a.rule.clone()).collect();
+        let message = if active.is_empty() {
+            "Administrator notification requested from console".to_string()
+        } else {
+            format!("Administrator notification: active alerts {}", active.join(", "))
+        };
+
+        self.system.log("INFO", "Security", &message);
+        self.alerts.notify(Alert {
+            rule: "notify-administrator".to_string(),
+            state: "FIRING".to_string(),
+            severity: "WARN".to_string(),
+            metric: "manual".to_string(),
+            value: active.len() as f64,
+            threshold: 0.0,
+            message,
+            timestamp: Local::now(),
+        });
     }
 }
 
@@ -94,7 +618,136 @@ pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     draw_header(f, chunks[0]);
     draw_tabs(f, app, chunks[1]);
     draw_content(f, app, chunks[2]);
-    draw_footer(f, chunks[3]);
+    draw_footer(f, app, chunks[3]);
+
+    if let Some(Prompt::PluginCommand(text)) = &app.prompt {
+        let area = Rect::new(chunks[2].x, chunks[2].bottom().saturating_sub(3), chunks[2].width, 3.min(chunks[2].height));
+        let input = Paragraph::new(format!(":{}", text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Plugin command [Enter=Run Esc=Cancel]")
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(Clear, area);
+        f.render_widget(input, area);
+    }
+    if app.show_monitor {
+        draw_self_monitor(f, app, chunks[2]);
+    }
+    if let Some(pending) = &app.confirm {
+        draw_confirm(f, app, pending, size);
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+// Overlay with the console's own overhead, toggled with F2 from any tab
+fn draw_self_monitor<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let monitor = &app.monitor;
+    let label = |text: &str| Span::styled(format!("{:<16}", text), Style::default().fg(Color::Yellow));
+    let value = |text: String| Span::styled(text, Style::default().fg(Color::Green));
+    let (rss, threads) = match monitor.usage {
+        Some(usage) => (format_bytes(usage.rss_bytes), usage.threads.to_string()),
+        None => ("-".to_string(), "-".to_string()),
+    };
+    let cpu_color = if monitor.cpu_percent > 25.0 {
+        Color::Red
+    } else if monitor.cpu_percent > 5.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+
+    let mut lines = vec![
+        Spans::from(vec![
+            label("Console CPU"),
+            Span::styled(format!("{:.1}%", monitor.cpu_percent), Style::default().fg(cpu_color)),
+            value(format!("  ({} threads)", threads)),
+        ]),
+        Spans::from(vec![label("Resident memory"), value(rss)]),
+        Spans::from(vec![
+            label("Frames"),
+            value(format!(
+                "{} drawn, last {}, avg {}, max {}",
+                monitor.frames,
+                millis(monitor.last_frame),
+                millis(monitor.average_frame()),
+                millis(monitor.max_frame)
+            )),
+        ]),
+        Spans::from(vec![
+            label("Ticks"),
+            value(format!("{} run, last {}, {} dropped", monitor.ticks, millis(monitor.last_tick), monitor.dropped_ticks)),
+        ]),
+        Spans::from(vec![
+            label("Input latency"),
+            value(format!("last {}, max {}", millis(monitor.input_latency), millis(monitor.max_input_latency))),
+        ]),
+        Spans::from(vec![
+            label("Sample queue"),
+            value(format!("{} waiting, {} to apply", millis(app.pipeline.queue_latency), millis(app.pipeline.apply_time))),
+        ]),
+        Spans::from(""),
+    ];
+    for status in &app.pipeline.status {
+        let state = status.state();
+        let color = match state {
+            "OK" => Color::Green,
+            "PAUSED" | "WAITING" => Color::Yellow,
+            _ => Color::Red,
+        };
+        lines.push(Spans::from(vec![
+            label(status.collector.name()),
+            value(format!("last {:<8} avg {:<8} ", millis(status.last_duration), millis(status.average()))),
+            Span::styled(state, Style::default().fg(color)),
+        ]));
+    }
+
+    let width = 60.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let rect = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+    let overlay = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Console Overhead [F2=Close]")
+            .style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(Clear, rect);
+    f.render_widget(overlay, rect);
+}
+
+fn draw_confirm<B: Backend>(f: &mut Frame<B>, app: &App, pending: &Confirm, size: Rect) {
+    let question = match pending {
+        Confirm::Service { service, action } => format!("{} {}?", action, service),
+        Confirm::LinkDown { interface } => format!("Take {} down? Connections over it will drop.", interface),
+        Confirm::PackagePlan => {
+            let plan = &app.packages.plan;
+            format!(
+                "Apply {} install, {} upgrade, {} remove{} ({})?",
+                plan.install.len(),
+                plan.upgrade.len(),
+                plan.remove.len(),
+                if plan.clean_cache { ", clean cache" } else { "" },
+                format_size_delta(plan.size_delta)
+            )
+        }
+    };
+    let width = (question.chars().count() as u16 + 6).max(50).min(size.width);
+    let area = Rect::new(size.x + (size.width - width) / 2, size.y + size.height.saturating_sub(5) / 2, width, 5.min(size.height));
+    let text = vec![
+        Spans::from(Span::styled(question, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Spans::from(""),
+        Spans::from(Span::styled("[y] Confirm  [any other key] Cancel", Style::default().fg(Color::Green))),
+    ];
+    let popup = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title("Confirm").style(Style::default().fg(Color::Red)))
+        .alignment(Alignment::Center);
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
 }
 
 fn draw_header<B: Backend>(f: &mut Frame<B>, area: Rect) {
@@ -112,7 +765,7 @@ fn draw_header<B: Backend>(f: &mut Frame<B>, area: Rect) {
     ];
 
     let header = Paragraph::new(ascii_art.iter().enumerate().map(|(i, &line)| {
-        let color = if i < 6 { Color::Green } else if i >= 6 && i < 9 { Color::Red } else { Color::Green };
+        let color = if i < 6 { Color::Green } else if (6..9).contains(&i) { Color::Red } else { Color::Green };
         Spans::from(vec![
             Span::styled(line, Style::default().fg(color).add_modifier(Modifier::BOLD))
         ])
@@ -156,22 +809,39 @@ fn draw_content<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
     match app.tabs.index {
         0 => draw_overview(f, &app.system, area),
         1 => modules::draw_kernel_monitor(f, &app.system, area),
-        2 => modules::draw_filesystem_inspector(f, &app.system, area),
+        2 if app.explorer.is_some() => draw_disk_usage(f, app, area),
+        2 if app.show_disk_io => draw_disk_io(f, app, area),
+        2 => draw_filesystem(f, app, area),
         3 => draw_processes(f, &app.system, area),
-        4 => draw_network(f, &app.system, area),
-        5 => modules::draw_security_audit(f, &app.system, area),
-        6 => advanced_modules::draw_package_manager(f, &app.system, area),
-        7 => advanced_modules::draw_developer_tools(f, &app.system, area),
-        8 => advanced_modules::draw_plugin_system(f, &app.system, area),
-        9 => draw_config(f, &app.system, area),
-        _ => {}
+        4 if app.show_connections => {
+            let prompt = match &app.prompt {
+                Some(Prompt::ConnectionFilter(text)) => Some(text.as_str()),
+                _ => None,
+            };
+            draw_connections(f, &app.system, &app.connection_filter, prompt, area)
+        }
+        4 => draw_network(f, app, area),
+        5 => modules::draw_security_audit(f, &app.alerts, area),
+        6 if app.show_package_detail => advanced_modules::draw_package_detail(f, app, area),
+        6 => advanced_modules::draw_package_manager(f, app, area),
+        7 => advanced_modules::draw_developer_tools(f, app, area),
+        8 => advanced_modules::draw_plugin_system(f, app, area),
+        9 => draw_config(f, &app.system, &app.probes, area),
+        10 => draw_performance(f, &app.system, app.history_window, app.chart_cursor, area),
+        11 => draw_services(f, app, area),
+        index => {
+            if let Some((plugin, tab)) = app.plugins.tabs().into_iter().nth(index - BUILTIN_TABS) {
+                let view = PluginTab { registry: &mut app.plugins, index: plugin, tab: &tab, system: &app.system };
+                f.render_widget(view, area);
+            }
+        }
     }
 }
 
 fn draw_overview<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Length(8), Constraint::Length(8), Constraint::Length(6), Constraint::Min(0)].as_ref())
         .split(area);
 
     // System info
@@ -194,10 +864,13 @@ fn draw_overview<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect)
 
     // Quick stats
     draw_quick_stats(f, system, chunks[2]);
+
+    // Recent log entries
+    draw_logs(f, system, chunks[3]);
 }
 
 fn draw_system_status<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
-    let items = vec![
+    let items = [
         format!("Boot Time: {}", system.boot_time.format("%Y-%m-%d %H:%M:%S")),
         format!("Uptime: {}", system.get_uptime_string()),
         format!("CPU Usage: {:.1}%", system.cpu_usage),
@@ -251,12 +924,12 @@ fn draw_resource_metrics<B: Backend>(f: &mut Frame<B>, system: &SystemState, are
     f.render_widget(memory_gauge, chunks[1]);
 
     // I/O Stats
-    let io_info = vec![
+    let io_info = [
         format!("IPC: {}/sec", system.ipc_messages),
         format!("FS Read: {}/sec", system.fs_reads),
         format!("FS Write: {}/sec", system.fs_writes),
-        format!("Net RX: {} KB", system.network_rx / 1024),
-        format!("Net TX: {} KB", system.network_tx / 1024),
+        format!("Net RX: {:.1} KB/s", system.network_rx_rate / 1024.0),
+        format!("Net TX: {:.1} KB/s", system.network_tx_rate / 1024.0),
     ];
 
     let io_items: Vec<ListItem> = io_info
@@ -308,7 +981,7 @@ fn draw_subsystem_status<B: Backend>(f: &mut Frame<B>, system: &SystemState, are
         .collect();
 
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Subsystems [n=Network, o=Orbital]"));
+        .block(Block::default().borders(Borders::ALL).title("Subsystems [o=Orbital]"));
 
     f.render_widget(list, area);
 }
@@ -334,12 +1007,21 @@ fn draw_quick_stats<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Re
     f.render_widget(process_para, chunks[0]);
 
     // Filesystems
-    let online_fs = system.filesystems.iter().filter(|fs| fs.status == "ONLINE").count();
-    let total_fs = system.filesystems.len();
+    let mounts: Vec<&FileSystem> = system.filesystems.iter().filter(|fs| !fs.pseudo).collect();
+    let online_fs = mounts.iter().filter(|fs| fs.status == "ONLINE").count();
+    // Bind mounts share a device, so count each device once
+    let mut devices = std::collections::HashSet::new();
+    let (used, free) = mounts
+        .iter()
+        .filter(|fs| devices.insert(fs.device.as_str()))
+        .fold((0, 0), |(used, free), fs| (used + fs.used_bytes, free + fs.free_bytes));
 
     let fs_text = format!(
-        "Mounted: {}/{}\nTotal Used: 3.5 GB\nTotal Free: 9.7 GB",
-        online_fs, total_fs
+        "Mounted: {}/{}\nTotal Used: {}\nTotal Free: {}",
+        online_fs,
+        mounts.len(),
+        format_bytes(used),
+        format_bytes(free)
     );
 
     let fs_para = Paragraph::new(fs_text)
@@ -349,9 +1031,7 @@ fn draw_quick_stats<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Re
     f.render_widget(fs_para, chunks[1]);
 
     // Security
-    let security_text = format!(
-        "Sandbox: ACTIVE\nAudit Logs: 247\nFailed Logins: 0\nActive Sessions: 2"
-    );
+    let security_text = "Sandbox: ACTIVE\nAudit Logs: 247\nFailed Logins: 0\nActive Sessions: 2";
 
     let security_para = Paragraph::new(security_text)
         .block(Block::default().borders(Borders::ALL).title("Security"))
@@ -399,115 +1079,346 @@ fn draw_processes<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect
     f.render_widget(table, area);
 }
 
-fn draw_filesystem<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+fn draw_filesystem<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let mounts = app.visible_mounts();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(10), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Min(0), Constraint::Length(6)].as_ref())
         .split(area);
 
     // Filesystem table
-    let header_cells = ["Mount", "Type", "Status", "Used", "Free", "Usage%"]
+    let header_cells = ["Mount", "Source", "Type", "Size", "Used", "Avail", "Use%", "Inodes", "IUse%", "Options"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let rows = system.filesystems.iter().map(|fs| {
+    let rows = mounts.iter().map(|fs| {
         let cells = vec![
-            Cell::from(fs.mount.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(fs.fs_type.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(fs.status.clone()).style(Style::default().fg(
+            Cell::from(fs.mount.clone()).style(Style::default().fg(
                 if fs.status == "ONLINE" { Color::Green } else { Color::Red }
             )),
-            Cell::from(fs.used.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(fs.free.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(format!("{}%", fs.usage_percent)).style(Style::default().fg(
-                if fs.usage_percent > 90 { Color::Red } 
-                else if fs.usage_percent > 70 { Color::Yellow } 
-                else { Color::Green }
-            )),
+            Cell::from(fs.source.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(fs.fs_type.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(format_bytes(fs.total_bytes)).style(Style::default().fg(Color::Green)),
+            Cell::from(format_bytes(fs.used_bytes)).style(Style::default().fg(Color::Green)),
+            Cell::from(format_bytes(fs.free_bytes)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{}%", fs.usage_percent)).style(Style::default().fg(usage_color(fs.usage_percent))),
+            Cell::from(format!("{}", fs.inodes_used)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{}%", fs.inode_percent)).style(Style::default().fg(usage_color(fs.inode_percent))),
+            Cell::from(if fs.status == "ONLINE" { fs.options.clone() } else { fs.status.clone() })
+                .style(Style::default().fg(if fs.status == "ONLINE" { Color::Green } else { Color::Red })),
         ];
         Row::new(cells).height(1)
     });
 
+    let title = format!(
+        "Filesystem Manager ({} mounts{}) [↑/↓=Select Enter=Explore p=Pseudo filesystems v=Disk I/O]",
+        mounts.len(),
+        if app.show_pseudo_fs { ", including pseudo" } else { "" }
+    );
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Filesystem Manager").style(Style::default().fg(Color::Green)))
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
         .widths(&[
-            Constraint::Length(12),
-            Constraint::Length(10),
-            Constraint::Length(8),
-            Constraint::Length(10),
-            Constraint::Length(10),
+            Constraint::Length(20),
+            Constraint::Length(14),
             Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Length(5),
+            Constraint::Length(20),
         ]);
 
-    f.render_widget(table, chunks[0]);
+    let mut state = TableState::default();
+    state.select(Some(app.selected_mount.min(mounts.len().saturating_sub(1))));
+    f.render_stateful_widget(table, chunks[0], &mut state);
 
-    // Filesystem usage bars
+    // Byte and inode usage bars for the fullest mounts
+    let mut fullest: Vec<&FileSystem> = mounts.iter().copied().filter(|fs| fs.total_bytes > 0).collect();
+    fullest.sort_by_key(|fs| std::cmp::Reverse(fs.usage_percent.max(fs.inode_percent)));
     let fs_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(25); 4].as_ref())
         .split(chunks[1]);
 
-    for (i, fs) in system.filesystems.iter().enumerate() {
-        if i < 4 {
-            let usage_ratio = fs.usage_percent as f64 / 100.0;
+    for (fs, chunk) in fullest.iter().zip(fs_chunks) {
+        let block = Block::default().borders(Borders::ALL).title(fs.mount.clone());
+        let inner = block.inner(chunk);
+        f.render_widget(block, chunk);
+        let bars = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Length(2)].as_ref())
+            .split(inner);
+
+        for (bar, (label, percent)) in bars.into_iter().zip([("bytes", fs.usage_percent), ("inodes", fs.inode_percent)]) {
             let gauge = Gauge::default()
-                .block(Block::default().borders(Borders::ALL).title(fs.mount.clone()))
-                .gauge_style(Style::default().fg(
-                    if fs.usage_percent > 90 { Color::Red }
-                    else if fs.usage_percent > 70 { Color::Yellow }
-                    else { Color::Green }
-                ))
-                .ratio(usage_ratio)
-                .label(format!("{}%", fs.usage_percent));
+                .gauge_style(Style::default().fg(usage_color(percent)))
+                .ratio(percent as f64 / 100.0)
+                .label(format!("{} {}%", label, percent));
+            f.render_widget(gauge, bar);
+        }
+    }
+}
 
-            f.render_widget(gauge, fs_chunks[i]);
+fn draw_disk_usage<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let explorer = match &app.explorer {
+        Some(explorer) => explorer,
+        None => return,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let listing = app.disk_usage.listing(&explorer.path);
+    let progress = app.disk_usage.progress(&explorer.path);
+    let status = match (&progress, listing) {
+        (Some(progress), _) => format!(
+            "Scanning {}: {} files, {}",
+            progress.root.display(),
+            progress.files,
+            format_bytes(progress.bytes)
+        ),
+        (None, Some(listing)) => format!(
+            "Total {} in {} entries, scanned {}s ago{}",
+            format_bytes(listing.total),
+            listing.entries.len(),
+            listing.scanned_at.elapsed().as_secs(),
+            if listing.errors > 0 { format!(", {} unreadable", listing.errors) } else { String::new() }
+        ),
+        (None, None) => "Not scanned".to_string(),
+    };
+    let header = Paragraph::new(status)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Disk Usage: {}", explorer.path.display()))
+                .style(Style::default().fg(Color::Green)),
+        )
+        .style(Style::default().fg(if progress.is_some() { Color::Yellow } else { Color::Green }));
+    f.render_widget(header, chunks[0]);
+
+    let listing = match listing {
+        Some(listing) => listing,
+        None => {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title("[Esc=Close]")
+                .style(Style::default().fg(Color::Green));
+            f.render_widget(block, chunks[1]);
+            return;
         }
+    };
+
+    // ncdu-style bars are relative to the largest entry, percentages to the directory total
+    const BAR_WIDTH: usize = 20;
+    let largest = listing.entries.first().map_or(0, |entry| entry.size).max(1);
+    let rows = listing.entries.iter().map(|entry| {
+        let percent = if listing.total == 0 { 0.0 } else { entry.size as f64 * 100.0 / listing.total as f64 };
+        let filled = (entry.size as f64 / largest as f64 * BAR_WIDTH as f64).round() as usize;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+        let name = entry.name.to_string_lossy();
+        let name = if entry.mount_point {
+            format!("{}/ (mount point)", name)
+        } else if entry.is_dir {
+            format!("{}/", name)
+        } else {
+            name.into_owned()
+        };
+        let cells = vec![
+            Cell::from(format_bytes(entry.size)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{:.1}%", percent)).style(Style::default().fg(usage_color(percent as u16))),
+            Cell::from(bar).style(Style::default().fg(usage_color(percent as u16))),
+            Cell::from(name).style(Style::default().fg(if entry.is_dir { Color::Cyan } else { Color::Green })),
+        ];
+        Row::new(cells).height(1)
+    });
+
+    let header_cells = ["Size", "%", "", "Name"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let table = Table::new(rows)
+        .header(Row::new(header_cells).height(1).bottom_margin(1))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("[↑/↓=Select Enter=Open Backspace=Up r=Rescan Esc=Close]")
+                .style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .widths(&[
+            Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Length(BAR_WIDTH as u16),
+            Constraint::Min(20),
+        ]);
+
+    let mut state = TableState::default();
+    state.select(Some(explorer.selected.min(listing.entries.len().saturating_sub(1))));
+    f.render_stateful_widget(table, chunks[1], &mut state);
+}
+
+fn draw_disk_io<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let table_height = (app.system.disks.len() as u16 + 4).clamp(5, 12);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(table_height + 7), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    modules::draw_filesystem_inspector(f, &app.system, chunks[0]);
+    draw_disk_history(f, &app.system, app.history_window, chunks[1]);
+}
+
+// Throughput and utilization charts for the busiest devices
+fn draw_disk_history<B: Backend>(f: &mut Frame<B>, system: &SystemState, history_window: usize, area: Rect) {
+    let (window_label, window) = HISTORY_WINDOWS[history_window];
+    let tier = metrics::tier_for(window);
+    let now = Local::now().timestamp();
+
+    let mut disks: Vec<&DiskDevice> = system.disks.iter().collect();
+    disks.sort_by(|a, b| (b.read_rate + b.write_rate).total_cmp(&(a.read_rate + a.write_rate)));
+    let count = disks.len().min((area.width / 40).max(1) as usize);
+    if count == 0 {
+        return;
+    }
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, count as u32); count])
+        .split(area);
+
+    for (disk, column) in disks.iter().zip(columns.iter()) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(*column);
+        let points = column.width.saturating_sub(10) as usize * 2;
+        let time = TimeWindow { now, span: window, cursor: None };
+
+        let read = history_points(system, &format!("disk.{}.read_rate", disk.name), tier, now, window, points);
+        let write = history_points(system, &format!("disk.{}.write_rate", disk.name), tier, now, window, points);
+        let peak = read.iter().chain(&write).fold(0.0_f64, |a, p| a.max(p.1));
+        let title = format!("{} [{}] read {} write {}", disk.name, window_label, format_rate(disk.read_rate), format_rate(disk.write_rate));
+        let series = [
+            ChartSeries { name: "Read", color: Color::Green, points: scaled(read, 1024.0) },
+            ChartSeries { name: "Write", color: Color::Yellow, points: scaled(write, 1024.0) },
+        ];
+        draw_history_chart(f, rows[0], &title, "K", &series, (peak / 1024.0 * 1.1).max(1.0), &time);
+
+        let util = history_points(system, &format!("disk.{}.util", disk.name), tier, now, window, points);
+        let title = format!("{} util {:.1}% queue {:.2}", disk.name, disk.util_percent, disk.queue_depth);
+        let series = [ChartSeries { name: "Util", color: Color::Cyan, points: util }];
+        draw_history_chart(f, rows[1], &title, "%", &series, 100.0, &time);
     }
 }
 
-fn draw_network<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+fn usage_color(percent: u16) -> Color {
+    if percent > 90 {
+        Color::Red
+    } else if percent > 70 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+pub fn format_size_delta(delta: i64) -> String {
+    format!("{}{}", if delta < 0 { "-" } else { "+" }, format_bytes(delta.unsigned_abs()))
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn draw_network<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let system = &app.system;
+    let row_height = |interface: &NetworkInterface| interface.addresses.len().max(2) as u16;
+    let table_height = (system.network_interfaces.iter().map(row_height).sum::<u16>() + 4).clamp(6, 20);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Length(table_height), Constraint::Length(7), Constraint::Min(0)].as_ref())
         .split(area);
 
-    // Network interfaces table
-    let header_cells = ["Interface", "Status", "IP Address", "RX Bytes", "TX Bytes", "RX Packets", "TX Packets"]
+    // Network interfaces table, two or more lines per interface
+    let header_cells = ["Interface", "Link", "MAC / MTU", "Addresses", "RX/s", "TX/s", "Errors", "Drops"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
     let rows = system.network_interfaces.iter().map(|interface| {
+        let errors = interface.rx_errors + interface.tx_errors;
+        let drops = interface.rx_dropped + interface.tx_dropped;
+        let kind = if interface.driver == "-" {
+            interface.kind.clone()
+        } else {
+            format!("{} ({})", interface.kind, interface.driver)
+        };
+        let speed = match interface.speed_mbps {
+            Some(speed) if speed >= 1000 => format!("{}G {}", speed / 1000, interface.duplex),
+            Some(speed) => format!("{}M {}", speed, interface.duplex),
+            None => "-".to_string(),
+        };
+        let addresses = if interface.addresses.is_empty() {
+            "-".to_string()
+        } else {
+            interface.addresses.join("\n")
+        };
         let cells = vec![
-            Cell::from(interface.name.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(interface.status.clone()).style(Style::default().fg(
+            Cell::from(format!("{}\n{}", interface.name, kind)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{} {}\n{}", interface.status, interface.operstate, speed)).style(Style::default().fg(
                 if interface.status == "UP" { Color::Green } else { Color::Red }
             )),
-            Cell::from(interface.ip.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(format!("{}", interface.rx_bytes)).style(Style::default().fg(Color::Green)),
-            Cell::from(format!("{}", interface.tx_bytes)).style(Style::default().fg(Color::Green)),
-            Cell::from(format!("{}", interface.rx_packets)).style(Style::default().fg(Color::Green)),
-            Cell::from(format!("{}", interface.tx_packets)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{}\nmtu {}", interface.mac, interface.mtu)).style(Style::default().fg(Color::Green)),
+            Cell::from(addresses).style(Style::default().fg(Color::Green)),
+            Cell::from(format_rate(interface.rx_rate)).style(Style::default().fg(Color::Green)),
+            Cell::from(format_rate(interface.tx_rate)).style(Style::default().fg(Color::Green)),
+            Cell::from(format!("{}", errors)).style(Style::default().fg(
+                if errors > 0 { Color::Red } else { Color::Green }
+            )),
+            Cell::from(format!("{}", drops)).style(Style::default().fg(
+                if drops > 0 { Color::Yellow } else { Color::Green }
+            )),
         ];
-        Row::new(cells).height(1)
+        Row::new(cells).height(row_height(interface))
     });
 
+    let title = format!(
+        "Network Interfaces ({}) [↑/↓=Select n=Up/Down v=Connections]",
+        app.links.mode().name()
+    );
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Network Interfaces [n=Toggle Network]").style(Style::default().fg(Color::Green)))
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
         .widths(&[
-            Constraint::Length(12),
-            Constraint::Length(8),
-            Constraint::Length(15),
+            Constraint::Length(21),
+            Constraint::Length(13),
+            Constraint::Length(17),
+            Constraint::Length(28),
             Constraint::Length(10),
             Constraint::Length(10),
-            Constraint::Length(12),
-            Constraint::Length(12),
+            Constraint::Length(6),
+            Constraint::Length(6),
         ]);
 
-    f.render_widget(table, chunks[0]);
+    let mut state = TableState::default();
+    state.select(Some(app.selected_interface_index()));
+    f.render_stateful_widget(table, chunks[0], &mut state);
 
     // Network statistics
     let net_stats_chunks = Layout::default()
@@ -516,9 +1427,14 @@ fn draw_network<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect)
         .split(chunks[1]);
 
     let rx_text = format!(
-        "Network Receive Statistics:\n\nTotal RX: {} KB\nPackets: {}\nErrors: 0\nDropped: 0",
+        "Rate: {}\nTotal RX: {} KB\nPackets: {}\nErrors: {} ({:.1}/s)\nDropped: {} ({:.1}/s)",
+        format_rate(system.network_rx_rate),
         system.network_rx / 1024,
-        system.network_interfaces.iter().map(|i| i.rx_packets).sum::<u64>()
+        system.network_interfaces.iter().map(|i| i.rx_packets).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.rx_errors).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.rx_error_rate).sum::<f64>(),
+        system.network_interfaces.iter().map(|i| i.rx_dropped).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.rx_drop_rate).sum::<f64>()
     );
 
     let rx_para = Paragraph::new(rx_text)
@@ -529,9 +1445,14 @@ fn draw_network<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect)
     f.render_widget(rx_para, net_stats_chunks[0]);
 
     let tx_text = format!(
-        "Network Transmit Statistics:\n\nTotal TX: {} KB\nPackets: {}\nErrors: 0\nDropped: 0",
+        "Rate: {}\nTotal TX: {} KB\nPackets: {}\nErrors: {} ({:.1}/s)\nDropped: {} ({:.1}/s)",
+        format_rate(system.network_tx_rate),
         system.network_tx / 1024,
-        system.network_interfaces.iter().map(|i| i.tx_packets).sum::<u64>()
+        system.network_interfaces.iter().map(|i| i.tx_packets).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.tx_errors).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.tx_error_rate).sum::<f64>(),
+        system.network_interfaces.iter().map(|i| i.tx_dropped).sum::<u64>(),
+        system.network_interfaces.iter().map(|i| i.tx_drop_rate).sum::<f64>()
     );
 
     let tx_para = Paragraph::new(tx_text)
@@ -540,37 +1461,349 @@ fn draw_network<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect)
         .wrap(Wrap { trim: true });
 
     f.render_widget(tx_para, net_stats_chunks[1]);
+
+    draw_interface_throughput(f, system, app.history_window, chunks[2]);
 }
 
-fn draw_services<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
-    let header_cells = ["Service", "Status", "Uptime", "Description"]
+// Whitespace-separated terms that must all match: a port number, a TCP/UDP state or a protocol
+fn connection_matches(connection: &Connection, filter: &str) -> bool {
+    filter.split_whitespace().all(|term| match term.parse::<u16>() {
+        Ok(port) => connection.local_port == Some(port) || connection.remote_port == Some(port),
+        Err(_) => {
+            let term = term.to_lowercase();
+            connection.proto.starts_with(&term) || connection.state.to_lowercase().starts_with(&term)
+        }
+    })
+}
+
+fn draw_connections<B: Backend>(
+    f: &mut Frame<B>,
+    system: &SystemState,
+    filter: &str,
+    prompt: Option<&str>,
+    area: Rect,
+) {
+    let constraints = if prompt.is_some() {
+        vec![Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)]
+    } else {
+        vec![Constraint::Length(3), Constraint::Min(0)]
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+
+    // Per-state summary over all sockets, independent of the filter
+    let mut tcp_states: Vec<(&str, usize)> = Vec::new();
+    for connection in system.connections.iter().filter(|c| c.proto.starts_with("tcp")) {
+        match tcp_states.iter_mut().find(|(state, _)| *state == connection.state) {
+            Some((_, count)) => *count += 1,
+            None => tcp_states.push((&connection.state, 1)),
+        }
+    }
+    tcp_states.sort_by_key(|(state, count)| (std::cmp::Reverse(*count), *state));
+    let udp = system.connections.iter().filter(|c| c.proto.starts_with("udp")).count();
+    let unix = system.connections.iter().filter(|c| c.proto.starts_with("unix")).count();
+
+    let mut summary = Vec::new();
+    for (state, count) in tcp_states {
+        summary.push(Span::styled(format!("{} ", state), Style::default().fg(Color::Yellow)));
+        summary.push(Span::styled(format!("{}   ", count), Style::default().fg(Color::Green)));
+    }
+    summary.push(Span::styled("UDP ", Style::default().fg(Color::Yellow)));
+    summary.push(Span::styled(format!("{}   ", udp), Style::default().fg(Color::Green)));
+    summary.push(Span::styled("Unix ", Style::default().fg(Color::Yellow)));
+    summary.push(Span::styled(format!("{}", unix), Style::default().fg(Color::Green)));
+
+    let summary = Paragraph::new(Spans::from(summary))
+        .block(Block::default().borders(Borders::ALL).title("TCP States").style(Style::default().fg(Color::Green)));
+    f.render_widget(summary, chunks[0]);
+
+    // Connection table
+    let mut connections: Vec<&Connection> = system
+        .connections
+        .iter()
+        .filter(|connection| connection_matches(connection, filter))
+        .collect();
+    connections.sort_by(|a, b| (&a.proto, a.local_port, &a.local).cmp(&(&b.proto, b.local_port, &b.local)));
+
+    let header_cells = ["Proto", "Local Address", "Remote Address", "State", "PID", "Process"]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let rows = system.services.iter().map(|service| {
+    let rows = connections.iter().map(|connection| {
+        let state_color = match connection.state.as_str() {
+            "ESTABLISHED" | "LISTEN" | "CONNECTED" => Color::Green,
+            "CLOSE_WAIT" | "UNKNOWN" => Color::Red,
+            _ => Color::Yellow,
+        };
+        let cells = vec![
+            Cell::from(connection.proto.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(connection.local.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(connection.remote.clone()).style(Style::default().fg(Color::Green)),
+            Cell::from(connection.state.clone()).style(Style::default().fg(state_color)),
+            Cell::from(connection.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()))
+                .style(Style::default().fg(Color::Green)),
+            Cell::from(connection.process.clone()).style(Style::default().fg(Color::Green)),
+        ];
+        Row::new(cells).height(1)
+    });
+
+    let title = if filter.is_empty() {
+        format!("Connections ({}) [/=Filter v=Interfaces]", connections.len())
+    } else {
+        format!(
+            "Connections ({} of {}) filter: {} [/=Filter v=Interfaces]",
+            connections.len(),
+            system.connections.len(),
+            filter
+        )
+    };
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .widths(&[
+            Constraint::Length(12),
+            Constraint::Length(30),
+            Constraint::Length(30),
+            Constraint::Length(12),
+            Constraint::Length(8),
+            Constraint::Min(10),
+        ]);
+    f.render_widget(table, chunks[1]);
+
+    if let Some(text) = prompt {
+        let input = Paragraph::new(format!("{}_", text))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Filter: port, state or protocol [Enter=Apply Esc=Cancel]")
+                    .style(Style::default().fg(Color::Yellow)),
+            )
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(input, chunks[2]);
+    }
+}
+
+fn format_rate(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1024.0 * 1024.0 {
+        format!("{:.1} MB/s", bytes_per_sec / (1024.0 * 1024.0))
+    } else if bytes_per_sec >= 1024.0 {
+        format!("{:.1} KB/s", bytes_per_sec / 1024.0)
+    } else {
+        format!("{:.0} B/s", bytes_per_sec)
+    }
+}
+
+// One RX/TX chart per interface, busiest first, as many as fit side by side
+fn draw_interface_throughput<B: Backend>(f: &mut Frame<B>, system: &SystemState, history_window: usize, area: Rect) {
+    let (window_label, window) = HISTORY_WINDOWS[history_window];
+    let tier = metrics::tier_for(window);
+    let now = Local::now().timestamp();
+
+    let mut interfaces: Vec<&NetworkInterface> = system.network_interfaces.iter().collect();
+    interfaces.sort_by_key(|i| std::cmp::Reverse(i.rx_bytes + i.tx_bytes));
+    let count = interfaces.len().min((area.width / 40).max(1) as usize);
+    if count == 0 {
+        return;
+    }
+
+    let chart_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, count as u32); count])
+        .split(area);
+
+    for (interface, chunk) in interfaces.iter().zip(chart_chunks.iter()) {
+        let points = chunk.width.saturating_sub(10) as usize * 2;
+        let rx = system.metrics.range(&format!("net.{}.rx_rate", interface.name), tier, now, window);
+        let tx = system.metrics.range(&format!("net.{}.tx_rate", interface.name), tier, now, window);
+        let peak = |samples: &[metrics::Sample]| samples.iter().fold(0.0_f64, |a, s| a.max(s.max));
+        let avg = |samples: &[metrics::Sample]| samples.iter().map(|s| s.avg).sum::<f64>() / samples.len().max(1) as f64;
+
+        let series = [
+            ChartSeries {
+                name: "RX",
+                color: Color::Green,
+                points: metrics::downsample(&rx, points).iter().map(|s| (s.timestamp as f64, s.avg / 1024.0)).collect(),
+            },
+            ChartSeries {
+                name: "TX",
+                color: Color::Yellow,
+                points: metrics::downsample(&tx, points).iter().map(|s| (s.timestamp as f64, s.avg / 1024.0)).collect(),
+            },
+        ];
+        let y_max = (peak(&rx).max(peak(&tx)) / 1024.0 * 1.1).max(1.0);
+        let title = format!(
+            "{} [{}] peak {}/{} avg {}/{}",
+            interface.name,
+            window_label,
+            format_rate(peak(&rx)),
+            format_rate(peak(&tx)),
+            format_rate(avg(&rx)),
+            format_rate(avg(&tx))
+        );
+        let time = TimeWindow { now, span: window, cursor: None };
+        draw_history_chart(f, *chunk, &title, "K", &series, y_max, &time);
+    }
+}
+
+fn draw_services<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let services = &app.system.services;
+    let affected = services::failed_requirements(services);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(12)].as_ref())
+        .split(area);
+
+    let header_cells = ["Service", "Status", "State", "PID", "Memory", "Restarts", "Uptime", "Impact", "Description"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = services.iter().map(|service| {
+        let impact = affected.get(&service.name);
+        let status_color = match service.status.as_str() {
+            "RUNNING" if impact.is_some() => Color::Yellow,
+            "RUNNING" => Color::Green,
+            "STARTING" => Color::Yellow,
+            _ => Color::Red,
+        };
         let cells = vec![
             Cell::from(service.name.clone()).style(Style::default().fg(Color::Green)),
-            Cell::from(service.status.clone()).style(Style::default().fg(
-                if service.status == "RUNNING" { Color::Green } else { Color::Red }
-            )),
+            Cell::from(service.status.clone()).style(Style::default().fg(status_color)),
+            Cell::from(format!("{}/{}", service.active_state, service.sub_state)).style(Style::default().fg(Color::Green)),
+            Cell::from(service.main_pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()))
+                .style(Style::default().fg(Color::Green)),
+            Cell::from(service.memory_bytes.map(format_bytes).unwrap_or_else(|| "-".to_string()))
+                .style(Style::default().fg(Color::Green)),
+            Cell::from(service.restarts.to_string())
+                .style(Style::default().fg(if service.restarts > 0 { Color::Yellow } else { Color::Green })),
             Cell::from(service.uptime.clone()).style(Style::default().fg(Color::Green)),
+            match impact {
+                Some(failed) => Cell::from(format!("needs {}", failed.join(", "))).style(Style::default().fg(Color::Yellow)),
+                None => Cell::from("-").style(Style::default().fg(Color::Green)),
+            },
             Cell::from(service.description.clone()).style(Style::default().fg(Color::Green)),
         ];
         Row::new(cells).height(1)
     });
 
+    let title = format!(
+        "Service Manager ({}, {} services, {} affected by failures) [↑/↓=Select s=Start x=Stop r=Restart]",
+        app.services.backend_name(),
+        services.len(),
+        affected.len()
+    );
     let table = Table::new(rows)
         .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Service Manager").style(Style::default().fg(Color::Green)))
+        .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
         .widths(&[
-            Constraint::Length(15),
-            Constraint::Length(10),
+            Constraint::Length(28),
+            Constraint::Length(9),
+            Constraint::Length(18),
+            Constraint::Length(8),
             Constraint::Length(10),
-            Constraint::Min(30),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(20),
+            Constraint::Min(20),
         ]);
 
-    f.render_widget(table, area);
+    let selected = app.selected_service.min(services.len().saturating_sub(1));
+    let mut state = TableState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(table, chunks[0], &mut state);
+
+    // Requirement and dependent trees of the selected service
+    let tree_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[1]);
+    let label = |name: &str| match services.iter().find(|service| service.name == name) {
+        Some(service) if service.status != "RUNNING" => (service.status.clone(), Color::Red),
+        Some(service) if affected.contains_key(name) => (format!("{}, affected", service.status), Color::Yellow),
+        Some(service) => (service.status.clone(), Color::Green),
+        None => ("not loaded".to_string(), Color::DarkGray),
+    };
+    let requirements = |name: &str| {
+        services
+            .iter()
+            .find(|service| service.name == name)
+            .map(|service| service.requires.clone())
+            .unwrap_or_default()
+    };
+    let dependents = |name: &str| services::dependents(services, name).into_iter().map(String::from).collect();
+    let trees: [(TreeChildren, &str); 2] = [(&requirements, "Requires"), (&dependents, "Required By")];
+    for ((children, title), chunk) in trees.into_iter().zip(tree_chunks) {
+        let lines = match services.get(selected) {
+            Some(service) => DependencyTree { children, label: &label }.lines(&service.name),
+            None => Vec::new(),
+        };
+        let tree = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title).style(Style::default().fg(Color::Green)));
+        f.render_widget(tree, chunk);
+    }
+}
+
+// Child nodes of a dependency tree node
+pub type TreeChildren<'a> = &'a dyn Fn(&str) -> Vec<String>;
+
+// ASCII tree of dependencies (or dependents) with a status label per node
+pub struct DependencyTree<'a> {
+    pub children: TreeChildren<'a>,
+    pub label: &'a dyn Fn(&str) -> (String, Color),
+}
+
+impl DependencyTree<'_> {
+    pub fn lines(&self, root: &str) -> Vec<Spans<'static>> {
+        let mut lines = Vec::new();
+        self.push(&mut lines, root, "", "", &mut Vec::new(), &mut HashSet::new());
+        lines
+    }
+
+    // Cycles are cut where a node repeats on the current path; nodes already expanded elsewhere are not repeated
+    fn push(
+        &self,
+        lines: &mut Vec<Spans<'static>>,
+        name: &str,
+        branch: &str,
+        indent: &str,
+        path: &mut Vec<String>,
+        expanded: &mut HashSet<String>,
+    ) {
+        let (label, color) = (self.label)(name);
+        let cycle = path.iter().any(|node| node == name);
+        let repeated = !cycle && expanded.contains(name);
+        lines.push(Spans::from(vec![
+            Span::styled(format!("{}{}", indent, branch), Style::default().fg(Color::DarkGray)),
+            Span::styled(name.to_string(), Style::default().fg(Color::Green)),
+            Span::styled(format!(" [{}]", label), Style::default().fg(color)),
+            match (cycle, repeated) {
+                (true, _) => Span::styled(" (cycle)", Style::default().fg(Color::Red)),
+                (_, true) => Span::styled(" (see above)", Style::default().fg(Color::DarkGray)),
+                _ => Span::raw(""),
+            },
+        ]));
+        if cycle || repeated {
+            return;
+        }
+
+        let children = (self.children)(name);
+        let indent = match branch {
+            "" => String::new(),
+            "└── " => format!("{}    ", indent),
+            _ => format!("{}│   ", indent),
+        };
+        path.push(name.to_string());
+        expanded.insert(name.to_string());
+        for (i, child) in children.iter().enumerate() {
+            let branch = if i + 1 == children.len() { "└── " } else { "├── " };
+            self.push(lines, child, branch, &indent, path, expanded);
+        }
+        path.pop();
+    }
 }
 
 fn draw_logs<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
@@ -615,41 +1848,182 @@ fn draw_logs<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
     f.render_widget(list, area);
 }
 
-fn draw_performance<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+struct TimeWindow {
+    now: i64,
+    span: i64,
+    cursor: Option<i64>,
+}
+
+struct ChartSeries {
+    name: &'static str,
+    color: Color,
+    points: Vec<(f64, f64)>,
+}
+
+fn history_points(system: &SystemState, metric: &str, tier: usize, now: i64, window: i64, points: usize) -> Vec<(f64, f64)> {
+    let samples = system.metrics.range(metric, tier, now, window);
+    metrics::downsample(&samples, points)
+        .iter()
+        .map(|s| (s.timestamp as f64, s.avg))
+        .collect()
+}
+
+fn scaled(points: Vec<(f64, f64)>, scale: f64) -> Vec<(f64, f64)> {
+    points.into_iter().map(|(t, v)| (t, v / scale)).collect()
+}
+
+fn value_at(points: &[(f64, f64)], timestamp: f64) -> Option<f64> {
+    points
+        .iter()
+        .min_by(|a, b| (a.0 - timestamp).abs().total_cmp(&(b.0 - timestamp).abs()))
+        .map(|p| p.1)
+}
+
+fn draw_history_chart<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    title: &str,
+    unit: &str,
+    series: &[ChartSeries],
+    y_max: f64,
+    time: &TimeWindow,
+) {
+    let x_bounds = [(time.now - time.span) as f64, time.now as f64];
+    let cursor = time.cursor;
+    let time_format = if time.span > 24 * 60 * 60 { "%m-%d %H:%M" } else { "%H:%M:%S" };
+    let time_label = |ts: f64| {
+        chrono::DateTime::from_timestamp(ts as i64, 0)
+            .map(|t| t.with_timezone(&Local).format(time_format).to_string())
+            .unwrap_or_default()
+    };
+
+    let mut title_spans = vec![Span::raw(format!("{} ", title))];
+    for s in series {
+        title_spans.push(Span::styled(format!("━ {} ", s.name), Style::default().fg(s.color)));
+    }
+    if let Some(cursor) = cursor {
+        title_spans.push(Span::styled(format!("│ {} ", time_label(cursor as f64)), Style::default().fg(Color::Yellow)));
+        for s in series {
+            if let Some(value) = value_at(&s.points, cursor as f64) {
+                title_spans.push(Span::styled(format!("{} {:.1}{} ", s.name, value, unit), Style::default().fg(s.color)));
+            }
+        }
+    }
+
+    let cursor_line = cursor.map(|c| vec![(c as f64, 0.0), (c as f64, y_max)]);
+    let mut datasets: Vec<Dataset> = series
+        .iter()
+        .map(|s| {
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(s.color))
+                .data(&s.points)
+        })
+        .collect();
+    if let Some(line) = &cursor_line {
+        datasets.push(
+            Dataset::default()
+                .marker(symbols::Marker::Dot)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Yellow))
+                .data(line),
+        );
+    }
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(Spans::from(title_spans)).style(Style::default().fg(Color::Green)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds(x_bounds)
+                .labels(vec![
+                    Span::raw(time_label(x_bounds[0])),
+                    Span::raw(time_label((x_bounds[0] + x_bounds[1]) / 2.0)),
+                    Span::raw(time_label(x_bounds[1])),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, y_max])
+                .labels(vec![
+                    Span::raw(format!("0{}", unit)),
+                    Span::raw(format!("{:.1}{}", y_max / 2.0, unit)),
+                    Span::raw(format!("{:.1}{}", y_max, unit)),
+                ]),
+        );
+
+    f.render_widget(chart, area);
+}
+
+fn draw_performance<B: Backend>(f: &mut Frame<B>, system: &SystemState, history_window: usize, cursor: Option<i64>, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Length(8), Constraint::Min(0)].as_ref())
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Length(11),
+        ].as_ref())
         .split(area);
 
-    // CPU sparkline
-    let cpu_data: Vec<u64> = system.cpu_history.iter().map(|&x| x as u64).collect();
-    let cpu_sparkline = Sparkline::default()
-        .block(Block::default().borders(Borders::ALL).title("CPU Usage History").style(Style::default().fg(Color::Green)))
-        .data(&cpu_data)
-        .style(Style::default().fg(Color::Red));
-
-    f.render_widget(cpu_sparkline, chunks[0]);
+    let (window_label, window) = HISTORY_WINDOWS[history_window];
+    let tier = metrics::tier_for(window);
+    let now = Local::now().timestamp();
+    let points = area.width.saturating_sub(10) as usize * 2;
+    let cpu_samples = system.metrics.range("cpu.usage", tier, now, window);
+    let history = |metric: &str| history_points(system, metric, tier, now, window, points);
+    let time = TimeWindow { now, span: window, cursor };
+    let chart_title = |name: &str| {
+        format!("{} [last {} @ {}]", name, window_label, RESOLUTIONS[tier].name)
+    };
+
+    // CPU chart
+    let cpu_series = [
+        ChartSeries { name: "total", color: Color::Red, points: history("cpu.usage") },
+        ChartSeries { name: "user", color: Color::Green, points: history("cpu.user") },
+        ChartSeries { name: "system", color: Color::Cyan, points: history("cpu.system") },
+    ];
+    draw_history_chart(f, chunks[0], &chart_title("CPU"), "%", &cpu_series, 100.0, &time);
 
-    // Memory sparkline  
-    let memory_data: Vec<u64> = system.memory_history.iter().map(|&x| (x * 100.0) as u64).collect();
-    let memory_sparkline = Sparkline::default()
-        .block(Block::default().borders(Borders::ALL).title("Memory Usage History").style(Style::default().fg(Color::Green)))
-        .data(&memory_data)
-        .style(Style::default().fg(Color::Red));
+    // Memory chart
+    let memory_series = [
+        ChartSeries { name: "used", color: Color::Red, points: history("memory.used_gb") },
+        ChartSeries { name: "swap", color: Color::Magenta, points: history("swap.used_gb") },
+    ];
+    let memory_max = system.memory_total.max(system.swap_total) as f64;
+    draw_history_chart(f, chunks[1], &chart_title("Memory"), "G", &memory_series, memory_max, &time);
 
-    f.render_widget(memory_sparkline, chunks[1]);
+    // Network chart
+    let network_series = [
+        ChartSeries { name: "RX", color: Color::Green, points: scaled(history("net.rx_rate"), 1024.0) },
+        ChartSeries { name: "TX", color: Color::Yellow, points: scaled(history("net.tx_rate"), 1024.0) },
+    ];
+    let network_max = network_series
+        .iter()
+        .flat_map(|s| s.points.iter().map(|p| p.1))
+        .fold(1.0_f64, f64::max)
+        * 1.1;
+    draw_history_chart(f, chunks[2], &chart_title("Network KB/s"), "K", &network_series, network_max, &time);
 
     // Performance stats
     let perf_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(33), Constraint::Percentage(33), Constraint::Percentage(34)].as_ref())
-        .split(chunks[2]);
+        .split(chunks[3]);
 
     let cpu_text = format!(
-        "CPU Performance:\n\nCurrent: {:.1}%\nAverage: {:.1}%\nPeak: {:.1}%\nCores: 4\nThreads: 8",
+        "CPU Performance:\n\nCurrent: {:.1}%\nAverage: {:.1}%\nPeak: {:.1}%\nLogical CPUs: {}\nuser {:.1}% sys {:.1}% iowait {:.1}%\nirq {:.1}% steal {:.1}%",
         system.cpu_usage,
-        system.cpu_history.iter().sum::<f32>() / system.cpu_history.len() as f32,
-        system.cpu_history.iter().fold(0.0_f32, |a, &b| a.max(b))
+        cpu_samples.iter().map(|s| s.avg).sum::<f64>() / cpu_samples.len().max(1) as f64,
+        cpu_samples.iter().fold(0.0_f64, |a, s| a.max(s.max)),
+        system.cpu_cores.len(),
+        system.cpu_breakdown.user,
+        system.cpu_breakdown.system,
+        system.cpu_breakdown.iowait,
+        system.cpu_breakdown.irq,
+        system.cpu_breakdown.steal
     );
 
     let cpu_para = Paragraph::new(cpu_text)
@@ -672,12 +2046,14 @@ fn draw_performance<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Re
     f.render_widget(memory_para, perf_chunks[1]);
 
     let io_text = format!(
-        "I/O Performance:\n\nIPC: {}/s\nDisk Read: {}/s\nDisk Write: {}/s\nNet RX: {} KB/s\nNet TX: {} KB/s",
+        "I/O Performance:\n\nIPC: {}/s\nDisk Read: {}/s\nDisk Write: {}/s\nNet RX: {}\nNet TX: {}\nNet Errors: {:.1}/s\nNet Drops: {:.1}/s",
         system.ipc_messages,
         system.fs_reads,
         system.fs_writes,
-        system.network_rx / 1024,
-        system.network_tx / 1024
+        format_rate(system.network_rx_rate),
+        format_rate(system.network_tx_rate),
+        system.network_error_rate,
+        system.network_drop_rate
     );
 
     let io_para = Paragraph::new(io_text)
@@ -687,7 +2063,7 @@ fn draw_performance<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Re
     f.render_widget(io_para, perf_chunks[2]);
 }
 
-fn draw_config<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
+fn draw_config<B: Backend>(f: &mut Frame<B>, system: &SystemState, probes: &ProbeRunner, area: Rect) {
     let boot_time = format!("  • Boot Time: {}", system.boot_time.format("%Y-%m-%d %H:%M:%S"));
     let uptime = format!("  • System Uptime: {}", system.get_uptime_string());
     let cpu_usage = format!("  • CPU Usage: {:.1}%", system.cpu_usage);
@@ -726,11 +2102,43 @@ fn draw_config<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
         "  • TCP Window: 64KB",
         "  • Max Connections: 1000",
         "",
+        "Command Probes:",
+        "",
         "Controls:",
-        "  [r] Refresh System  [n] Toggle Network  [o] Toggle Orbital",
+        "  [r] Refresh System  [n] Bring selected interface up/down (asks before down)  [o] Start/stop Orbital",
         "  [q] Quit Console    [ESC] Exit          [Tab] Next Tab",
+        "  [+/-] Zoom Performance history  [ and ] Move chart cursor  [c] Clear cursor",
+        "  [v] Network connections  [/] Filter connections by port, state or protocol",
+        "  [p] Show pseudo filesystems  [v] Toggle Disk I/O  [Enter] Explore disk usage of a mount",
+        "  [s] Start service  [x] Stop service  [r] Restart service (Services tab, asks to confirm)",
+        "  [/] Search packages  [Enter] Package dependencies and reverse dependencies  [l] List all",
+        "  [i/u/r/c] Queue install, upgrade, remove or cache clean  [a] Apply plan  [x] Clear queue",
+        "  [m] Check package repositories now",
+        "  [e] Enable/disable plugin  [l] Load  [u] Unload (Plugins tab)  [:] Run a plugin command",
     ];
 
+    let mut probe_lines: Vec<String> = probes
+        .status
+        .iter()
+        .map(|probe| {
+            let result = match (&probe.last_error, probe.last_value) {
+                (Some(err), _) => format!("FAILING: {}", err),
+                (None, Some(value)) => format!("{}", value),
+                (None, None) => "pending".to_string(),
+            };
+            format!(
+                "  • probe.{} every {:?} (timeout {:?}): {} ({} runs, {} failed)",
+                probe.name, probe.interval, probe.timeout, result, probe.runs, probe.failures
+            )
+        })
+        .collect();
+    if probe_lines.is_empty() {
+        probe_lines.push("  • None configured ([[probes]] in config.toml)".to_string());
+    }
+    let mut config_lines = config_lines;
+    let at = config_lines.iter().position(|line| *line == "Command Probes:").map_or(config_lines.len(), |i| i + 1);
+    config_lines.splice(at..at, probe_lines.iter().map(String::as_str));
+
     let items: Vec<ListItem> = config_lines
         .iter()
         .map(|&line| {
@@ -756,7 +2164,17 @@ fn draw_config<B: Backend>(f: &mut Frame<B>, system: &SystemState, area: Rect) {
     f.render_widget(list, area);
 }
 
-fn draw_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
+fn draw_footer<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let monitor = &app.monitor;
+    let overhead = match monitor.usage {
+        Some(usage) => format!(
+            "{:.1}% CPU • {} RSS • {}/frame [F2]",
+            monitor.cpu_percent,
+            format_bytes(usage.rss_bytes),
+            millis(monitor.last_frame)
+        ),
+        None => format!("{}/frame [F2]", millis(monitor.last_frame)),
+    };
     let footer_text = vec![
         Spans::from(vec![
             Span::styled("Redox OS Console v2.0", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
@@ -764,6 +2182,8 @@ fn draw_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
             Span::styled("Built with Rust", Style::default().fg(Color::Green)),
             Span::styled(" | ", Style::default().fg(Color::Green)),
             Span::styled("Memory Safe • Concurrent • Fast", Style::default().fg(Color::Red)),
+            Span::styled(" | ", Style::default().fg(Color::Green)),
+            Span::styled(overhead, Style::default().fg(Color::Yellow)),
         ])
     ];
 
@@ -772,4 +2192,157 @@ fn draw_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
         .alignment(Alignment::Center);
 
     f.render_widget(footer, area);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{CpuCore, Process, ServiceStatus};
+    use std::fs;
+    use std::process;
+    use std::time::Instant;
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+
+    // One frame at the default 30 fps; debug builds draw the heaviest views about 3x slower
+    const FRAME_BUDGET: Duration =
+        if cfg!(debug_assertions) { Duration::from_millis(100) } else { Duration::from_millis(33) };
+    const FRAMES: u32 = 5;
+
+    // Empty plugin and dashboard directories, no service, package or repository data, no alert
+    // sinks and link changes only logged, so tests never touch the machine's own setup
+    fn test_config() -> Config {
+        let empty = std::env::temp_dir().join(format!("redox-console-ui-{}-empty", process::id()));
+        fs::create_dir_all(&empty).unwrap();
+        let mut config = Config::default();
+        config.alerts.sinks = Vec::new();
+        config.history.enabled = false;
+        config.network.dry_run = true;
+        config.services.backend = "supervisor".to_string();
+        config.services.dir = Some(empty.clone());
+        config.packages.root = empty.clone();
+        config.plugins.dir = Some(empty.clone());
+        config.plugins.dashboards = Some(empty);
+        config
+    }
+
+    fn large_app() -> App {
+        let mut app = App::new(&test_config());
+        let system = &mut app.system;
+
+        let process = system.processes[0].clone();
+        system.processes = (0..5000)
+            .map(|pid| Process { pid, name: format!("worker-{}", pid), cpu: (pid % 100) as f32 / 10.0, ..process.clone() })
+            .collect();
+        system.connections = (0..2000)
+            .map(|i| Connection {
+                proto: if i % 2 == 0 { "tcp" } else { "udp" }.to_string(),
+                local: "10.0.0.1".to_string(),
+                local_port: Some(1024 + i as u16),
+                remote: format!("10.0.{}.{}", i / 250, i % 250),
+                remote_port: Some(443),
+                state: "ESTABLISHED".to_string(),
+                pid: Some(i),
+                process: format!("worker-{}", i),
+            })
+            .collect();
+        let filesystem = system.filesystems[0].clone();
+        system.filesystems = (0..500)
+            .map(|i| FileSystem { mount: format!("/mnt/volume{}", i), usage_percent: (i % 100) as u16, ..filesystem.clone() })
+            .collect();
+        let interface = system.network_interfaces[0].clone();
+        system.network_interfaces = (0..64)
+            .map(|i| NetworkInterface { name: format!("veth{}", i), rx_rate: i as f64 * 1024.0, ..interface.clone() })
+            .collect();
+        system.packages = (0..3000)
+            .map(|i| Package {
+                name: format!("package-{}", i),
+                version: "1.0.0".to_string(),
+                size_bytes: Some(i * 4096),
+                depends: (1..4).filter(|d| *d < i).map(|d| format!("package-{}", i - d)).collect(),
+                description: format!("Generated package {}", i),
+                update: (i % 7 == 0).then(|| "1.0.1".to_string()),
+            })
+            .collect();
+        let service = system.services[0].clone();
+        system.services = (0..300).map(|i| ServiceStatus { name: format!("service-{}", i), ..service.clone() }).collect();
+        system.cpu_cores = (0..64).map(|i| CpuCore { name: format!("cpu{}", i), user: 20.0, ..CpuCore::default() }).collect();
+        system.disks = (0..32).map(|i| DiskDevice { name: format!("nvme{}n1", i), util_percent: 40.0, ..DiskDevice::default() }).collect();
+        for i in 0..500 {
+            system.log("INFO", "Bench", &format!("log line {}", i));
+        }
+        let now = Local::now().timestamp();
+        for second in 0..3600 {
+            let value = (second % 100) as f64;
+            for metric in ["cpu.usage", "cpu.user", "cpu.system", "memory.used_gb", "net.rx_rate", "net.tx_rate"] {
+                system.metrics.record(metric, now - 3600 + second, value);
+            }
+        }
+        app
+    }
+
+    #[test]
+    fn draw_large_state_within_budget() {
+        let mut app = large_app();
+        app.show_monitor = true;
+        let mut terminal = Terminal::new(TestBackend::new(200, 60)).unwrap();
+
+        let mut views: Vec<(String, usize, bool)> =
+            app.tabs.titles.iter().enumerate().map(|(index, title)| (title.clone(), index, false)).collect();
+        views.push(("Connections".to_string(), 4, true));
+
+        let mut slow = Vec::new();
+        for (title, index, connections) in views {
+            app.tabs.index = index;
+            app.show_connections = connections;
+            // The fastest of a few frames, so a busy machine does not fail the test
+            let per_frame = (0..FRAMES)
+                .map(|_| {
+                    let started = Instant::now();
+                    terminal.draw(|f| draw(f, &mut app)).unwrap();
+                    started.elapsed()
+                })
+                .min()
+                .unwrap_or_default();
+            if per_frame > FRAME_BUDGET {
+                slow.push(format!("{} {:?}", title, per_frame));
+            }
+        }
+        assert!(slow.is_empty(), "views over the {:?} frame budget: {}", FRAME_BUDGET, slow.join(", "));
+    }
+
+    #[test]
+    fn interface_selection_follows_name() {
+        let mut app = App::new(&test_config());
+        let template = app.system.network_interfaces[0].clone();
+        let interface = |name: &str, status: &str| NetworkInterface { name: name.to_string(), status: status.to_string(), ..template.clone() };
+        app.system.network_interfaces = vec![interface("eth0", "UP"), interface("wlan0", "DOWN")];
+
+        app.select_interface(1);
+        assert_eq!(app.selected_interface.as_deref(), Some("wlan0"));
+
+        // A new interface sorting first must not move the selection to a different link
+        app.system.network_interfaces.insert(0, interface("docker0", "UP"));
+        assert_eq!(app.selected_interface_index(), 2);
+        app.select_interface(-1);
+        assert_eq!(app.selected_interface.as_deref(), Some("eth0"));
+
+        // A vanished interface falls back to the first row
+        app.system.network_interfaces.remove(1);
+        assert_eq!(app.selected_interface_index(), 0);
+    }
+
+    #[test]
+    fn link_down_asks_for_confirmation() {
+        let mut app = App::new(&test_config());
+        let template = app.system.network_interfaces[0].clone();
+        app.system.network_interfaces = vec![NetworkInterface { name: "eth0".to_string(), status: "UP".to_string(), ..template }];
+
+        app.toggle_selected_interface();
+        assert!(matches!(&app.confirm, Some(Confirm::LinkDown { interface }) if interface == "eth0"));
+
+        app.handle_confirm_key(KeyCode::Char('n'));
+        assert!(app.confirm.is_none());
+        assert_eq!(app.system.network_interfaces[0].status, "UP");
+    }
+}
@@ -0,0 +1,558 @@
+// WASM plugin runtime: sandboxed wasm32 modules that only see the narrow "console" host API
+//
+// Imports from "console" (strings are (ptr, len) in the module's exported memory):
+//   metric_get(name, len) -> f64        latest value of a metric, NaN when unknown
+//   metric_emit(name, len, value: f64)  recorded as plugin.<plugin>.<name>
+//   log(level, msg, len)                level 0 INFO, 1 WARN, 2 ERROR
+//   register_tab(title, len)            during init only
+//   draw_text(x, y, text, len, color)   during render only, relative to the panel
+//   table_header(cells, len)            tab-separated cells, during render only
+//   table_row(cells, len, color)        colors: 0 green, 1 yellow, 2 red, 3 cyan, 4 gray
+// Exports: memory, and optionally init(), collect() and render(tab, width, height).
+use crate::plugins::Plugin;
+use crate::system::SystemState;
+use anyhow::{anyhow, bail, Context};
+use chrono::Local;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget, Wrap},
+};
+use wasmi::core::TrapCode;
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+// Caps on what a single call can hand to the host, so a module cannot exhaust host memory
+const MAX_STRING: u32 = 1024;
+const MAX_EMITTED: usize = 256;
+const MAX_LOGS: usize = 32;
+const MAX_DRAW_OPS: usize = 512;
+const MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+#[derive(Default)]
+struct Panel {
+    texts: Vec<(u16, u16, String, Color)>,
+    header: Vec<String>,
+    rows: Vec<(Vec<String>, Color)>,
+}
+
+struct HostState {
+    limits: StoreLimits,
+    // Metric values as of the start of the call
+    metrics: HashMap<String, f64>,
+    emitted: Vec<(String, f64)>,
+    logs: Vec<(&'static str, String)>,
+    tabs: Vec<String>,
+    initializing: bool,
+    rendering: bool,
+    panel: Panel,
+}
+
+fn color(code: i32) -> Color {
+    match code {
+        1 => Color::Yellow,
+        2 => Color::Red,
+        3 => Color::Cyan,
+        4 => Color::Gray,
+        _ => Color::Green,
+    }
+}
+
+fn read_str(caller: &Caller<'_, HostState>, ptr: u32, len: u32) -> Result<String, wasmi::Error> {
+    if len > MAX_STRING {
+        return Err(wasmi::Error::new(format!("string of {} bytes exceeds {} byte limit", len, MAX_STRING)));
+    }
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("module exports no memory"))?;
+    let mut bytes = vec![0; len as usize];
+    memory
+        .read(caller, ptr as usize, &mut bytes)
+        .map_err(|_| wasmi::Error::new("string out of bounds"))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn draw_op(caller: &mut Caller<'_, HostState>, name: &str) -> Result<(), wasmi::Error> {
+    let state = caller.data();
+    if !state.rendering {
+        return Err(wasmi::Error::new(format!("{} called outside render", name)));
+    }
+    if state.panel.texts.len() + state.panel.rows.len() >= MAX_DRAW_OPS {
+        return Err(wasmi::Error::new(format!("more than {} draw calls", MAX_DRAW_OPS)));
+    }
+    Ok(())
+}
+
+fn host_api(engine: &Engine) -> anyhow::Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap("console", "metric_get", |caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+        let name = read_str(&caller, ptr, len)?;
+        Ok(caller.data().metrics.get(&name).copied().unwrap_or(f64::NAN))
+    })?;
+    linker.func_wrap("console", "metric_emit", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32, value: f64| {
+        let name = read_str(&caller, ptr, len)?;
+        let state = caller.data_mut();
+        if state.emitted.len() >= MAX_EMITTED {
+            return Err(wasmi::Error::new(format!("more than {} metrics in one call", MAX_EMITTED)));
+        }
+        state.emitted.push((name, value));
+        Ok(())
+    })?;
+    linker.func_wrap("console", "log", |mut caller: Caller<'_, HostState>, level: i32, ptr: u32, len: u32| {
+        let message = read_str(&caller, ptr, len)?;
+        let level = match level {
+            2 => "ERROR",
+            1 => "WARN",
+            _ => "INFO",
+        };
+        let state = caller.data_mut();
+        if state.logs.len() < MAX_LOGS {
+            state.logs.push((level, message));
+        }
+        Ok(())
+    })?;
+    linker.func_wrap("console", "register_tab", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+        let title = read_str(&caller, ptr, len)?;
+        let state = caller.data_mut();
+        if !state.initializing {
+            return Err(wasmi::Error::new("register_tab called outside init"));
+        }
+        if state.tabs.len() < 4 {
+            state.tabs.push(title);
+        }
+        Ok(())
+    })?;
+    linker.func_wrap(
+        "console",
+        "draw_text",
+        |mut caller: Caller<'_, HostState>, x: u32, y: u32, ptr: u32, len: u32, code: i32| {
+            draw_op(&mut caller, "draw_text")?;
+            let text = read_str(&caller, ptr, len)?;
+            let (x, y) = (x.min(u16::MAX as u32) as u16, y.min(u16::MAX as u32) as u16);
+            caller.data_mut().panel.texts.push((x, y, text, color(code)));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap("console", "table_header", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+        draw_op(&mut caller, "table_header")?;
+        let cells = read_str(&caller, ptr, len)?;
+        caller.data_mut().panel.header = cells.split('\t').map(str::to_string).collect();
+        Ok(())
+    })?;
+    linker.func_wrap("console", "table_row", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32, code: i32| {
+        draw_op(&mut caller, "table_row")?;
+        let cells = read_str(&caller, ptr, len)?;
+        caller.data_mut().panel.rows.push((cells.split('\t').map(str::to_string).collect(), color(code)));
+        Ok(())
+    })?;
+    Ok(linker)
+}
+
+pub struct WasmPlugin {
+    name: String,
+    fuel: u64,
+    store: Store<HostState>,
+    memory: Option<Memory>,
+    init: Option<TypedFunc<(), ()>>,
+    collect: Option<TypedFunc<(), ()>>,
+    render: Option<TypedFunc<(i32, i32, i32), ()>>,
+    error: Option<String>,
+}
+
+impl WasmPlugin {
+    pub fn open(path: &Path, fuel: u64, memory_limit: usize) -> anyhow::Result<WasmPlugin> {
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let wasm = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::instantiate(name, &wasm, fuel, memory_limit).with_context(|| path.display().to_string())
+    }
+
+    fn instantiate(name: String, wasm: &[u8], fuel: u64, memory_limit: usize) -> anyhow::Result<WasmPlugin> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(|e| anyhow!("{}", e))?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(memory_limit)
+            .memories(1)
+            .tables(1)
+            .table_elements(MAX_TABLE_ELEMENTS)
+            .instances(1)
+            .build();
+        let state = HostState {
+            limits,
+            metrics: HashMap::new(),
+            emitted: Vec::new(),
+            logs: Vec::new(),
+            tabs: Vec::new(),
+            initializing: false,
+            rendering: false,
+            panel: Panel::default(),
+        };
+        let mut store = Store::new(&engine, state);
+        store.limiter(|state| &mut state.limits);
+        // Data segments and the start function run on the same budget as a call
+        store.set_fuel(fuel).map_err(|e| anyhow!("{}", e))?;
+
+        let linker = host_api(&engine)?;
+        let instance: Instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| anyhow!("instantiating {}: {}", name, e))?;
+
+        let memory = instance.get_memory(&store, "memory");
+        let init = instance.get_typed_func(&store, "init").ok();
+        let collect = instance.get_typed_func(&store, "collect").ok();
+        let render = instance.get_typed_func(&store, "render").ok();
+        if collect.is_none() && render.is_none() {
+            bail!("{} exports neither collect nor render", name);
+        }
+        Ok(WasmPlugin { name, fuel, store, memory, init, collect, render, error: None })
+    }
+
+    // Runs one export on a fresh fuel budget with a snapshot of the current metrics
+    fn call<P: wasmi::WasmParams, R: wasmi::WasmResults>(
+        &mut self,
+        func: TypedFunc<P, R>,
+        params: P,
+        metrics: HashMap<String, f64>,
+    ) -> anyhow::Result<R> {
+        self.store.data_mut().metrics = metrics;
+        self.store.set_fuel(self.fuel).map_err(|e| anyhow!("{}", e))?;
+        func.call(&mut self.store, params).map_err(|err| match err.as_trap_code() {
+            Some(TrapCode::OutOfFuel) => anyhow!("fuel exhausted (limit {})", self.fuel),
+            _ => anyhow!("{}", err),
+        })
+    }
+
+    // Applies metrics and log lines the module produced during the last call
+    fn flush(&mut self, system: &mut SystemState) {
+        let now = Local::now().timestamp();
+        let state = self.store.data_mut();
+        for (metric, value) in state.emitted.drain(..) {
+            system.metrics.record(&format!("plugin.{}.{}", self.name, metric), now, value);
+        }
+        for (level, message) in state.logs.drain(..) {
+            system.log(level, "Plugins", &format!("{}: {}", self.name, message));
+        }
+    }
+}
+
+impl Plugin for WasmPlugin {
+    fn load(&mut self, system: &mut SystemState) -> anyhow::Result<()> {
+        if let Some(init) = self.init {
+            self.store.data_mut().initializing = true;
+            let result = self.call(init, (), HashMap::new());
+            self.store.data_mut().initializing = false;
+            self.flush(system);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, system: &mut SystemState) -> anyhow::Result<()> {
+        let collect = match self.collect {
+            Some(collect) => collect,
+            None => return Ok(()),
+        };
+        let result = self.call(collect, (), system.metrics.latest_values());
+        self.flush(system);
+        result
+    }
+
+    fn tabs(&self) -> Vec<String> {
+        self.store.data().tabs.clone()
+    }
+
+    fn render(&mut self, tab: &str, system: &SystemState, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{} ({})", tab, self.name))
+            .style(Style::default().fg(Color::Green));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let index = self.store.data().tabs.iter().position(|title| title == tab).unwrap_or(0) as i32;
+        if let Some(render) = self.render {
+            self.store.data_mut().panel = Panel::default();
+            self.store.data_mut().rendering = true;
+            let result = self.call(render, (index, inner.width as i32, inner.height as i32), system.metrics.latest_values());
+            self.store.data_mut().rendering = false;
+            self.error = result.err().map(|err| format!("render failed: {:#}", err));
+        }
+        if let Some(err) = &self.error {
+            Paragraph::new(Span::styled(err.clone(), Style::default().fg(Color::Red)))
+                .wrap(Wrap { trim: true })
+                .render(inner, buf);
+            return;
+        }
+
+        let panel = &self.store.data().panel;
+        let mut table_top = inner.y;
+        for (x, y, text, color) in &panel.texts {
+            if *x >= inner.width || *y >= inner.height {
+                continue;
+            }
+            let width = (inner.width - x) as usize;
+            buf.set_stringn(inner.x + x, inner.y + y, text, width, Style::default().fg(*color));
+            table_top = table_top.max(inner.y + y + 1);
+        }
+        if panel.header.is_empty() && panel.rows.is_empty() {
+            return;
+        }
+
+        let area = Rect::new(inner.x, table_top, inner.width, inner.bottom().saturating_sub(table_top));
+        let columns = panel.rows.iter().map(|(cells, _)| cells.len()).chain([panel.header.len()]).max().unwrap_or(1).max(1);
+        let widths = vec![Constraint::Ratio(1, columns as u32); columns];
+        let header = Row::new(panel.header.iter().map(|cell| {
+            Cell::from(cell.clone()).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        }));
+        let rows = panel
+            .rows
+            .iter()
+            .map(|(cells, color)| Row::new(cells.iter().map(|cell| Cell::from(cell.clone()))).style(Style::default().fg(*color)));
+        Table::new(rows).header(header).widths(&widths).render(area, buf);
+    }
+
+    fn memory_bytes(&self) -> Option<u64> {
+        self.memory.map(|memory| memory.data(&self.store).len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUEL: u64 = 1_000_000;
+    const PAGE: usize = 64 * 1024;
+    const I32: u8 = 0x7f;
+
+    fn uleb(mut value: u32, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    // i32.const with a small non-negative operand
+    fn i32_const(value: u8, out: &mut Vec<u8>) {
+        assert!(value < 0x40);
+        out.extend([0x41, value]);
+    }
+
+    fn call(index: u32, out: &mut Vec<u8>) {
+        out.push(0x10);
+        uleb(index, out);
+    }
+
+    fn name(text: &str, out: &mut Vec<u8>) {
+        uleb(text.len() as u32, out);
+        out.extend(text.as_bytes());
+    }
+
+    fn section(id: u8, count: usize, items: Vec<u8>, out: &mut Vec<u8>) {
+        let mut content = Vec::new();
+        uleb(count as u32, &mut content);
+        content.extend(items);
+        out.push(id);
+        uleb(content.len() as u32, out);
+        out.extend(content);
+    }
+
+    struct Func {
+        export: &'static str,
+        params: Vec<u8>,
+        body: Vec<u8>,
+    }
+
+    // Assembles a module with one (type, import) per "console" import, exported functions,
+    // a memory of `pages` pages holding `data` at offset 0, and an optional funcref table
+    fn assemble(imports: &[(&str, &[u8])], funcs: &[Func], pages: u32, data: &[u8], table: Option<u32>) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+
+        let mut types = Vec::new();
+        let signatures = imports.iter().map(|(_, params)| *params).chain(funcs.iter().map(|f| &f.params[..]));
+        for params in signatures.clone() {
+            types.push(0x60);
+            uleb(params.len() as u32, &mut types);
+            types.extend(params);
+            types.push(0);
+        }
+        section(1, signatures.count(), types, &mut wasm);
+
+        let mut entries = Vec::new();
+        for (index, (field, _)) in imports.iter().enumerate() {
+            name("console", &mut entries);
+            name(field, &mut entries);
+            entries.push(0);
+            uleb(index as u32, &mut entries);
+        }
+        section(2, imports.len(), entries, &mut wasm);
+
+        let mut indices = Vec::new();
+        for index in 0..funcs.len() {
+            uleb((imports.len() + index) as u32, &mut indices);
+        }
+        section(3, funcs.len(), indices, &mut wasm);
+
+        if let Some(elements) = table {
+            let mut tables = vec![0x70, 0];
+            uleb(elements, &mut tables);
+            section(4, 1, tables, &mut wasm);
+        }
+
+        let mut memory = vec![0];
+        uleb(pages, &mut memory);
+        section(5, 1, memory, &mut wasm);
+
+        let mut exports = Vec::new();
+        name("memory", &mut exports);
+        exports.extend([2, 0]);
+        for (index, func) in funcs.iter().enumerate() {
+            name(func.export, &mut exports);
+            exports.push(0);
+            uleb((imports.len() + index) as u32, &mut exports);
+        }
+        section(7, funcs.len() + 1, exports, &mut wasm);
+
+        let mut code = Vec::new();
+        for func in funcs {
+            let mut body = vec![0];
+            body.extend(&func.body);
+            body.push(0x0b);
+            uleb(body.len() as u32, &mut code);
+            code.extend(body);
+        }
+        section(10, funcs.len(), code, &mut wasm);
+
+        let mut segment = vec![0];
+        i32_const(0, &mut segment);
+        segment.push(0x0b);
+        uleb(data.len() as u32, &mut segment);
+        segment.extend(data);
+        section(11, 1, segment, &mut wasm);
+        wasm
+    }
+
+    fn func(export: &'static str, body: Vec<u8>) -> Func {
+        Func { export, params: Vec::new(), body }
+    }
+
+    fn instantiate(wasm: &[u8]) -> anyhow::Result<WasmPlugin> {
+        WasmPlugin::instantiate("test".to_string(), wasm, FUEL, 2 * PAGE)
+    }
+
+    // Calls a (ptr, len) import on the "Disk" string at offset 0
+    fn call_with_title(import: u32, len: u8) -> Vec<u8> {
+        let mut body = Vec::new();
+        i32_const(0, &mut body);
+        i32_const(len, &mut body);
+        call(import, &mut body);
+        body
+    }
+
+    #[test]
+    fn fuel_exhaustion_traps() {
+        // loop br 0 end
+        let wasm = assemble(&[], &[func("collect", vec![0x03, 0x40, 0x0c, 0x00, 0x0b])], 1, b"", None);
+        let mut plugin = WasmPlugin::instantiate("spin".to_string(), &wasm, 10_000, PAGE).unwrap();
+        let err = plugin.collect(&mut SystemState::new()).unwrap_err();
+        assert_eq!(err.to_string(), "fuel exhausted (limit 10000)");
+    }
+
+    #[test]
+    fn memory_is_limited() {
+        // Growing past the limit fails inside the module: memory.grow returns -1
+        let mut body = Vec::new();
+        i32_const(8, &mut body);
+        body.extend([0x40, 0x00, 0x1a]);
+        let mut plugin = instantiate(&assemble(&[], &[func("collect", body)], 1, b"", None)).unwrap();
+        plugin.collect(&mut SystemState::new()).unwrap();
+        assert_eq!(plugin.memory_bytes(), Some(PAGE as u64));
+
+        // A module asking for more up front is refused
+        assert!(instantiate(&assemble(&[], &[func("collect", Vec::new())], 4, b"", None)).is_err());
+        // So is a table larger than the element cap
+        assert!(instantiate(&assemble(&[], &[func("collect", Vec::new())], 1, b"", Some(MAX_TABLE_ELEMENTS + 1))).is_err());
+        assert!(instantiate(&assemble(&[], &[func("collect", Vec::new())], 1, b"", Some(16))).is_ok());
+    }
+
+    #[test]
+    fn register_tab_only_during_init() {
+        let wasm = assemble(
+            &[("register_tab", &[I32, I32])],
+            &[func("init", call_with_title(0, 4)), func("collect", call_with_title(0, 4))],
+            1,
+            b"Disk",
+            None,
+        );
+        let mut plugin = instantiate(&wasm).unwrap();
+        let mut system = SystemState::new();
+        plugin.load(&mut system).unwrap();
+        assert_eq!(plugin.tabs(), ["Disk"]);
+
+        let err = plugin.collect(&mut system).unwrap_err();
+        assert!(format!("{:#}", err).contains("register_tab called outside init"), "{:#}", err);
+        assert_eq!(plugin.tabs(), ["Disk"]);
+    }
+
+    #[test]
+    fn draw_calls_only_during_render() {
+        let mut body = Vec::new();
+        i32_const(0, &mut body);
+        i32_const(4, &mut body);
+        i32_const(0, &mut body);
+        call(0, &mut body);
+        let wasm = assemble(&[("table_row", &[I32, I32, I32])], &[func("collect", body)], 1, b"Disk", None);
+        let err = instantiate(&wasm).unwrap().collect(&mut SystemState::new()).unwrap_err();
+        assert!(format!("{:#}", err).contains("table_row called outside render"), "{:#}", err);
+    }
+
+    #[test]
+    fn long_strings_are_refused() {
+        // log(0, 0, MAX_STRING + 1)
+        let mut body = Vec::new();
+        i32_const(0, &mut body);
+        i32_const(0, &mut body);
+        body.push(0x41);
+        uleb(MAX_STRING + 1, &mut body);
+        call(0, &mut body);
+        let wasm = assemble(&[("log", &[I32, I32, I32])], &[func("collect", body)], 1, b"", None);
+        let mut system = SystemState::new();
+        let logs = system.logs.len();
+        let err = instantiate(&wasm).unwrap().collect(&mut system).unwrap_err();
+        assert!(format!("{:#}", err).contains("exceeds 1024 byte limit"), "{:#}", err);
+        assert_eq!(system.logs.len(), logs);
+    }
+
+    #[test]
+    fn draw_calls_are_capped() {
+        // MAX_DRAW_OPS + 1 calls to table_row(0, 4, 0)
+        let mut body = Vec::new();
+        for _ in 0..=MAX_DRAW_OPS {
+            i32_const(0, &mut body);
+            i32_const(4, &mut body);
+            i32_const(0, &mut body);
+            call(0, &mut body);
+        }
+        let render = Func { export: "render", params: vec![I32, I32, I32], body };
+        let wasm = assemble(&[("table_row", &[I32, I32, I32])], &[render], 1, b"Disk", None);
+        let mut plugin = instantiate(&wasm).unwrap();
+
+        let area = Rect::new(0, 0, 40, 10);
+        let mut buf = Buffer::empty(area);
+        plugin.render("test", &SystemState::new(), area, &mut buf);
+        assert_eq!(plugin.error.as_deref(), Some("render failed: more than 512 draw calls"));
+        assert_eq!(plugin.store.data().panel.rows.len(), MAX_DRAW_OPS);
+    }
+}